@@ -4,13 +4,14 @@
 //! formatter, and the future macro-compilation backend. Carries no resolved types or operator
 //! overloads: resolution and type/range validation are deferred to a later, separate phase.
 
+use cel_runtime::{CelNull, Quantifier};
 use std::any::Any;
 use std::ffi::CString;
 
 use proc_macro2::Span;
 
 use crate::op_table::OpLookup;
-use crate::parser_context::ParserContext;
+use crate::parser_context::{OperatorSpan, ParserContext};
 
 /// Source range of an AST node: start of its first token to end of its last.
 ///
@@ -89,15 +90,32 @@ pub enum Literal {
     CStr(CString),
     /// Unit (`()`).
     Unit,
+    /// `null`.
+    Null,
 }
 
-/// The two short-circuiting logical operators.
+/// The short-circuiting logical operators.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LogicalOp {
     /// `&&`.
     And,
     /// `||`.
     Or,
+    /// `?:`, the left operand unless it is falsy, else the right operand; see
+    /// [`cel_runtime::truthiness`].
+    Elvis,
+}
+
+impl LogicalOp {
+    /// Returns the operator's source spelling, e.g. `"&&"`.
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            LogicalOp::And => "&&",
+            LogicalOp::Or => "||",
+            LogicalOp::Elvis => "?:",
+        }
+    }
 }
 
 /// A parsed CEL expression with source spans on every node.
@@ -151,6 +169,26 @@ pub enum Expr {
         /// The span of the whole tuple, including its parentheses.
         span: ExprSpan,
     },
+    /// A list literal (`[a, b, ...]`).
+    List {
+        /// The element sub-expressions, in source order.
+        elements: Vec<Expr>,
+        /// The span of the whole list, including its brackets.
+        span: ExprSpan,
+    },
+    /// An `all`/`exists` comprehension (`list.all(x, predicate)`).
+    Comprehension {
+        /// Whether the predicate must hold for all elements or for any.
+        quantifier: Quantifier,
+        /// The list being iterated.
+        range: Box<Expr>,
+        /// The name each element is bound to within `predicate`.
+        variable: String,
+        /// The predicate, evaluated once per element.
+        predicate: Box<Expr>,
+        /// The span from the start of `range` through the closing `)`.
+        span: ExprSpan,
+    },
     /// A tuple index (`base.N`). Whether `base` is actually a tuple and `N` is in range is
     /// unchecked here — deferred to the type-checking phase (see the module doc comment).
     TupleIndex {
@@ -195,6 +233,8 @@ impl Expr {
             | Expr::Apply { span, .. }
             | Expr::Tuple { span, .. }
             | Expr::TupleIndex { span, .. }
+            | Expr::List { span, .. }
+            | Expr::Comprehension { span, .. }
             | Expr::If { span, .. }
             | Expr::Logical { span, .. } => *span,
         }
@@ -205,7 +245,7 @@ impl Expr {
 ///
 /// - Precondition: `T` is one of the concrete types `push_literal_token` (`lib.rs`) pushes:
 ///   the signed/unsigned integer widths, `f32`/`f64`, `bool`, `char`, `String`, `Vec<u8>`,
-///   `CString`, or `()`.
+///   `CString`, `()`, or [`CelNull`].
 fn to_literal<T: 'static + Clone>(value: &T) -> Literal {
     let any = value as &dyn Any;
     macro_rules! map {
@@ -225,6 +265,9 @@ fn to_literal<T: 'static + Clone>(value: &T) -> Literal {
     if any.is::<()>() {
         return Literal::Unit;
     }
+    if any.is::<CelNull>() {
+        return Literal::Null;
+    }
     unreachable!("push_literal called with an unsupported literal type")
 }
 
@@ -280,6 +323,9 @@ impl AstContext {
 }
 
 impl ParserContext for AstContext {
+    type Variable = ();
+    type Binding = Expr;
+
     fn new_context() -> Self {
         AstContext { values: Vec::new() }
     }
@@ -300,10 +346,12 @@ impl ParserContext for AstContext {
         _op_lookup: &OpLookup,
         name: &str,
         arity: usize,
-        start: Span,
-        end: Span,
+        span: OperatorSpan,
     ) -> crate::Result<()> {
-        let span = ExprSpan { start, end };
+        let span = ExprSpan {
+            start: span.start,
+            end: span.end,
+        };
         if arity == 0 {
             self.values.push(Expr::Ident {
                 name: name.to_string(),
@@ -338,6 +386,7 @@ impl ParserContext for AstContext {
         let op = match name {
             "||" => LogicalOp::Or,
             "&&" => LogicalOp::And,
+            "?:" => LogicalOp::Elvis,
             other => unreachable!("apply_logical called with unsupported operator `{other}`"),
         };
         let lhs = self.pop();
@@ -419,6 +468,81 @@ impl ParserContext for AstContext {
     fn current_stack_offset(&self) -> usize {
         self.values.len()
     }
+
+    fn push_list_element(
+        &mut self,
+        _op_lookup: &OpLookup,
+        first: bool,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()> {
+        let element = self.pop();
+        let mut elements = if first {
+            Vec::new()
+        } else {
+            match self.pop() {
+                Expr::List { elements, .. } => elements,
+                _ => unreachable!("push_list_element appends to the list below"),
+            }
+        };
+        elements.push(element);
+        self.values.push(Expr::List {
+            elements,
+            span: ExprSpan { start, end },
+        });
+        Ok(())
+    }
+
+    fn new_predicate_fragment(
+        &self,
+        _op_lookup: &OpLookup,
+        _span: Span,
+    ) -> crate::Result<(Self, ())> {
+        Ok((AstContext { values: Vec::new() }, ()))
+    }
+
+    fn push_predicate_variable(&mut self, _variable: &(), name: &str, span: Span) {
+        self.values.push(Expr::Ident {
+            name: name.to_string(),
+            span: ExprSpan::point(span),
+        });
+    }
+
+    fn apply_comprehension(
+        &mut self,
+        _op_lookup: &OpLookup,
+        quantifier: Quantifier,
+        variable: &str,
+        mut predicate: Self,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()> {
+        let range = self.pop();
+        debug_assert_eq!(
+            predicate.values.len(),
+            1,
+            "predicate fragment produces exactly one value"
+        );
+        let predicate = predicate.pop();
+        self.values.push(Expr::Comprehension {
+            quantifier,
+            range: Box::new(range),
+            variable: variable.to_string(),
+            predicate: Box::new(predicate),
+            span: ExprSpan { start, end },
+        });
+        Ok(())
+    }
+
+    fn compile_binding(value: Self) -> Expr {
+        value.into_expr()
+    }
+
+    fn push_binding(&mut self, binding: &Expr, _span: Span) {
+        // There is no node for a binding: each reference is a copy of the binding's tree, with
+        // the spans of its definition.
+        self.values.push(binding.clone());
+    }
 }
 
 #[cfg(test)]
@@ -511,14 +635,24 @@ mod tests {
             Literal::CStr(CString::new("c").unwrap())
         );
         assert_eq!(literal_of(()), Literal::Unit);
+        assert_eq!(literal_of(CelNull), Literal::Null);
     }
 
     #[test]
     fn apply_op_with_arity_zero_records_an_ident_node() {
         let mut ctx = AstContext::new_context();
         let lookup = OpLookup::new();
-        ctx.apply_op(&lookup, "x", 0, Span::call_site(), Span::call_site())
-            .unwrap();
+        ctx.apply_op(
+            &lookup,
+            "x",
+            0,
+            OperatorSpan {
+                operator: Span::call_site(),
+                start: Span::call_site(),
+                end: Span::call_site(),
+            },
+        )
+        .unwrap();
         assert!(matches!(ctx.into_expr(), Expr::Ident { name, .. } if name == "x"));
     }
 
@@ -526,11 +660,29 @@ mod tests {
     fn apply_op_with_the_call_operator_records_an_apply_node() {
         let mut ctx = AstContext::new_context();
         let lookup = OpLookup::new();
-        ctx.apply_op(&lookup, "x", 0, Span::call_site(), Span::call_site())
-            .unwrap(); // callee
+        ctx.apply_op(
+            &lookup,
+            "x",
+            0,
+            OperatorSpan {
+                operator: Span::call_site(),
+                start: Span::call_site(),
+                end: Span::call_site(),
+            },
+        )
+        .unwrap(); // callee
         ctx.push_literal(1i32, Span::call_site()); // arg
-        ctx.apply_op(&lookup, "()", 2, Span::call_site(), Span::call_site())
-            .unwrap();
+        ctx.apply_op(
+            &lookup,
+            "()",
+            2,
+            OperatorSpan {
+                operator: Span::call_site(),
+                start: Span::call_site(),
+                end: Span::call_site(),
+            },
+        )
+        .unwrap();
         match ctx.into_expr() {
             Expr::Apply { callee, args, .. } => {
                 assert!(matches!(*callee, Expr::Ident { ref name, .. } if name == "x"));
@@ -546,8 +698,17 @@ mod tests {
         let lookup = OpLookup::new();
         ctx.push_literal(1i32, Span::call_site());
         ctx.push_literal(2i32, Span::call_site());
-        ctx.apply_op(&lookup, "+", 2, Span::call_site(), Span::call_site())
-            .unwrap();
+        ctx.apply_op(
+            &lookup,
+            "+",
+            2,
+            OperatorSpan {
+                operator: Span::call_site(),
+                start: Span::call_site(),
+                end: Span::call_site(),
+            },
+        )
+        .unwrap();
         match ctx.into_expr() {
             Expr::Op { name, operands, .. } => {
                 assert_eq!(name, "+");
@@ -719,6 +880,30 @@ mod tests {
         assert_eq!(operands.len(), 1);
     }
 
+    #[test]
+    fn comprehension_over_list_literal_is_a_comprehension_node() {
+        let mut parser = Parser::<AstContext>::new(OpLookup::new());
+        let expr = parser.parse_str_ast("[a, 2].exists(x, x > a)").unwrap();
+        let Expr::Comprehension {
+            quantifier,
+            range,
+            variable,
+            predicate,
+            ..
+        } = expr
+        else {
+            panic!("expected Comprehension");
+        };
+        assert_eq!(quantifier, Quantifier::Exists);
+        assert_eq!(variable, "x");
+        assert!(matches!(*range, Expr::List { ref elements, .. } if elements.len() == 2));
+        let Expr::Op { operands, .. } = *predicate else {
+            panic!("expected Op");
+        };
+        assert!(matches!(&operands[0], Expr::Ident { name, .. } if name == "x"));
+        assert!(matches!(&operands[1], Expr::Ident { name, .. } if name == "a"));
+    }
+
     #[test]
     fn bare_identifier_is_an_ident_node() {
         let mut parser = Parser::<AstContext>::new(OpLookup::new());
@@ -897,6 +1082,16 @@ mod tests {
         assert_eq!(op, LogicalOp::And);
     }
 
+    #[test]
+    fn elvis_is_recorded_as_its_own_logical_op() {
+        let mut parser = Parser::<AstContext>::new(OpLookup::new());
+        let expr = parser.parse_str_ast("a ?: b").unwrap();
+        let Expr::Logical { op, .. } = expr else {
+            panic!("expected Logical");
+        };
+        assert_eq!(op, LogicalOp::Elvis);
+    }
+
     #[test]
     fn logical_mixes_with_comparison_at_the_right_precedence() {
         let mut parser = Parser::<AstContext>::new(OpLookup::new());
@@ -0,0 +1,307 @@
+//! Compiles a parsed [`Expr`] into a [`Bytecode`] program, the serializable alternative to the
+//! closures [`DynSegmentContext`](crate::parser_context::DynSegmentContext) emits.
+//!
+//! Bytecode covers the CEL built-in scalar operators on `bool`, `i32`, `i64`, `u32`, `u64`,
+//! `f64`, and `String` values, identifiers (loaded as named arguments), `if`/`else`, and the
+//! short-circuiting logical operators. Operators are not otherwise resolved through an
+//! [`OpLookup`], so casts, field access, calls, tuples, lists, and comprehensions are rejected,
+//! as is every other literal type, an operator the lookup could resolve to something other
+//! than a built-in, and any [`OverflowMode`] but [`OverflowMode::Checked`].
+//!
+//! Available with the `bytecode` feature.
+
+use cel_runtime::bytecode::{Bytecode, Instruction, Value};
+
+use crate::{Expr, ExprSpan, Literal, LogicalOp, OpLookup, OverflowMode, ParseError, Result};
+
+/// Compiles `expr` into a [`Bytecode`] program whose arguments are the identifiers `expr`
+/// references, by name, with the operators `op_lookup` resolves.
+///
+/// # Errors
+///
+/// Returns an error, spanning the offending node, if `expr` uses a literal type, operator, or
+/// construct bytecode doesn't support, or if `op_lookup` isn't set to
+/// [`OverflowMode::Checked`].
+///
+/// - Complexity: O(n) in the number of nodes in `expr`.
+///
+/// # Examples
+///
+/// ```rust
+/// use cel_parser::bytecode::compile;
+/// use cel_parser::{AstContext, OpLookup, Parser};
+/// use cel_runtime::bytecode::Value;
+/// use std::collections::HashMap;
+///
+/// let lookup = OpLookup::new();
+/// let expr = Parser::<AstContext>::new(OpLookup::new())
+///     .parse_str_ast("x * 2i64")
+///     .unwrap();
+/// let args = HashMap::from([("x".to_string(), Value::I64(21))]);
+/// let bytecode = compile(&expr, &lookup).unwrap();
+/// assert_eq!(bytecode.execute(&args).unwrap(), Value::I64(42));
+/// ```
+pub fn compile(expr: &Expr, op_lookup: &OpLookup) -> Result<Bytecode> {
+    let mode = op_lookup.overflow_mode();
+    if mode != OverflowMode::Checked {
+        return Err(unsupported(
+            &format!("overflow mode `{mode:?}`"),
+            expr.span(),
+        ));
+    }
+    let mut instructions = Vec::new();
+    emit(expr, op_lookup, &mut instructions)?;
+    Ok(Bytecode::new(instructions))
+}
+
+/// Appends the instructions evaluating `expr` to `out`.
+fn emit(expr: &Expr, op_lookup: &OpLookup, out: &mut Vec<Instruction>) -> Result<()> {
+    match expr {
+        Expr::Literal { value, span } => out.push(Instruction::Push(literal_value(value, *span)?)),
+        Expr::Ident { name, .. } => out.push(Instruction::Load(name.clone())),
+        Expr::Op {
+            name,
+            operands,
+            span,
+        } => {
+            let instruction = match (name.as_str(), operands.len()) {
+                ("-", 1) => Instruction::Neg,
+                ("!", 1) => Instruction::Not,
+                ("+", 2) => Instruction::Add,
+                ("-", 2) => Instruction::Sub,
+                ("*", 2) => Instruction::Mul,
+                ("/", 2) => Instruction::Div,
+                ("%", 2) => Instruction::Rem,
+                ("==", 2) => Instruction::Eq,
+                ("!=", 2) => Instruction::Ne,
+                ("<", 2) => Instruction::Lt,
+                ("<=", 2) => Instruction::Le,
+                (">", 2) => Instruction::Gt,
+                (">=", 2) => Instruction::Ge,
+                _ => return Err(unsupported(&format!("operator `{name}`"), *span)),
+            };
+            if !op_lookup.resolves_to_builtin(name, operands.len()) {
+                return Err(unsupported(
+                    &format!("operator `{name}` not resolved to a built-in"),
+                    *span,
+                ));
+            }
+            for operand in operands {
+                emit(operand, op_lookup, out)?;
+            }
+            out.push(instruction);
+        }
+        Expr::If {
+            cond,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            emit(cond, op_lookup, out)?;
+            let then_branch = fragment(then_branch, op_lookup)?;
+            let else_branch = fragment(else_branch, op_lookup)?;
+            out.push(Instruction::BranchIfFalse(then_branch.len() + 1));
+            out.extend(then_branch);
+            out.push(Instruction::Jump(else_branch.len()));
+            out.extend(else_branch);
+        }
+        Expr::Logical { op, lhs, rhs, .. } => {
+            emit(lhs, op_lookup, out)?;
+            let rhs = fragment(rhs, op_lookup)?;
+            match op {
+                LogicalOp::And => {
+                    out.push(Instruction::BranchIfFalse(rhs.len() + 1));
+                    out.extend(rhs);
+                    out.push(Instruction::Jump(1));
+                    out.push(Instruction::Push(Value::Bool(false)));
+                }
+                LogicalOp::Or => {
+                    out.push(Instruction::BranchIfFalse(2));
+                    out.push(Instruction::Push(Value::Bool(true)));
+                    out.push(Instruction::Jump(rhs.len()));
+                    out.extend(rhs);
+                }
+                LogicalOp::Elvis => {
+                    out.push(Instruction::JumpIfTruthy(rhs.len()));
+                    out.extend(rhs);
+                }
+            }
+        }
+        Expr::Apply { span, .. } => return Err(unsupported("a function call", *span)),
+        Expr::Tuple { span, .. } => return Err(unsupported("a tuple", *span)),
+        Expr::TupleIndex { span, .. } => return Err(unsupported("a tuple index", *span)),
+        Expr::List { span, .. } => return Err(unsupported("a list literal", *span)),
+        Expr::Comprehension { span, .. } => return Err(unsupported("a comprehension", *span)),
+    }
+    Ok(())
+}
+
+/// Returns the instructions evaluating `expr` on their own, for a branch to jump over.
+fn fragment(expr: &Expr, op_lookup: &OpLookup) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    emit(expr, op_lookup, &mut instructions)?;
+    Ok(instructions)
+}
+
+/// Converts `literal` to a [`Value`], or fails if bytecode has no value of its type.
+fn literal_value(literal: &Literal, span: ExprSpan) -> Result<Value> {
+    Ok(match literal {
+        Literal::Bool(value) => Value::Bool(*value),
+        Literal::I32(value) => Value::I32(*value),
+        Literal::I64(value) => Value::I64(*value),
+        Literal::U32(value) => Value::U32(*value),
+        Literal::U64(value) => Value::U64(*value),
+        Literal::F64(value) => Value::F64(*value),
+        Literal::Str(value) => Value::String(value.clone()),
+        _ => return Err(unsupported("this literal type", span)),
+    })
+}
+
+/// Returns the error for `what`, which bytecode doesn't support, at `span`.
+fn unsupported(what: &str, span: ExprSpan) -> ParseError {
+    ParseError::new_range(
+        format!("{what} is not supported by bytecode"),
+        span.start,
+        span.end,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AstContext, CELParser, Parser};
+    use std::collections::HashMap;
+
+    fn compile_str(source: &str) -> anyhow::Result<Bytecode> {
+        Parser::<AstContext>::new(OpLookup::new())
+            .parse_str_bytecode(source)
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    /// Converts a result of a segment compiled by [`CELParser`](crate::CELParser) to a [`Value`].
+    fn segment_value(value: Box<dyn std::any::Any>) -> Value {
+        macro_rules! downcast {
+            ($($t:ty => $variant:ident),*) => {
+                $(if let Some(value) = value.downcast_ref::<$t>() {
+                    return Value::$variant(value.clone());
+                })*
+            };
+        }
+        downcast!(
+            bool => Bool, i32 => I32, i64 => I64, u32 => U32, u64 => U64, f64 => F64,
+            String => String
+        );
+        panic!("bytecode has no value of the segment's result type");
+    }
+
+    #[test]
+    fn bytecode_matches_compiled_segment() -> anyhow::Result<()> {
+        for source in [
+            "3 + 2.5",
+            "2.5 * 4u32 - 1",
+            "7 / 2 + 7.0 / 2",
+            "7 % 3",
+            "2147483647 + 1",
+            "1 / 0",
+            r#""a" + "b" == "ab""#,
+            "0 ?: 5",
+            "3 ?: 5",
+            r#""" ?: "empty""#,
+            "0.0 ?: 1.5",
+            "false ?: true",
+            "true && 1 > 2 || 2.5 >= 2.5",
+            "if 1 < 2 { 10i64 } else { 20i64 }",
+            "-(2 * 3) + 1",
+        ] {
+            let expected = CELParser::new(OpLookup::new())
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?
+                .call0_any()
+                .map(segment_value);
+            let actual = compile_str(source).and_then(|bytecode| bytecode.execute(&HashMap::new()));
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => assert_eq!(actual, expected, "{source}"),
+                (Err(_), Err(_)) => {}
+                (expected, actual) => panic!("{source}: expected {expected:?}, got {actual:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_configurations_bytecode_cannot_follow_are_rejected() {
+        let mut parser = Parser::<AstContext>::new(OpLookup::new());
+        parser.set_overflow_mode(OverflowMode::Wrap);
+        assert_eq!(
+            parser.parse_str_bytecode("1 + 2").unwrap_err().to_string(),
+            "overflow mode `Wrap` is not supported by bytecode"
+        );
+
+        let mut lookup = OpLookup::new();
+        lookup.push_scope(|_, _, _, _| Ok(false));
+        assert_eq!(
+            Parser::<AstContext>::new(lookup)
+                .parse_str_bytecode("1 + 2")
+                .unwrap_err()
+                .to_string(),
+            "operator `+` not resolved to a built-in is not supported by bytecode"
+        );
+    }
+
+    #[test]
+    fn compiled_bytecode_round_trips_through_serde() -> anyhow::Result<()> {
+        let bytecode = compile_str("1 + 2")?;
+        assert_eq!(
+            bytecode.instructions(),
+            [
+                Instruction::Push(Value::I32(1)),
+                Instruction::Push(Value::I32(2)),
+                Instruction::Add
+            ]
+        );
+        let json = serde_json::to_string(&bytecode)?;
+        let restored: Bytecode = serde_json::from_str(&json)?;
+        assert_eq!(restored, bytecode);
+        assert_eq!(restored.execute(&HashMap::new())?, Value::I32(3));
+        Ok(())
+    }
+
+    #[test]
+    fn branches_and_logical_operators_short_circuit() -> anyhow::Result<()> {
+        let bytecode = compile_str("if x > 0 && 10 / x > 2 { \"big\" } else { \"small\" }")?;
+        for (x, expected) in [(0, "small"), (3, "big"), (5, "small")] {
+            let args = HashMap::from([("x".to_string(), Value::I32(x))]);
+            assert_eq!(
+                bytecode.execute(&args)?,
+                Value::String(expected.to_string()),
+                "x = {x}"
+            );
+        }
+
+        let bytecode = compile_str("x == 0 || 10 / x == 5")?;
+        for (x, expected) in [(0, true), (2, true), (3, false)] {
+            let args = HashMap::from([("x".to_string(), Value::I32(x))]);
+            assert_eq!(bytecode.execute(&args)?, Value::Bool(expected), "x = {x}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_constructs_are_rejected() {
+        for (source, message) in [
+            (
+                "1u8 + 2u8",
+                "this literal type is not supported by bytecode",
+            ),
+            ("1 as i64", "operator `as i64` is not supported by bytecode"),
+            ("f(1)", "a function call is not supported by bytecode"),
+            ("[1, 2]", "a list literal is not supported by bytecode"),
+        ] {
+            assert_eq!(
+                compile_str(source).unwrap_err().to_string(),
+                message,
+                "{source}"
+            );
+        }
+    }
+}
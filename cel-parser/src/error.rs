@@ -5,6 +5,7 @@
 //! location from a `proc_macro2::Span` when building errors in the parser.
 
 use annotate_snippets::{AnnotationKind, Group, Level, Renderer, Snippet};
+use cel_runtime::OpSpan;
 use proc_macro2::LineColumn;
 
 /// Source region as start/end line and column.
@@ -125,6 +126,21 @@ impl SourceSpan {
     }
 }
 
+impl From<SourceSpan> for OpSpan {
+    fn from(span: SourceSpan) -> Self {
+        OpSpan {
+            start: (span.start.line, span.start.column),
+            end: (span.end.line, span.end.column),
+        }
+    }
+}
+
+impl From<OpSpan> for SourceSpan {
+    fn from(span: OpSpan) -> Self {
+        SourceSpan::new(span.start.0, span.start.1, span.end.0, span.end.1)
+    }
+}
+
 /// A CEL parse error with a message and source location.
 ///
 /// Uses a [`SourceSpan`] (line/column only) so the error is `Send + Sync` and
@@ -579,13 +595,16 @@ impl FormatRustcStyle for anyhow::Error {
         renderer: &Renderer,
     ) -> String {
         if let Some(ctx) = self.downcast_ref::<SpanContext>() {
-            // `SpanContext` is the outermost layer in the anyhow chain, so `self.to_string()`
-            // would return the span location string ("at 1:5-1:6") rather than the op error
-            // message. `source()` recovers the actual error from the layer below.
+            // `SpanContext` is a layer in the anyhow chain, so `self.to_string()` may return the
+            // span location string ("at 1:5-1:6") rather than the op error message. The message
+            // is every other layer, outermost first, as `{:#}` would print them.
+            let span = ctx.to_string();
             let message = self
-                .source()
+                .chain()
                 .map(|e| e.to_string())
-                .unwrap_or_else(|| self.to_string());
+                .filter(|layer| *layer != span)
+                .collect::<Vec<_>>()
+                .join(": ");
             ctx.format_rustc_style(&message, source_code, filename, start_line, renderer)
         } else {
             self.to_string()
@@ -619,6 +638,15 @@ mod tests {
         assert_eq!(cel.span(), SourceSpan::from_proc_macro2(Span::call_site()));
     }
 
+    #[test]
+    fn renderer_selects_plain_or_colored_output() {
+        let e = CELError::new("unexpected token", SourceSpan::new(1, 6, 1, 8));
+        let plain = e.format_rustc_style("10 + 20 30", "test.cel", 1, &Renderer::plain());
+        let styled = e.format_rustc_style("10 + 20 30", "test.cel", 1, &Renderer::styled());
+        assert!(!plain.contains('\x1b'), "{plain:?}");
+        assert!(styled.contains('\x1b'), "{styled:?}");
+    }
+
     #[test]
     fn parse_error_format_rustc_style() {
         let source = "10 + 20 30";
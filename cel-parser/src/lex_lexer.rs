@@ -151,6 +151,8 @@ impl LexLexer {
                 | ('>', '=')
                 | ('<', '<')
                 | ('>', '>')
+                | ('*', '*')
+                | ('?', ':')
                 | ('-', '>')
                 | ('=', '>')
         )
@@ -207,7 +209,7 @@ impl HasSpan for Literal {
 ///
 /// Groups are flattened into OpenDelim and CloseDelim tokens, making parsing
 /// simpler by removing nesting from the token stream.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Token {
     /// A literal value (integer, string, boolean, or float) with eager discrimination.
     Literal(Literal),
@@ -469,6 +471,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn not_equal_is_one_token_and_bang_alone_is_not() {
+        for source in ["1 != 2", "1!=2"] {
+            let input = TokenStream::from_str(source).unwrap();
+            let tokens: Vec<_> = LexLexer::new(input.into_iter()).collect();
+            assert_eq!(tokens.len(), 3, "{source}");
+            assert!(matches!(&tokens[1], Token::Punct { op, .. } if op == "!="));
+        }
+
+        for (source, count) in [("!true", 2), ("!!true", 3)] {
+            let input = TokenStream::from_str(source).unwrap();
+            let tokens: Vec<_> = LexLexer::new(input.into_iter()).collect();
+            assert_eq!(tokens.len(), count, "{source}");
+            assert!(matches!(&tokens[count - 2], Token::Punct { op, .. } if op == "!"));
+        }
+    }
+
     #[test]
     fn test_group_flattening() {
         let input = TokenStream::from_str("(10 + 20)").unwrap();
@@ -553,6 +572,18 @@ mod tests {
         assert!(matches!(tokens[2], Token::Literal(Lit::Int(..))));
     }
 
+    #[test]
+    fn addition_lexes_to_literal_punct_literal_skipping_comments() {
+        for source in ["10 + 20", "10 /* lhs */ + // rhs\n 20"] {
+            let input = TokenStream::from_str(source).unwrap();
+            let tokens: Vec<_> = LexLexer::new(input.into_iter()).collect();
+            assert_eq!(tokens.len(), 3, "{source}");
+            assert!(matches!(&tokens[0], Token::Literal(Lit::Int(i)) if i.base10_digits() == "10"));
+            assert!(matches!(&tokens[1], Token::Punct { op, .. } if op == "+"));
+            assert!(matches!(&tokens[2], Token::Literal(Lit::Int(i)) if i.base10_digits() == "20"));
+        }
+    }
+
     #[test]
     fn arrow_is_two_char_punct() {
         let stream: TokenStream = "->".parse().unwrap();
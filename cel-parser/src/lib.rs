@@ -13,8 +13,10 @@
 //! # Grammar
 //!
 //! ```text
-//! expression = or_expression ?eos?.
-//! or_expression = and_expression { "||" and_expression }.
+//! expression = { let_binding } or_expression ?eos?.
+//! let_binding = "let" identifier "=" or_expression ";".
+//! or_expression = and_expression { ("||" | "?:") and_expression }
+//!     [ "?" or_expression ":" or_expression ].
 //! and_expression = comparison_expression { "&&" comparison_expression }.
 //! comparison_expression = bitwise_or_expression
 //!     [ ("==" | "!=" | "<" | ">" | "<=" | ">=") bitwise_or_expression ].
@@ -23,13 +25,22 @@
 //! bitwise_and_expression = bitwise_shift_expression { "&" bitwise_shift_expression }.
 //! bitwise_shift_expression = additive_expression { ("<<" | ">>") additive_expression }.
 //! additive_expression = multiplicative_expression { ("+" | "-") multiplicative_expression }.
-//! multiplicative_expression = unary_expression { ("*" | "/" | "%") unary_expression }.
-//! unary_expression = (("-" | "!") unary_expression) | postfix_expression.
-//! postfix_expression = primary_expression { "(" parameter_list ")" | "." unsuffixed_integer }.
-//! primary_expression = literal | identifier | tuple_or_group | if_expression.
+//! multiplicative_expression = power_expression { ("*" | "/" | "%") power_expression }.
+//! power_expression = cast_expression [ "**" power_expression ].
+//! cast_expression = unary_expression { "as" type_name }.
+//! unary_expression = (("-" | "!") unary_expression [ "**" power_expression ])
+//!     | postfix_expression.
+//! postfix_expression = primary_expression
+//!     { "(" argument_list ")" | "." unsuffixed_integer | "." identifier | comprehension }.
+//! comprehension = "." ("all" | "exists") "(" identifier "," or_expression ")".
+//! primary_expression = literal | "null" | has_macro | function_call | identifier
+//!     | tuple_or_group | list_literal | if_expression.
+//! has_macro = "has" "(" postfix_expression ")".
+//! function_call = function_name "(" argument_list ")".
 //! tuple_or_group = "(" [ or_expression ["," [ or_expression { "," or_expression } ]] ] ")".
+//! list_literal = "[" argument_list [ "," ] "]".
 //! if_expression = "if" or_expression "{" or_expression "}" [ "else" ( "{" or_expression "}" | if_expression ) ].
-//! parameter_list = [ or_expression { "," or_expression } ].
+//! argument_list = [ or_expression { "," or_expression } ].
 //! ```
 //!
 //! # Note
@@ -86,6 +97,8 @@
 //! ```
 
 pub mod ast;
+#[cfg(feature = "bytecode")]
+pub mod bytecode;
 mod error;
 pub mod lex_lexer;
 pub mod op_table;
@@ -94,14 +107,17 @@ pub mod ty;
 
 pub use ast::{AstContext, Expr, ExprSpan, Literal, LogicalOp};
 pub use error::{CELError, FormatRustcStyle, ParseError, SourceSpan, SpanContext};
-pub use op_table::{OpLookup, OperandTypes, builtin_operand_types};
-pub use parser_context::{DynSegmentContext, ParserContext};
+pub use op_table::{OpLookup, OperandTypes, OverflowMode, builtin_operand_types};
+pub use parser_context::{DynSegmentContext, OperatorSpan, ParserContext};
 pub use proc_macro2::LineColumn;
 pub use ty::Ty;
 
 use lex_lexer::{LexLexer, Literal as CelLiteral, Token, TokenStreamIter};
 
-use cel_runtime::DynSegment;
+use annotate_snippets::Renderer;
+#[cfg(feature = "bytecode")]
+use cel_runtime::bytecode::Bytecode;
+use cel_runtime::{CelNull, DynSegment, Quantifier};
 use proc_macro2::{Delimiter, Span, TokenStream};
 use std::iter::Peekable;
 use std::str::FromStr;
@@ -109,18 +125,89 @@ use std::str::FromStr;
 /// Parser result type.
 pub type Result<T> = std::result::Result<T, ParseError>;
 
-/// Pushes a literal value from `token` onto `output`.
+/// An integer literal token, as passed to an [`IntegerLiteralPolicy`].
+pub type IntegerLit = syn::LitInt;
+
+/// The type an integer literal is parsed as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerType {
+    /// `i8`.
+    I8,
+    /// `i16`.
+    I16,
+    /// `i32`.
+    I32,
+    /// `i64`.
+    I64,
+    /// `i128`.
+    I128,
+    /// `isize`.
+    Isize,
+    /// `u8`.
+    U8,
+    /// `u16`.
+    U16,
+    /// `u32`.
+    U32,
+    /// `u64`.
+    U64,
+    /// `u128`.
+    U128,
+    /// `usize`.
+    Usize,
+}
+
+impl IntegerType {
+    /// Returns the literal suffix naming this type, e.g. `"i64"`.
+    #[must_use]
+    pub fn suffix(self) -> &'static str {
+        match self {
+            IntegerType::I8 => "i8",
+            IntegerType::I16 => "i16",
+            IntegerType::I32 => "i32",
+            IntegerType::I64 => "i64",
+            IntegerType::I128 => "i128",
+            IntegerType::Isize => "isize",
+            IntegerType::U8 => "u8",
+            IntegerType::U16 => "u16",
+            IntegerType::U32 => "u32",
+            IntegerType::U64 => "u64",
+            IntegerType::U128 => "u128",
+            IntegerType::Usize => "usize",
+        }
+    }
+}
+
+/// Chooses the type of an unsuffixed integer literal; see
+/// [`Parser::set_integer_literal_policy`].
+pub type IntegerLiteralPolicy = fn(&IntegerLit) -> IntegerType;
+
+/// The default [`IntegerLiteralPolicy`]: every unsuffixed integer literal is an `i32`.
+fn default_integer_literal_policy(_integer: &IntegerLit) -> IntegerType {
+    IntegerType::I32
+}
+
+/// Pushes a literal value from `token` onto `output`, typing an unsuffixed integer literal with
+/// `integer_policy`.
 ///
 /// # Errors
 ///
 /// Returns `Err` if the literal type is unsupported or if a suffixed numeric
 /// literal cannot be parsed.
-fn push_literal_token<C: ParserContext>(output: &mut C, lit: CelLiteral) -> Result<()> {
+fn push_literal_token<C: ParserContext>(
+    output: &mut C,
+    lit: CelLiteral,
+    integer_policy: IntegerLiteralPolicy,
+) -> Result<()> {
     match lit {
         CelLiteral::Int(integer) => {
             let span = integer.span();
-            match integer.suffix() {
-                "" | "i32" => output.push_literal(
+            let suffix = match integer.suffix() {
+                "" => integer_policy(&integer).suffix(),
+                suffix => suffix,
+            };
+            match suffix {
+                "i32" => output.push_literal(
                     integer.base10_parse::<i32>().map_err(|e| {
                         ParseError::new(
                             format!("invalid i32 literal `{integer}`: {e}"),
@@ -239,18 +326,8 @@ fn push_literal_token<C: ParserContext>(output: &mut C, lit: CelLiteral) -> Resu
         CelLiteral::Float(float) => {
             let span = float.span();
             match float.suffix() {
-                "" | "f64" => output.push_literal(
-                    float.base10_parse::<f64>().map_err(|e| {
-                        ParseError::new(format!("invalid f64 literal `{float}`: {e}"), float.span())
-                    })?,
-                    span,
-                ),
-                "f32" => output.push_literal(
-                    float.base10_parse::<f32>().map_err(|e| {
-                        ParseError::new(format!("invalid f32 literal `{float}`: {e}"), float.span())
-                    })?,
-                    span,
-                ),
+                "" | "f64" => output.push_literal(parse_finite_float::<f64>(&float, "f64")?, span),
+                "f32" => output.push_literal(parse_finite_float::<f32>(&float, "f32")?, span),
                 suffix => {
                     return Err(ParseError::new(
                         format!("invalid float literal suffix: `{suffix}`"),
@@ -293,6 +370,35 @@ fn push_literal_token<C: ParserContext>(output: &mut C, lit: CelLiteral) -> Resu
     Ok(())
 }
 
+/// Parses `float` as an `F`, which `suffix` names in error messages.
+///
+/// Scientific notation (e.g. `1.5e3`) is accepted. A literal too large to represent, which would
+/// otherwise round to infinity (e.g. `1e309f64`), is an error, as with rustc's
+/// `overflowing_literals` lint.
+///
+/// # Errors
+///
+/// Returns `Err` if `float` can't be parsed as an `F` or is out of range for it.
+fn parse_finite_float<F>(float: &syn::LitFloat, suffix: &str) -> Result<F>
+where
+    F: FromStr + Into<f64> + Copy,
+    F::Err: std::fmt::Display,
+{
+    let value = float.base10_parse::<F>().map_err(|e| {
+        ParseError::new(
+            format!("invalid {suffix} literal `{float}`: {e}"),
+            float.span(),
+        )
+    })?;
+    if value.into().is_infinite() {
+        return Err(ParseError::new(
+            format!("{suffix} literal `{float}` is out of range"),
+            float.span(),
+        ));
+    }
+    Ok(value)
+}
+
 /// A recursive descent parser for expressions, generic over the [`ParserContext`] it emits
 /// into.
 ///
@@ -344,6 +450,163 @@ pub struct Parser<C: ParserContext> {
     context: C,
     op_lookup: OpLookup,
     last_span: Span,
+    /// Whether to collect [`warnings`](Parser::warnings) about unparenthesized operator mixes.
+    precedence_lint: bool,
+    warnings: Vec<ParseError>,
+    /// The outermost binary operator of the operand just parsed, or `None` for a unary or
+    /// primary expression (including a parenthesized group).
+    operand_operator: Option<&'static str>,
+    /// `let` bindings of the current token stream, in definition order, each compiled once.
+    bindings: Vec<(String, C::Binding)>,
+    /// Variables of the comprehensions whose predicates are being parsed, outermost first.
+    comprehension_variables: Vec<(String, C::Variable)>,
+    /// Number of nested sub-expressions currently being parsed; see
+    /// [`set_max_nesting_depth`](Parser::set_max_nesting_depth).
+    nesting_depth: usize,
+    max_nesting_depth: usize,
+    /// Chooses the type of unsuffixed integer literals.
+    integer_literal_policy: IntegerLiteralPolicy,
+}
+
+/// Default limit for [`Parser::set_max_nesting_depth`], low enough that an unoptimized build
+/// parsing on a 2 MiB thread stack (the default for spawned threads) doesn't overflow.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 32;
+
+/// How a chain of operators from one precedence level groups.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Associativity {
+    /// `a - b - c` parses as `(a - b) - c`.
+    Left,
+    /// `a ** b ** c` parses as `a ** (b ** c)`.
+    Right,
+    /// At most one operator per expression: `a == b == c` stops after `a == b`.
+    NonAssociative,
+}
+
+/// How an operator from one precedence level is emitted into the [`ParserContext`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OpBuilder {
+    /// A short-circuiting operator: the right operand is parsed into its own fragment and folded
+    /// in with [`ParserContext::apply_logical`].
+    ShortCircuit,
+    /// An ordinary binary operator resolved with [`ParserContext::apply_op`].
+    Binary,
+}
+
+/// One level of binary operators in [`PRECEDENCE_LEVELS`].
+struct PrecedenceLevel {
+    /// Grammar production this level parses, for error messages.
+    production: &'static str,
+    /// Operators at this level, matched in order.
+    operators: &'static [&'static str],
+    associativity: Associativity,
+    builder: OpBuilder,
+}
+
+/// Binary operator levels from loosest to tightest binding; operands of the last level are
+/// `cast_expression`s. Adding an operator only needs an entry here and a matching op in
+/// [`OpLookup`].
+const PRECEDENCE_LEVELS: &[PrecedenceLevel] = &[
+    PrecedenceLevel {
+        production: "or_expression",
+        operators: &["||", "?:"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::ShortCircuit,
+    },
+    PrecedenceLevel {
+        production: "and_expression",
+        operators: &["&&"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::ShortCircuit,
+    },
+    PrecedenceLevel {
+        production: "comparison_expression",
+        operators: &["==", "!=", "<=", ">=", "<", ">"],
+        associativity: Associativity::NonAssociative,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "bitwise_or_expression",
+        operators: &["|"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "bitwise_xor_expression",
+        operators: &["^"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "bitwise_and_expression",
+        operators: &["&"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "bitwise_shift_expression",
+        operators: &["<<", ">>"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "additive_expression",
+        operators: &["+", "-"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "multiplicative_expression",
+        operators: &["*", "/", "%"],
+        associativity: Associativity::Left,
+        builder: OpBuilder::Binary,
+    },
+    PrecedenceLevel {
+        production: "power_expression",
+        operators: &["**"],
+        associativity: Associativity::Right,
+        builder: OpBuilder::Binary,
+    },
+];
+
+/// Index of the `power_expression` level in [`PRECEDENCE_LEVELS`], the only level that binds
+/// tighter than prefix operators.
+const POWER_LEVEL: usize = PRECEDENCE_LEVELS.len() - 1;
+
+/// The precedence and associativity of a binary operator, as returned by [`operator_info`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperatorInfo {
+    /// Binding strength; an operator with a higher precedence binds tighter. Operators with the
+    /// same precedence share a grammar production.
+    pub precedence: usize,
+    /// How a chain of operators with this precedence groups.
+    pub associativity: Associativity,
+}
+
+/// Returns the precedence and associativity the grammar gives binary operator `op`, or `None`
+/// if `op` isn't a binary operator, for tooling (e.g. an editor inserting parentheses) that
+/// needs to agree with the parser.
+///
+/// # Examples
+///
+/// ```rust
+/// use cel_parser::{Associativity, operator_info};
+///
+/// let add = operator_info("+").unwrap();
+/// assert!(operator_info("*").unwrap().precedence > add.precedence);
+/// assert_eq!(add.associativity, Associativity::Left);
+/// assert!(operator_info("!").is_none());
+/// ```
+#[must_use]
+pub fn operator_info(op: &str) -> Option<OperatorInfo> {
+    PRECEDENCE_LEVELS
+        .iter()
+        .enumerate()
+        .find(|(_, level)| level.operators.contains(&op))
+        .map(|(index, level)| OperatorInfo {
+            precedence: index + 1,
+            associativity: level.associativity,
+        })
 }
 
 /// A recursive descent parser that executes directly into a [`DynSegment`].
@@ -352,6 +615,29 @@ pub struct Parser<C: ParserContext> {
 /// became generic over [`ParserContext`].
 pub type CELParser = Parser<DynSegmentContext>;
 
+/// Parses `source` with the built-in operations into a [`DynSegment`], for tooling that compiles
+/// many small expressions. On failure, returns the parse error rendered against `source` by
+/// [`ParseError::format_rustc_style`], with `<expression>` as the file name.
+///
+/// # Errors
+///
+/// Returns the rendered error if `source` is not a valid CEL expression.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut segment = cel_parser::compile("1 + 2").unwrap();
+/// assert_eq!(segment.call0::<i32>().unwrap(), 3);
+///
+/// let error = cel_parser::compile("1 +").err().unwrap();
+/// assert!(error.contains("<expression>:1:"));
+/// ```
+pub fn compile(source: &str) -> std::result::Result<DynSegment, String> {
+    CELParser::new(OpLookup::new())
+        .parse_str(source)
+        .map_err(|e| e.format_rustc_style(source, "<expression>", 1, &Renderer::plain()))
+}
+
 impl<C: ParserContext> Parser<C> {
     /// Creates a new CEL parser with the given operation lookup.
     ///
@@ -368,7 +654,91 @@ impl<C: ParserContext> Parser<C> {
             context: C::new_context(),
             op_lookup,
             last_span: Span::call_site(),
+            precedence_lint: false,
+            warnings: Vec::new(),
+            operand_operator: None,
+            bindings: Vec::new(),
+            comprehension_variables: Vec::new(),
+            nesting_depth: 0,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            integer_literal_policy: default_integer_literal_policy,
+        }
+    }
+
+    /// Sets how deeply sub-expressions may nest (parenthesized groups, call arguments, `if`
+    /// branches, and chains of unary or right-associative operators) before parsing fails with
+    /// "expression nesting too deep", instead of overflowing the stack. Defaults to
+    /// [`DEFAULT_MAX_NESTING_DEPTH`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    ///
+    /// let mut parser = CELParser::new(OpLookup::new());
+    /// parser.set_max_nesting_depth(3);
+    /// assert!(parser.parse_str("((1))").is_ok());
+    /// assert!(parser.parse_str("(((1)))").is_err());
+    /// ```
+    pub fn set_max_nesting_depth(&mut self, depth: usize) {
+        self.max_nesting_depth = depth;
+    }
+
+    /// Sets the policy choosing the type of an integer literal without a suffix, for dialects
+    /// where e.g. `10` is an `i64`. By default every unsuffixed integer literal is an `i32`; a
+    /// suffixed literal always has its suffix's type.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, IntegerType, OpLookup};
+    ///
+    /// let mut parser = CELParser::new(OpLookup::new());
+    /// parser.set_integer_literal_policy(|_| IntegerType::U64);
+    /// assert_eq!(parser.parse_str("10 + 5").unwrap().call0::<u64>().unwrap(), 15);
+    /// assert_eq!(parser.parse_str("10i32").unwrap().call0::<i32>().unwrap(), 10);
+    /// ```
+    pub fn set_integer_literal_policy(&mut self, policy: IntegerLiteralPolicy) {
+        self.integer_literal_policy = policy;
+    }
+
+    /// Runs `parse` one nesting level deeper.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the maximum nesting depth is already reached, or if `parse` does.
+    fn nested<R>(&mut self, parse: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        if self.nesting_depth >= self.max_nesting_depth {
+            return Err(self.error_at("expression nesting too deep"));
         }
+        self.nesting_depth += 1;
+        let result = parse(self);
+        self.nesting_depth -= 1;
+        result
+    }
+
+    /// Enables or disables the precedence lint, which records a [warning](Self::warnings) when a
+    /// bitwise operator is an unparenthesized operand of a comparison (e.g. `a & b == c`). Off by
+    /// default; warnings never fail the parse.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    ///
+    /// let mut parser = CELParser::new(OpLookup::new());
+    /// parser.set_precedence_lint(true);
+    /// parser.parse_str("6 & 3 == 2").unwrap();
+    /// assert_eq!(parser.warnings().len(), 1);
+    /// ```
+    pub fn set_precedence_lint(&mut self, enabled: bool) {
+        self.precedence_lint = enabled;
+    }
+
+    /// Returns the non-fatal diagnostics recorded while parsing the current token stream.
+    #[must_use]
+    pub fn warnings(&self) -> &[ParseError] {
+        &self.warnings
     }
 
     /// Sets the token stream for parsing, resetting internal state.
@@ -379,6 +749,10 @@ impl<C: ParserContext> Parser<C> {
         self.tokens = Some(LexLexer::new(tokens).peekable());
         self.context = C::new_context();
         self.last_span = Span::call_site();
+        self.warnings.clear();
+        self.bindings.clear();
+        self.comprehension_variables.clear();
+        self.nesting_depth = 0;
     }
 
     /// Sets the token stream from an existing [`LexLexer`] iterator for inline expression parsing.
@@ -390,6 +764,10 @@ impl<C: ParserContext> Parser<C> {
         self.tokens = Some(tokens);
         self.context = C::new_context();
         self.last_span = Span::call_site();
+        self.warnings.clear();
+        self.bindings.clear();
+        self.comprehension_variables.clear();
+        self.nesting_depth = 0;
     }
 
     /// Parses one `or_expression` from the current token stream and returns the built context.
@@ -424,9 +802,14 @@ impl<C: ParserContext> Parser<C> {
     ///
     /// # Errors
     ///
-    /// Returns an error if the input does not contain a valid CEL expression.
+    /// Returns an error with the message `"empty expression"` if the input has no tokens, so
+    /// callers can tell empty input from malformed input, or an error if the input does not
+    /// contain a valid CEL expression.
     pub fn parse_tokens_ctx(&mut self, tokens: TokenStreamIter) -> Result<C> {
         self.set_tokens(tokens);
+        if self.peek_token().is_none() {
+            return Err(ParseError::new("empty expression", Span::call_site()));
+        }
         if !self.is_expression()? {
             return Err(self.error_at("expression expected"));
         }
@@ -483,6 +866,46 @@ impl<C: ParserContext> Parser<C> {
         &mut self.op_lookup
     }
 
+    /// Registers a named function taking `arity` arguments, called as `name(args...)`.
+    ///
+    /// Forwards to [`OpLookup::register_function`]; `push_fn` receives the segment with the
+    /// parsed arguments on top of the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    ///
+    /// let mut parser = CELParser::new(OpLookup::new());
+    /// parser.register_function("double", 1, |seg, _span| seg.op1(|x: i32| x * 2));
+    /// let mut segment = parser.parse_str("double(21)").unwrap();
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 42);
+    /// ```
+    pub fn register_function<F>(&mut self, name: &str, arity: usize, push_fn: F)
+    where
+        F: Fn(&mut DynSegment, SourceSpan) -> anyhow::Result<()> + Send + Sync + 'static,
+    {
+        self.op_lookup.register_function(name, arity, push_fn);
+    }
+
+    /// Selects what integer `+`, `-`, and `*` compile to on overflow: wrap, fail, or panic.
+    ///
+    /// Forwards to [`OpLookup::set_overflow_mode`]; [`OverflowMode::Checked`] by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup, OverflowMode};
+    ///
+    /// let mut parser = CELParser::new(OpLookup::new());
+    /// assert!(parser.parse_str("200u8 + 100u8").unwrap().call0::<u8>().is_err());
+    /// parser.set_overflow_mode(OverflowMode::Wrap);
+    /// assert_eq!(parser.parse_str("200u8 + 100u8").unwrap().call0::<u8>().unwrap(), 44);
+    /// ```
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.op_lookup.set_overflow_mode(mode);
+    }
+
     /// Advances past the current token, recording its span in `last_span`.
     ///
     /// # Panics
@@ -490,13 +913,8 @@ impl<C: ParserContext> Parser<C> {
     /// Panics if no token stream has been set or if there is no current token.
     fn advance(&mut self) {
         use lex_lexer::HasSpan;
-        self.last_span = self
-            .tokens
-            .as_mut()
-            .expect("tokens set")
-            .next()
-            .expect("token required to advance")
-            .span();
+        let token = self.tokens.as_mut().expect("tokens set").next();
+        self.last_span = token.expect("token required to advance").span();
     }
 
     /// Returns the span of the next token without consuming it, or `None` if exhausted.
@@ -537,6 +955,17 @@ impl<C: ParserContext> Parser<C> {
         }
     }
 
+    /// Consumes and returns `true` if the next token closes a `delimiter` group.
+    fn is_close_delimiter(&mut self, delimiter: Delimiter) -> bool {
+        match self.peek_token() {
+            Some(Token::CloseDelim { delimiter: d, .. }) if *d == delimiter => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Consumes and returns `true` if the next token is an identifier matching `keyword`.
     fn is_keyword(&mut self, keyword: &str) -> bool {
         match self.peek_token() {
@@ -548,8 +977,9 @@ impl<C: ParserContext> Parser<C> {
         }
     }
 
-    /// `expression = or_expression <EOF>.`
+    /// `expression = { let_binding } or_expression <EOF>.`
     pub fn is_expression(&mut self) -> Result<bool> {
+        while self.is_let_binding()? {}
         if !self.is_or_expression()? {
             return Ok(false);
         }
@@ -559,274 +989,266 @@ impl<C: ParserContext> Parser<C> {
         Ok(true)
     }
 
-    /// `or_expression = and_expression { "||" and_expression }.`
+    /// `let_binding = "let" identifier "=" or_expression ";".`
+    ///
+    /// Compiles the expression once, into a context of its own that sees only the bindings
+    /// defined before it; each later reference to `identifier` pushes its value as if
+    /// parenthesized.
     ///
     /// # Errors
     ///
-    /// Returns an error if the RHS is missing after `||`, if the RHS does not
-    /// produce a `bool`, or if any sub-expression returns an error.
-    fn is_or_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_and_expression()? {
-            while self.is_punctuation("||") {
-                let mut rhs_fragment = self.context.new_fragment();
-                std::mem::swap(&mut self.context, &mut rhs_fragment);
-                if !self.is_and_expression()? {
-                    return Err(self.error_at("expected and_expression"));
-                }
-                std::mem::swap(&mut self.context, &mut rhs_fragment);
-                self.context.apply_logical(
-                    "||",
-                    rhs_fragment,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+    /// Returns an error if the identifier, `=`, expression, or terminating `;` is missing, or if
+    /// the expression fails to parse.
+    fn is_let_binding(&mut self) -> Result<bool> {
+        if !self.is_keyword("let") {
+            return Ok(false);
+        }
+        let name = match self.peek_token() {
+            Some(Token::Identifier(ident)) => ident.to_string(),
+            _ => return Err(self.error_at("expected identifier after `let`")),
+        };
+        self.advance();
+        if !self.is_punctuation("=") {
+            return Err(self.error_at("expected `=` after let binding name"));
         }
+
+        let outer_context = std::mem::replace(&mut self.context, C::new_context());
+        let parsed = self.is_or_expression();
+        let value = std::mem::replace(&mut self.context, outer_context);
+        if !parsed? {
+            return Err(self.error_at("expected expression in let binding"));
+        }
+        if !self.is_punctuation(";") {
+            return Err(self.error_at("expected `;` after let binding"));
+        }
+
+        self.bindings.push((name, C::compile_binding(value)));
+        Ok(true)
     }
 
-    /// `and_expression = comparison_expression { "&&" comparison_expression }.`
+    /// `or_expression = and_expression { ("||" | "?:") and_expression }
+    ///     [ "?" or_expression ":" or_expression ].`
+    ///
+    /// Entry point of the binary operator levels in [`PRECEDENCE_LEVELS`]. A trailing
+    /// `? then : else` makes the expression the condition of a conditional, which groups to the
+    /// right: `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
     ///
     /// # Errors
     ///
-    /// Returns an error if the RHS is missing after `&&`, if the RHS does not
-    /// produce a `bool`, or if any sub-expression returns an error.
-    fn is_and_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_comparison_expression()? {
-            while self.is_punctuation("&&") {
-                let mut rhs_fragment = self.context.new_fragment();
-                std::mem::swap(&mut self.context, &mut rhs_fragment);
-                if !self.is_comparison_expression()? {
-                    return Err(self.error_at("expected comparison_expression"));
-                }
-                std::mem::swap(&mut self.context, &mut rhs_fragment);
-                self.context.apply_logical(
-                    "&&",
-                    rhs_fragment,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
+    /// Returns an error if an operand is missing after a binary operator, if a logical operand
+    /// does not produce a `bool`, if a conditional's branch or `:` is missing or its branch types
+    /// don't unify, or if any sub-expression returns an error.
+    fn is_or_expression(&mut self) -> Result<bool> {
+        self.nested(|parser| {
+            let start_span = parser.peek_span();
+            if !parser.is_binary_expression(0)? {
+                return Ok(false);
             }
-            Ok(true)
-        } else {
-            Ok(false)
-        }
-    }
-
-    /// `comparison_expression = bitwise_or_expression [ ("==" | "!=" | "<" | ">" | "<=" | ">=") bitwise_or_expression ].`
-    fn is_comparison_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_bitwise_or_expression()? {
-            // Longer operators first: must check "==" before "=", "<=" before "<", etc.
-            let op_name = if self.is_punctuation("==") {
-                Some("==")
-            } else if self.is_punctuation("!=") {
-                Some("!=")
-            } else if self.is_punctuation("<=") {
-                Some("<=")
-            } else if self.is_punctuation(">=") {
-                Some(">=")
-            } else if self.is_punctuation("<") {
-                Some("<")
-            } else if self.is_punctuation(">") {
-                Some(">")
-            } else {
-                None
-            };
-
-            if let Some(op_name) = op_name {
-                if !self.is_bitwise_or_expression()? {
-                    return Err(self.error_at("expected bitwise_or_expression"));
-                }
-                self.context.apply_op(
-                    &self.op_lookup,
-                    op_name,
-                    2,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
+            if parser.is_punctuation("?") {
+                let start = start_span.expect("production has token at start");
+                parser.conditional_branches(start)?;
             }
             Ok(true)
-        } else {
-            Ok(false)
-        }
+        })
     }
 
-    /// `bitwise_or_expression = bitwise_xor_expression { "|" bitwise_xor_expression }.`
-    fn is_bitwise_or_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_bitwise_xor_expression()? {
-            while self.is_punctuation("|") {
-                if !self.is_bitwise_xor_expression()? {
-                    return Err(self.error_at("expected bitwise_xor_expression"));
-                }
-                self.context.apply_op(
-                    &self.op_lookup,
-                    "|",
-                    2,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+    /// Parses `or_expression ":" or_expression`, the branches of a conditional whose condition
+    /// and `?` were already parsed, and joins them. `start` spans the condition's first token.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a branch or the `:` is missing, if the condition isn't a `bool`, or
+    /// if the branch types don't unify.
+    fn conditional_branches(&mut self, start: Span) -> Result<()> {
+        let mut then_fragment = self.context.new_fragment();
+        std::mem::swap(&mut self.context, &mut then_fragment);
+        let then_parsed = self.is_or_expression();
+        std::mem::swap(&mut self.context, &mut then_fragment);
+        if !then_parsed? {
+            return Err(self.error_at("expected expression after `?`"));
         }
-    }
-
-    /// `bitwise_xor_expression = bitwise_and_expression { "^" bitwise_and_expression }.`
-    fn is_bitwise_xor_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_bitwise_and_expression()? {
-            while self.is_punctuation("^") {
-                if !self.is_bitwise_and_expression()? {
-                    return Err(self.error_at("expected bitwise_and_expression"));
-                }
-                self.context.apply_op(
-                    &self.op_lookup,
-                    "^",
-                    2,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+        if !self.is_punctuation(":") {
+            return Err(self.error_at("expected `:` in conditional expression"));
+        }
+        let mut else_fragment = self.context.new_fragment();
+        std::mem::swap(&mut self.context, &mut else_fragment);
+        let else_parsed = self.is_or_expression();
+        std::mem::swap(&mut self.context, &mut else_fragment);
+        if !else_parsed? {
+            return Err(self.error_at("expected expression after `:`"));
         }
+        self.context
+            .join2(then_fragment, else_fragment, start, self.last_span)
+            .map_err(|e| ParseError::new_range(e.to_string(), start, self.last_span))
     }
 
-    /// `bitwise_and_expression = bitwise_shift_expression { "&" bitwise_shift_expression }.`
-    fn is_bitwise_and_expression(&mut self) -> Result<bool> {
+    /// Parses the production for `PRECEDENCE_LEVELS[level]` by precedence climbing, or
+    /// `cast_expression` once `level` is past the last entry. For example, level `0` is
+    /// `or_expression = and_expression { "||" and_expression }.`
+    ///
+    /// A left-associative level folds each operand into the running result as it goes; a
+    /// right-associative level parses its right operand at the same level, so the rightmost
+    /// operator applies first; a non-associative level accepts at most one operator.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operand is missing after an operator, or if applying an operator
+    /// or any sub-expression returns an error.
+    fn is_binary_expression(&mut self, level: usize) -> Result<bool> {
+        if level == PRECEDENCE_LEVELS.len() {
+            let matched = self.is_cast_expression()?;
+            self.operand_operator = None;
+            return Ok(matched);
+        }
         let start_span = self.peek_span();
-        if self.is_bitwise_shift_expression()? {
-            while self.is_punctuation("&") {
-                if !self.is_bitwise_shift_expression()? {
-                    return Err(self.error_at("expected bitwise_shift_expression"));
-                }
-                self.context.apply_op(
-                    &self.op_lookup,
-                    "&",
-                    2,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
-                )?;
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+        if !self.is_binary_expression(level + 1)? {
+            return Ok(false);
         }
+        self.binary_operators(level, start_span)?;
+        Ok(true)
     }
 
-    /// `bitwise_shift_expression = additive_expression { ("<<" | ">>") additive_expression }.`
-    fn is_bitwise_shift_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_additive_expression()? {
-            loop {
-                let op_name = if self.is_punctuation("<<") {
-                    Some("<<")
-                } else if self.is_punctuation(">>") {
-                    Some(">>")
-                } else {
-                    None
-                };
-
-                if let Some(op_name) = op_name {
-                    if !self.is_additive_expression()? {
-                        return Err(self.error_at("expected additive_expression"));
+    /// Parses the operators, and their right operands, of the production for
+    /// `PRECEDENCE_LEVELS[level]` following a left operand that started at `start_span`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operand is missing after an operator, or if applying an operator
+    /// or any sub-expression returns an error.
+    fn binary_operators(&mut self, level: usize, start_span: Option<Span>) -> Result<()> {
+        let precedence = &PRECEDENCE_LEVELS[level];
+        let operand_level = match precedence.associativity {
+            Associativity::Right => level,
+            Associativity::Left | Associativity::NonAssociative => level + 1,
+        };
+        while let Some(op_name) = self.match_operator(precedence.operators) {
+            let operator_span = self.last_span;
+            let start = start_span.expect("production has token at start");
+            let lhs_operator = self.operand_operator;
+            match precedence.builder {
+                OpBuilder::ShortCircuit => {
+                    let mut rhs_fragment = self.context.new_fragment();
+                    std::mem::swap(&mut self.context, &mut rhs_fragment);
+                    self.expect_binary_operand(operand_level)?;
+                    std::mem::swap(&mut self.context, &mut rhs_fragment);
+                    self.context
+                        .apply_logical(op_name, rhs_fragment, start, self.last_span)?;
+                }
+                OpBuilder::Binary => {
+                    if operand_level == level {
+                        // A right-associative operand recurses at this level, so it nests.
+                        self.nested(|parser| parser.expect_binary_operand(operand_level))?;
+                    } else {
+                        self.expect_binary_operand(operand_level)?;
                     }
+                    self.lint_precedence(precedence, op_name, lhs_operator, start);
                     self.context.apply_op(
                         &self.op_lookup,
                         op_name,
                         2,
-                        start_span.expect("production has token at start"),
-                        self.last_span,
+                        OperatorSpan {
+                            operator: operator_span,
+                            start,
+                            end: self.last_span,
+                        },
                     )?;
-                } else {
-                    break;
                 }
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            self.operand_operator = Some(op_name);
+            if precedence.associativity != Associativity::Left {
+                break;
+            }
         }
+        Ok(())
     }
 
-    /// `additive_expression = multiplicative_expression { ("+" | "-") multiplicative_expression }.`
-    fn is_additive_expression(&mut self) -> Result<bool> {
-        let start_span = self.peek_span();
-        if self.is_multiplicative_expression()? {
-            loop {
-                let op_name = if self.is_punctuation("+") {
-                    Some("+")
-                } else if self.is_punctuation("-") {
-                    Some("-")
-                } else {
-                    None
-                };
-
-                if let Some(op_name) = op_name {
-                    if !self.is_multiplicative_expression()? {
-                        return Err(self.error_at("expected multiplicative_expression"));
-                    }
-                    self.context.apply_op(
-                        &self.op_lookup,
-                        op_name,
-                        2,
-                        start_span.expect("production has token at start"),
-                        self.last_span,
-                    )?;
-                } else {
-                    break;
-                }
+    /// Records a precedence warning if `op_name`, from `precedence`, is a comparison with an
+    /// unparenthesized bitwise operand: `lhs_operator` for the left operand,
+    /// [`operand_operator`](Self::operand_operator) for the right operand just parsed. `start`
+    /// spans the first token of the left operand.
+    fn lint_precedence(
+        &mut self,
+        precedence: &PrecedenceLevel,
+        op_name: &str,
+        lhs_operator: Option<&str>,
+        start: Span,
+    ) {
+        const BITWISE: &[&str] = &["&", "|", "^"];
+        if !self.precedence_lint || precedence.production != "comparison_expression" {
+            return;
+        }
+        for operand in [lhs_operator, self.operand_operator].into_iter().flatten() {
+            if BITWISE.contains(&operand) {
+                self.warnings.push(ParseError::new_range(
+                    format!(
+                        "`{operand}` binds tighter than `{op_name}`; add parentheses to clarify"
+                    ),
+                    start,
+                    self.last_span,
+                ));
             }
-            Ok(true)
-        } else {
-            Ok(false)
         }
     }
 
-    /// `multiplicative_expression = unary_expression { ("*" | "/" | "%") unary_expression }.`
-    fn is_multiplicative_expression(&mut self) -> Result<bool> {
+    /// Parses the operand following a binary operator at `level`, which must be present.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the expected production if no operand follows.
+    fn expect_binary_operand(&mut self, level: usize) -> Result<()> {
+        if self.is_binary_expression(level)? {
+            return Ok(());
+        }
+        let production = PRECEDENCE_LEVELS
+            .get(level)
+            .map_or("cast_expression", |precedence| precedence.production);
+        Err(self.error_at(&format!("expected {production}")))
+    }
+
+    /// Consumes and returns the next token if it is one of `operators`.
+    fn match_operator(&mut self, operators: &[&'static str]) -> Option<&'static str> {
+        operators.iter().copied().find(|op| self.is_punctuation(op))
+    }
+
+    /// `cast_expression = unary_expression { "as" type_name }.`
+    ///
+    /// Each cast applies the unary operator `"as <type_name>"`, so the conversions available are
+    /// whatever [`OpLookup`] resolves for that name and the operand type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `as` isn't followed by a type name, if no conversion exists from the
+    /// operand type, or if the operand returns an error.
+    fn is_cast_expression(&mut self) -> Result<bool> {
         let start_span = self.peek_span();
-        if self.is_unary_expression()? {
-            loop {
-                let op_name = if self.is_punctuation("*") {
-                    Some("*")
-                } else if self.is_punctuation("/") {
-                    Some("/")
-                } else if self.is_punctuation("%") {
-                    Some("%")
-                } else {
-                    None
-                };
-
-                if let Some(op_name) = op_name {
-                    if !self.is_unary_expression()? {
-                        return Err(self.error_at("expected unary_expression"));
-                    }
-                    self.context.apply_op(
-                        &self.op_lookup,
-                        op_name,
-                        2,
-                        start_span.expect("production has token at start"),
-                        self.last_span,
-                    )?;
-                } else {
-                    break;
-                }
-            }
-            Ok(true)
-        } else {
-            Ok(false)
+        if !self.is_unary_expression()? {
+            return Ok(false);
+        }
+        while self.is_keyword("as") {
+            let operator_span = self.last_span;
+            let Some(Token::Identifier(type_name)) = self.peek_token() else {
+                return Err(self.error_at("expected type name after `as`"));
+            };
+            let op_name = format!("as {type_name}");
+            self.advance();
+            self.context.apply_op(
+                &self.op_lookup,
+                &op_name,
+                1,
+                OperatorSpan {
+                    operator: operator_span,
+                    start: start_span.expect("production has token at start"),
+                    end: self.last_span,
+                },
+            )?;
         }
+        Ok(true)
     }
 
-    /// `unary_expression = (("-" | "!") unary_expression) | primary_expression.`
+    /// `unary_expression = (("-" | "!") unary_expression [ "**" power_expression ])
+    ///     | postfix_expression.`
+    ///
+    /// `**` binds tighter than a prefix operator, so `-2 ** 2` is `-(2 ** 2)`.
     fn is_unary_expression(&mut self) -> Result<bool> {
         let start_span = self.peek_span();
         let op_name = if self.is_punctuation("-") {
@@ -838,15 +1260,21 @@ impl<C: ParserContext> Parser<C> {
         };
 
         if let Some(op_name) = op_name {
-            if !self.is_unary_expression()? {
+            let operand_span = self.peek_span();
+            if !self.nested(Self::is_unary_expression)? {
                 return Err(self.error_at("expected unary_expression"));
             }
+            self.binary_operators(POWER_LEVEL, operand_span)?;
+            let start = start_span.expect("production has token at start");
             self.context.apply_op(
                 &self.op_lookup,
                 op_name,
                 1,
-                start_span.expect("production has token at start"),
-                self.last_span,
+                OperatorSpan {
+                    operator: start,
+                    start,
+                    end: self.last_span,
+                },
             )?;
             Ok(true)
         } else {
@@ -854,7 +1282,8 @@ impl<C: ParserContext> Parser<C> {
         }
     }
 
-    /// `postfix_expression = primary_expression { "(" parameter_list ")" | "." unsuffixed_integer }.`
+    /// `postfix_expression = primary_expression
+    ///     { "(" argument_list ")" | "." unsuffixed_integer | "." identifier }.`
     ///
     /// The repetition allows chained indices (`t.0.1`): each `"." unsuffixed_integer`
     /// is applied in turn to whatever value the previous step left on top of the
@@ -862,11 +1291,22 @@ impl<C: ParserContext> Parser<C> {
     /// float literal `0.1` (Rust's own lexer maximally munches the digits after
     /// the second `.`), so that case is detected and split back into its two
     /// integer indices — see the `Token::Literal(CelLiteral::Float(..))` arm below.
+    /// A `"." identifier` field access is the unary operator `".identifier"`.
     fn is_postfix_expression(&mut self) -> Result<bool> {
         let start_span = self.peek_span();
         if !self.is_primary_expression()? {
             return Ok(false);
         }
+        self.postfix_chain(start_span, false)?;
+        Ok(true)
+    }
+
+    /// Parses the `{ ... }` repetition of `postfix_expression` after its primary expression,
+    /// which started at `start_span`.
+    ///
+    /// If `presence_test`, a field access directly followed by `)` is applied as the presence
+    /// test `"has .identifier"` instead, ending the chain, and `true` is returned.
+    fn postfix_chain(&mut self, start_span: Option<Span>, presence_test: bool) -> Result<bool> {
         loop {
             if matches!(
                 self.peek_token(),
@@ -876,23 +1316,19 @@ impl<C: ParserContext> Parser<C> {
                 })
             ) {
                 self.advance(); // consume "("
-                let arg_count = self.parameter_list()?;
-                match self.peek_token() {
-                    Some(Token::CloseDelim {
-                        delimiter: Delimiter::Parenthesis,
-                        ..
-                    }) => {
-                        self.advance(); // consume ")"
-                    }
-                    _ => return Err(self.error_at("expected closing parenthesis")),
-                }
+                let operator_span = self.last_span;
+                let arg_count =
+                    self.parse_argument_list(Delimiter::Parenthesis, &mut |_, _, _| Ok(()))?;
                 // Stack order is [callee, arg1, arg2, ...]; lookup peeks top (arg_count + 1) entries.
                 self.context.apply_op(
                     &self.op_lookup,
                     "()",
                     arg_count + 1,
-                    start_span.expect("production has token at start"),
-                    self.last_span,
+                    OperatorSpan {
+                        operator: operator_span,
+                        start: start_span.expect("production has token at start"),
+                        end: self.last_span,
+                    },
                 )?;
             } else if self.is_punctuation(".") {
                 match self.peek_token() {
@@ -940,13 +1376,124 @@ impl<C: ParserContext> Parser<C> {
                         self.apply_tuple_index(first_index, idx_start)?;
                         self.apply_tuple_index(second_index, idx_start)?;
                     }
-                    _ => return Err(self.error_at("expected integer after '.'")),
+                    Some(Token::Identifier(ident)) => {
+                        let field = ident.to_string();
+                        let field_span = ident.span();
+                        self.advance();
+                        let quantifier = match field.as_str() {
+                            "all" => Some(Quantifier::All),
+                            "exists" => Some(Quantifier::Exists),
+                            _ => None,
+                        };
+                        if let Some(quantifier) = quantifier
+                            && matches!(
+                                self.peek_token(),
+                                Some(Token::OpenDelim {
+                                    delimiter: Delimiter::Parenthesis,
+                                    ..
+                                })
+                            )
+                        {
+                            self.comprehension(
+                                quantifier,
+                                field_span,
+                                start_span.expect("production has token at start"),
+                            )?;
+                            continue;
+                        }
+                        let presence = presence_test
+                            && matches!(
+                                self.peek_token(),
+                                Some(Token::CloseDelim {
+                                    delimiter: Delimiter::Parenthesis,
+                                    ..
+                                })
+                            );
+                        let op_name = if presence {
+                            format!("has .{field}")
+                        } else {
+                            format!(".{field}")
+                        };
+                        self.context.apply_op(
+                            &self.op_lookup,
+                            &op_name,
+                            1,
+                            OperatorSpan {
+                                operator: field_span,
+                                start: start_span.expect("production has token at start"),
+                                end: self.last_span,
+                            },
+                        )?;
+                        if presence {
+                            return Ok(true);
+                        }
+                    }
+                    _ => return Err(self.error_at("expected integer or identifier after '.'")),
                 }
             } else {
                 break;
             }
         }
-        Ok(true)
+        Ok(false)
+    }
+
+    /// `comprehension = "." ("all" | "exists") "(" identifier "," or_expression ")".`
+    ///
+    /// Replaces the list on top with whether the predicate holds for all or any of its elements,
+    /// with each element bound to the identifier in turn. `start` is the span of the list
+    /// expression.
+    ///
+    /// - Precondition: the macro name (whose token spans `name_span`) has just been consumed and
+    ///   the next token is `(`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variable, comma, predicate, or closing `)` is missing, if the top
+    /// value isn't a list, or if the predicate doesn't produce a `bool`.
+    fn comprehension(
+        &mut self,
+        quantifier: Quantifier,
+        name_span: Span,
+        start: Span,
+    ) -> Result<()> {
+        self.advance(); // consume "("
+        let variable = match self.peek_token() {
+            Some(Token::Identifier(ident)) => ident.to_string(),
+            _ => return Err(self.error_at("expected comprehension variable")),
+        };
+        self.advance();
+        if !self.is_punctuation(",") {
+            return Err(self.error_at("expected `,` after comprehension variable"));
+        }
+        let (mut predicate, binding) = self
+            .context
+            .new_predicate_fragment(&self.op_lookup, name_span)?;
+        std::mem::swap(&mut self.context, &mut predicate);
+        self.comprehension_variables
+            .push((variable.clone(), binding));
+        let parsed = self.is_or_expression();
+        self.comprehension_variables.pop();
+        std::mem::swap(&mut self.context, &mut predicate);
+        if !parsed? {
+            return Err(self.error_at("expected predicate after `,`"));
+        }
+        match self.peek_token() {
+            Some(Token::CloseDelim {
+                delimiter: Delimiter::Parenthesis,
+                ..
+            }) => {
+                self.advance(); // consume ")"
+            }
+            _ => return Err(self.error_at("expected closing parenthesis")),
+        }
+        self.context.apply_comprehension(
+            &self.op_lookup,
+            quantifier,
+            &variable,
+            predicate,
+            start,
+            self.last_span,
+        )
     }
 
     /// Applies a single `.N` tuple-index operation to the value currently on
@@ -970,23 +1517,69 @@ impl<C: ParserContext> Parser<C> {
         Ok(())
     }
 
-    /// `parameter_list = [ or_expression { "," or_expression } ].`
+    /// `argument_list = [ or_expression { "," or_expression } ].`, followed by the `close`
+    /// delimiter, which is consumed.
+    ///
+    /// Parses each argument into the shared context, then calls `each` with the argument's index
+    /// and the span of the token after it (its `,` or the closing delimiter). Function calls and
+    /// list literals share this, so their arguments parse the same way.
     ///
     /// Returns the argument count.
-    fn parameter_list(&mut self) -> Result<usize> {
+    ///
+    /// A list (`close` is [`Delimiter::Bracket`]) may end with a comma, as in `[1, 2,]`.
+    ///
+    /// - Precondition: the opening delimiter matching `close` has just been consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an argument is missing before or after a comma, if the closing
+    /// delimiter is missing, or if `each` fails.
+    fn parse_argument_list(
+        &mut self,
+        close: Delimiter,
+        each: &mut dyn FnMut(&mut Self, usize, Span) -> Result<()>,
+    ) -> Result<usize> {
         let mut count = 0;
         if self.is_or_expression()? {
-            count += 1;
-            while self.is_punctuation(",") {
+            loop {
+                let next = self.peek_span().unwrap_or(self.last_span);
+                each(self, count, next)?;
+                count += 1;
+                if !self.is_punctuation(",") {
+                    break;
+                }
+                if close == Delimiter::Bracket && self.is_close_delimiter(close) {
+                    return Ok(count);
+                }
                 if !self.is_or_expression()? {
-                    return Err(self.error_at("expected expression after comma"));
+                    return Err(self.missing_argument_error(close));
                 }
-                count += 1;
             }
+        } else if close == Delimiter::Bracket
+            && matches!(self.peek_token(), Some(Token::Punct { op, .. }) if op == ",")
+        {
+            return Err(self.missing_argument_error(close));
+        }
+        if !self.is_close_delimiter(close) {
+            return Err(self.error_at(match close {
+                Delimiter::Bracket => "expected ',' or closing bracket",
+                _ => "expected closing parenthesis",
+            }));
         }
         Ok(count)
     }
 
+    /// Builds the error for an argument missing at the current token, which follows a comma or,
+    /// in a list, may be a comma itself.
+    fn missing_argument_error(&mut self, close: Delimiter) -> ParseError {
+        match self.peek_token() {
+            Some(Token::Punct { op, .. }) if close == Delimiter::Bracket && op == "," => {
+                self.error_at("expected list element before `,`")
+            }
+            _ => self.error_at("expected expression after comma"),
+        }
+    }
+
     /// `primary_expression = literal | identifier | tuple_or_group | if_expression.`
     ///
     /// Dispatches to [`is_if_expression`](Self::is_if_expression) when the `if` keyword is seen,
@@ -1004,7 +1597,7 @@ impl<C: ParserContext> Parser<C> {
             Some(Token::Literal(lit)) => {
                 let lit_clone = lit.clone();
                 self.advance();
-                push_literal_token(&mut self.context, lit_clone)?;
+                push_literal_token(&mut self.context, lit_clone, self.integer_literal_policy)?;
                 Ok(true)
             }
             Some(Token::Identifier(ident)) => {
@@ -1016,8 +1609,46 @@ impl<C: ParserContext> Parser<C> {
                     return self.is_if_expression(ident_span);
                 }
 
-                self.context
-                    .apply_op(&self.op_lookup, &ident_name, 0, ident_span, ident_span)?;
+                if ident_name == "null" {
+                    self.context.push_literal(CelNull, ident_span);
+                    return Ok(true);
+                }
+
+                if ident_name == "has" {
+                    return self.is_has_macro(ident_span);
+                }
+
+                if let Some((_, variable)) = self
+                    .comprehension_variables
+                    .iter()
+                    .rfind(|(name, _)| *name == ident_name)
+                {
+                    self.context
+                        .push_predicate_variable(variable, &ident_name, ident_span);
+                    return Ok(true);
+                }
+
+                if let Some((_, binding)) =
+                    self.bindings.iter().rfind(|(name, _)| *name == ident_name)
+                {
+                    self.context.push_binding(binding, ident_span);
+                    return Ok(true);
+                }
+
+                if let Some(arity) = self.op_lookup.function_arity(&ident_name) {
+                    return self.is_function_call(&ident_name, arity, ident_span);
+                }
+
+                self.context.apply_op(
+                    &self.op_lookup,
+                    &ident_name,
+                    0,
+                    OperatorSpan {
+                        operator: ident_span,
+                        start: ident_span,
+                        end: ident_span,
+                    },
+                )?;
 
                 Ok(true)
             }
@@ -1025,10 +1656,89 @@ impl<C: ParserContext> Parser<C> {
                 delimiter: Delimiter::Parenthesis,
                 ..
             }) => self.is_tuple_or_group(),
+            Some(Token::OpenDelim {
+                delimiter: Delimiter::Bracket,
+                ..
+            }) => self.is_list_literal(),
             _ => Ok(false),
         }
     }
 
+    /// `has_macro = "has" "(" postfix_expression ")".`
+    ///
+    /// The argument must end in a field access, `base.identifier`, which is applied as the
+    /// presence test `"has .identifier"` on `base` rather than as an access.
+    ///
+    /// - Precondition: `has` (whose token spans `has_span`) has just been consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parentheses are missing, if the argument doesn't end in a field
+    /// access, or if the presence test cannot be applied to the type of `base`.
+    fn is_has_macro(&mut self, has_span: Span) -> Result<bool> {
+        if !matches!(
+            self.peek_token(),
+            Some(Token::OpenDelim {
+                delimiter: Delimiter::Parenthesis,
+                ..
+            })
+        ) {
+            return Err(self.error_at("expected `(` after `has`"));
+        }
+        self.advance(); // consume "("
+        let start_span = self.peek_span();
+        if !self.is_primary_expression()? || !self.postfix_chain(start_span, true)? {
+            return Err(ParseError::new_range(
+                "`has` requires a field access argument (e.g. `has(m.key)`)",
+                has_span,
+                self.last_span,
+            ));
+        }
+        self.advance(); // consume ")"
+        Ok(true)
+    }
+
+    /// `function_call = function_name "(" argument_list ")".`
+    ///
+    /// - Precondition: `name` (whose token spans `name_span`) has just been consumed and is
+    ///   registered as a function taking `arity` arguments.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the argument list is missing or malformed, if its length differs from
+    /// `arity`, or if the function's operation cannot be applied to the argument types.
+    fn is_function_call(&mut self, name: &str, arity: usize, name_span: Span) -> Result<bool> {
+        if !matches!(
+            self.peek_token(),
+            Some(Token::OpenDelim {
+                delimiter: Delimiter::Parenthesis,
+                ..
+            })
+        ) {
+            return Err(self.error_at(&format!("expected `(` after function `{name}`")));
+        }
+        self.advance(); // consume "("
+        let arg_count = self.parse_argument_list(Delimiter::Parenthesis, &mut |_, _, _| Ok(()))?;
+        if arg_count != arity {
+            return Err(ParseError::new_range(
+                format!("function `{name}` expects {arity} argument(s), got {arg_count}"),
+                name_span,
+                self.last_span,
+            ));
+        }
+        self.context.apply_op(
+            &self.op_lookup,
+            name,
+            arity,
+            OperatorSpan {
+                operator: name_span,
+                start: name_span,
+                end: self.last_span,
+            },
+        )?;
+        Ok(true)
+    }
+
     /// `tuple_or_group = "(" [ or_expression ["," [ or_expression { "," or_expression } ]] ] ")".`
     ///
     /// `()` parses as unit, `(expr)` as grouping, `(expr,)` as a 1-tuple, and
@@ -1113,6 +1823,36 @@ impl<C: ParserContext> Parser<C> {
         Ok(true)
     }
 
+    /// `list_literal = "[" argument_list [ "," ] "]".`, with at least one element.
+    ///
+    /// - Precondition: The next token is `Token::OpenDelim` with `Delimiter::Bracket`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list is empty (its element type would be unknown), if an element
+    /// or comma is missing, if the closing `]` is missing, or if the elements aren't all of one
+    /// registered list element type.
+    fn is_list_literal(&mut self) -> Result<bool> {
+        let open_span = self
+            .peek_span()
+            .expect("list_literal requires an opening '[' token");
+        self.advance();
+        // Each element's span runs through the following `,` or `]`, so the finished list's
+        // span covers its brackets.
+        let count = self.parse_argument_list(Delimiter::Bracket, &mut |parser, index, end| {
+            parser
+                .context
+                .push_list_element(&parser.op_lookup, index == 0, open_span, end)
+        })?;
+        if count == 0 {
+            return Err(ParseError::new(
+                "expected list element (an empty list `[]` has no element type)",
+                self.last_span,
+            ));
+        }
+        Ok(true)
+    }
+
     /// `if_expression = "if" or_expression "{" or_expression "}" [ "else" ( "{" or_expression "}" | if_expression ) ].`
     ///
     /// - Precondition: The `if` keyword has already been consumed by the caller; `if_span` is
@@ -1300,12 +2040,40 @@ impl Parser<AstContext> {
     pub fn parse_str_ast(&mut self, s: &str) -> Result<Expr> {
         self.parse_str_ctx(s).map(AstContext::into_expr)
     }
+
+    /// Parses a string and compiles it into a serializable [`Bytecode`] program with the
+    /// parser's operators, as [`bytecode::compile`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error on lex failure, if the input does not contain a valid CEL expression,
+    /// or if it uses a construct bytecode doesn't support.
+    ///
+    /// - Complexity: O(n) in the length of `s`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{AstContext, OpLookup, Parser};
+    /// use cel_runtime::bytecode::Value;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut parser = Parser::<AstContext>::new(OpLookup::new());
+    /// let bytecode = parser.parse_str_bytecode("1 + 2").unwrap();
+    /// assert_eq!(bytecode.execute(&HashMap::new()).unwrap(), Value::I32(3));
+    /// ```
+    #[cfg(feature = "bytecode")]
+    pub fn parse_str_bytecode(&mut self, s: &str) -> Result<Bytecode> {
+        let expr = self.parse_str_ast(s)?;
+        bytecode::compile(&expr, &self.op_lookup)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use annotate_snippets::Renderer;
+    use std::any::TypeId;
 
     #[test]
     fn simple_expression() {
@@ -1343,6 +2111,65 @@ mod tests {
         assert!((value - 42.14).abs() < 1e-10);
     }
 
+    #[test]
+    fn integer_and_float_operands_promote_to_f64() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (source, expected) in [("3 + 2.5", 5.5), ("2.5 * 2u8", 5.0), ("1.0 / 4", 0.25)] {
+            let mut segment = parser
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<f64>()?, expected, "{source}");
+        }
+        assert!(parser.parse_str("3i64 + 2.5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parsed_sum_is_structurally_eq_to_hand_built_segment() -> anyhow::Result<()> {
+        let parsed = CELParser::new(OpLookup::new())
+            .parse_str("1 + 2")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        // Constant folding compiles `1 + 2` to a single op pushing the sum.
+        let mut folded = DynSegment::new::<()>();
+        folded.just(3i32);
+        assert!(parsed.structurally_eq(&folded));
+
+        let mut unfolded = DynSegment::new::<()>();
+        unfolded.op0(|| 1i32);
+        unfolded.op0(|| 2i32);
+        unfolded.op2(|a: i32, b: i32| a + b)?;
+        assert!(!parsed.structurally_eq(&unfolded));
+        Ok(())
+    }
+
+    #[test]
+    fn failing_op_maps_to_operator_span() -> anyhow::Result<()> {
+        use annotate_snippets::Renderer;
+
+        let source = "1 / 0";
+        let mut ctx = CELParser::new(OpLookup::new())
+            .parse_str_ctx(source)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(ctx.op_spans().len(), ctx.op_count());
+        let error = ctx.call0::<i32>().unwrap_err();
+        let span = ctx.error_span(&error).expect("division op has a span");
+        assert_eq!(span, SourceSpan::new(1, 2, 1, 3));
+        assert_eq!(&source[span.to_byte_range(source)], "/");
+
+        let rendered = CELError::new(error.root_cause().to_string(), span).format_rustc_style(
+            source,
+            "test.cel",
+            1,
+            &Renderer::plain(),
+        );
+        assert!(rendered.contains("division by zero"), "{rendered}");
+        assert!(rendered.contains("  ^"), "{rendered}");
+
+        assert_eq!(ctx.error_span(&anyhow::anyhow!("not from a call")), None);
+        Ok(())
+    }
+
     #[test]
     fn float_literal_f64_suffix() {
         let mut parser = CELParser::new(OpLookup::new());
@@ -1361,6 +2188,37 @@ mod tests {
         assert!((value - 42.14f32).abs() < 1e-6);
     }
 
+    #[test]
+    fn float_literal_scientific_notation() {
+        let mut parser = CELParser::new(OpLookup::new());
+        assert_eq!(
+            parser.parse_str("1.5e3").unwrap().call0::<f64>().unwrap(),
+            1500.0
+        );
+        let value = parser
+            .parse_str("2.5e-3f32")
+            .unwrap()
+            .call0::<f32>()
+            .unwrap();
+        assert!((value - 2.5e-3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn float_literal_overflow_is_an_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (source, message) in [
+            ("1e309f64", "f64 literal `1e309f64` is out of range"),
+            ("1e309", "f64 literal `1e309` is out of range"),
+            ("1e39f32", "f32 literal `1e39f32` is out of range"),
+        ] {
+            let err = match parser.parse_str(source) {
+                Err(e) => e,
+                Ok(_) => panic!("expected `{source}` to be out of range"),
+            };
+            assert_eq!(err.message(), message);
+        }
+    }
+
     #[test]
     fn invalid_float_suffix() {
         let mut parser = CELParser::new(OpLookup::new());
@@ -1665,6 +2523,50 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn deep_nesting_is_a_clean_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for source in [
+            format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000)),
+            format!("{}1", "-".repeat(10_000)),
+            format!("2{}", " ** 2".repeat(10_000)),
+        ] {
+            let err = match parser.parse_str(&source) {
+                Err(e) => e,
+                Ok(_) => panic!("expected nesting error"),
+            };
+            assert_eq!(err.message(), "expression nesting too deep");
+        }
+        let nested = format!("{}1{}", "(".repeat(20), ")".repeat(20));
+        let mut seg = parser.parse_str(&nested).unwrap();
+        assert_eq!(seg.call0::<i32>().unwrap(), 1);
+    }
+
+    #[test]
+    fn cast_expression() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        let mut seg = parser.parse_str("7i32 as f64 / 2.0").unwrap();
+        assert_eq!(seg.call0::<f64>()?, 3.5);
+        let mut seg = parser.parse_str("-300i64 as i16 as i32 * 2").unwrap();
+        assert_eq!(seg.call0::<i32>()?, -600);
+
+        let mut seg = parser.parse_str("3000000000i64 as i32").unwrap();
+        let err = seg.call0::<i32>().unwrap_err();
+        assert!(format!("{err:#}").contains("cast from `i64` to `i32` failed"));
+
+        let err = match parser.parse_str("1i64 as f32") {
+            Err(e) => e,
+            Ok(_) => panic!("expected error for lossy float cast"),
+        };
+        assert_eq!(err.message(), "no operation `as f32` for types [`i64`]");
+        let err = match parser.parse_str("1 as") {
+            Err(e) => e,
+            Ok(_) => panic!("expected error for missing cast type"),
+        };
+        assert_eq!(err.message(), "expected type name after `as`");
+        Ok(())
+    }
+
     #[test]
     fn unary_expression() {
         let mut parser = CELParser::new(OpLookup::new());
@@ -1736,6 +2638,34 @@ mod tests {
         assert!(formatted.contains("^"));
     }
 
+    #[test]
+    fn compile_returns_segment_or_rendered_error() -> anyhow::Result<()> {
+        let mut segment = compile("2 * (3 + 4)").unwrap();
+        assert_eq!(segment.call0::<i32>()?, 14);
+
+        let error = compile("2 * (3 + )").err().unwrap();
+        assert!(error.starts_with("error: "), "{error}");
+        assert!(error.contains("<expression>:1:"), "{error}");
+        assert!(error.contains("1 | 2 * (3 + )"), "{error}");
+        Ok(())
+    }
+
+    #[test]
+    fn undefined_identifier_error_underlines_identifier() {
+        let source = "1 +  foo * 2";
+        let err = match CELParser::new(OpLookup::new()).parse_str(source) {
+            Err(e) => e,
+            Ok(_) => panic!("bare identifier must fail"),
+        };
+        assert_eq!(err.message(), "undefined identifier: `foo`");
+        let formatted = err.format_rustc_style(source, "test.cel", 1u32, &Renderer::plain());
+        assert!(formatted.contains("test.cel:1:6"));
+        assert!(
+            formatted.contains("1 | 1 +  foo * 2\n  |      ^^^"),
+            "{formatted}"
+        );
+    }
+
     #[test]
     fn error_formatting_with_line_offset() {
         let source = "10 + 20 30";
@@ -1897,6 +2827,108 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn not_equal_and_logical_not_do_not_mislex() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, expected) in [
+            ("1 != 2", true),
+            ("1!=1", false),
+            ("!true", false),
+            ("!!true", true),
+            ("(1 != 2) == !false", true),
+        ] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<bool>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn operator_info_matches_grammar() {
+        let info = |op| operator_info(op).expect("binary operator");
+        assert!(info("*").precedence > info("+").precedence);
+        assert_eq!(info("+").precedence, info("-").precedence);
+        assert!(info("&&").precedence > info("||").precedence);
+        assert!(info("**").precedence > info("%").precedence);
+        assert_eq!(info("==").associativity, Associativity::NonAssociative);
+        assert_eq!(info("**").associativity, Associativity::Right);
+        assert_eq!(info("<<").associativity, Associativity::Left);
+        assert_eq!(operator_info("!"), None);
+        assert_eq!(operator_info("as"), None);
+    }
+
+    #[test]
+    fn integer_literal_policy_types_unsuffixed_literals() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.set_integer_literal_policy(|_| IntegerType::I64);
+
+        let mut segment = parser
+            .parse_str("10")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i64>()?, 10);
+
+        let mut segment = parser
+            .parse_str("3000000000 - 1i64")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i64>()?, 2_999_999_999);
+
+        let mut segment = parser
+            .parse_str("10u8")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<u8>()?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_mode_selects_integer_arithmetic() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+
+        let mut segment = parser
+            .parse_str("200u8 + 100u8")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let message = format!("{:#}", segment.call0::<u8>().unwrap_err());
+        assert!(message.contains("arithmetic overflow"), "got: {message}");
+
+        parser.set_overflow_mode(OverflowMode::Wrap);
+        let mut segment = parser
+            .parse_str("200u8 + 100u8")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<u8>()?, 44);
+
+        parser.set_overflow_mode(OverflowMode::Panic);
+        let mut segment = parser
+            .parse_str("200u8 + 100u8")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = segment.call0::<u8>();
+        }))
+        .unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().map(String::as_str),
+            Some("arithmetic overflow")
+        );
+
+        let mut segment = parser
+            .parse_str("200u8 - 100u8 * 2u8")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<u8>()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn modulo_execution() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, expected) in [("7 % 3", 1), ("7%3", 1), ("-7 % 3", -1)] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_u32_addition_execution() -> anyhow::Result<()> {
         let mut parser = CELParser::new(OpLookup::new());
@@ -1930,13 +2962,177 @@ mod tests {
         });
         let mut parser = CELParser::new(lookup);
         let mut segment = parser
-            .parse_str("x + y")
+            .parse_str("x + y")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let result = segment.call0::<i32>()?;
+        assert_eq!(result, 30);
+        Ok(())
+    }
+
+    #[test]
+    fn map_field_access_errors_on_absent_key() -> anyhow::Result<()> {
+        use std::collections::HashMap;
+
+        let mut lookup = OpLookup::new();
+        lookup.push_scope(|name, segment, num_operands, _span| {
+            if name != "m" || num_operands != 0 {
+                return Ok(false);
+            }
+            segment.op0(|| HashMap::from([("a".to_string(), 1i64)]));
+            Ok(true)
+        });
+        let mut parser = CELParser::new(lookup);
+
+        let mut segment = parser
+            .parse_str("m.a + 1i64")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i64>()?, 2);
+
+        let mut segment = parser
+            .parse_str("m.b + 1i64")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let message = format!("{:#}", segment.call0::<i64>().unwrap_err());
+        assert!(message.contains("no key `b` in map"), "got: {message}");
+
+        let mut segment = parser
+            .parse_str("if has(m.b) { m.b } else { 0i64 }")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i64>()?, 0);
+
+        let mut segment = parser
+            .parse_str("has(m.a) && !has(m.b)")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert!(segment.call0::<bool>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn has_requires_field_access() {
+        let mut parser = CELParser::new(OpLookup::new());
+        let err = match parser.parse_str("has(1i32)") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for `has` without a field access"),
+        };
+        assert!(
+            err.message().contains("requires a field access"),
+            "{}",
+            err.message()
+        );
+
+        let err = match parser.parse_str("(1i32, 2i32).x") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for field access on a tuple"),
+        };
+        assert!(
+            err.message().contains("no operation `.x`"),
+            "{}",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn all_comprehension_over_list() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (source, expected) in [
+            ("[1, 2, 3].all(x, x > 0)", true),
+            ("[1, -2, 3].all(x, x > 0)", false),
+            ("[1, 2, 3].all(x, x > 0 && x < 3)", false),
+            ("[\"a\", \"b\"].all(s, s != \"\")", true),
+        ] {
+            let mut segment = parser
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<bool>()?, expected, "{source}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn exists_comprehension_over_list() -> anyhow::Result<()> {
+        let mut lookup = OpLookup::new();
+        lookup.register_list_type::<Vec<i32>>();
+        let mut parser = CELParser::new(lookup);
+        for (source, expected) in [
+            ("[1, 2, 3].exists(x, x == 2)", true),
+            ("[1, 2, 3].exists(x, x > 3)", false),
+            ("[[1, 2], [3]].exists(l, l.all(x, x > 2))", true),
+            ("[1, 2].exists(x, [3, 4].exists(y, x + y == 6))", true),
+        ] {
+            let mut segment = parser
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<bool>()?, expected, "{source}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn plus_concatenates_lists() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        let mut segment = parser
+            .parse_str("[1, 2] + [3, 4]")
             .map_err(|e| anyhow::anyhow!("{}", e))?;
-        let result = segment.call0::<i32>()?;
-        assert_eq!(result, 30);
+        assert_eq!(segment.call0::<Vec<i32>>()?, vec![1, 2, 3, 4]);
+
+        let mut segment = parser
+            .parse_str("([\"a\"] + [\"b\"] + [\"c\"]).all(s, s != \"\")")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert!(segment.call0::<bool>()?);
+
+        assert!(parser.parse_str("[1, 2] + [3i64]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn list_literal_accepts_trailing_comma() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for source in ["[1, 2, 3,]", "[1, 2, 3]"] {
+            let mut segment = parser
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<Vec<i32>>()?, vec![1, 2, 3], "{source}");
+        }
         Ok(())
     }
 
+    #[test]
+    fn list_literal_missing_element_is_error_at_the_extra_comma() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (source, column) in [("[1,,2]", 3), ("[,]", 1), ("[1,,]", 3)] {
+            let err = match parser.parse_str(source) {
+                Err(e) => e,
+                Ok(_) => panic!("expected parse error for `{source}`"),
+            };
+            assert_eq!(
+                err.message(),
+                "expected list element before `,`",
+                "{source}"
+            );
+            let start = err.span().start();
+            assert_eq!((start.line, start.column), (1, column), "{source}");
+        }
+    }
+
+    #[test]
+    fn comprehension_errors() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (source, expected) in [
+            (
+                "1i32.all(x, x > 0)",
+                "comprehension requires a list, found `i32`",
+            ),
+            ("[1, 2].all(x, x + 1)", "predicate must produce a `bool`"),
+            ("[1, 2i64]", "no operation `[]`"),
+            ("[]", "an empty list `[]` has no element type"),
+        ] {
+            let err = match parser.parse_str(source) {
+                Err(e) => e,
+                Ok(_) => panic!("expected parse error for `{source}`"),
+            };
+            assert!(err.message().contains(expected), "{}", err.message());
+        }
+    }
+
     #[test]
     fn test_undefined_identifier_error() {
         let mut parser = CELParser::new(OpLookup::new());
@@ -2004,6 +3200,67 @@ mod tests {
         Ok(())
     }
 
+    /// Parses the parenthesized `source` as an argument list into a fresh parser, returning the
+    /// parser and the indices `parse_argument_list` reported.
+    fn parse_arguments(source: &str) -> anyhow::Result<(CELParser, Vec<usize>)> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.set_tokens(TokenStream::from_str(source).unwrap().into_iter());
+        parser.advance(); // consume "("
+        let mut indices = Vec::new();
+        let count = parser
+            .parse_argument_list(Delimiter::Parenthesis, &mut |_, index, _| {
+                indices.push(index);
+                Ok(())
+            })
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(count, indices.len());
+        assert!(parser.peek_token().is_none(), "`)` must be consumed");
+        Ok((parser, indices))
+    }
+
+    #[test]
+    fn argument_list_of_zero_arguments() -> anyhow::Result<()> {
+        let (parser, indices) = parse_arguments("()")?;
+        assert!(indices.is_empty());
+        assert_eq!(parser.context.current_stack_offset(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn argument_list_of_one_argument() -> anyhow::Result<()> {
+        let (parser, indices) = parse_arguments("(40 + 2)")?;
+        assert_eq!(indices, [0]);
+        let types: Vec<_> = parser
+            .context
+            .peek_stack_infos(1)
+            .iter()
+            .map(|info| info.type_id)
+            .collect();
+        assert_eq!(types, [TypeId::of::<i32>()]);
+        Ok(())
+    }
+
+    #[test]
+    fn argument_list_of_three_arguments() -> anyhow::Result<()> {
+        let (parser, indices) = parse_arguments("(1, 2u8, !false)")?;
+        assert_eq!(indices, [0, 1, 2]);
+        let types: Vec<_> = parser
+            .context
+            .peek_stack_infos(3)
+            .iter()
+            .map(|info| info.type_id)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                TypeId::of::<i32>(),
+                TypeId::of::<u8>(),
+                TypeId::of::<bool>()
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn call_empty_arg_list() -> anyhow::Result<()> {
         let mut lookup = OpLookup::new();
@@ -2401,6 +3658,451 @@ mod tests {
         let result = parser.parse_or_expression();
         assert!(result.is_err(), "expected Err for empty input");
     }
+
+    #[test]
+    fn parser_is_reusable_across_token_streams() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        let first = TokenStream::from_str("1 + 2").unwrap();
+        let mut segment = parser
+            .parse_tokens(first.into_iter())
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 3);
+        let second = TokenStream::from_str("3 * 4").unwrap();
+        let mut segment = parser
+            .parse_tokens(second.into_iter())
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn precedence_lint_warns_on_bitwise_comparison_mix() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.set_precedence_lint(true);
+        let mut segment = parser
+            .parse_str("6 & 3 == 2")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert!(segment.call0::<bool>()?);
+        let messages: Vec<_> = parser.warnings().iter().map(ParseError::message).collect();
+        assert_eq!(
+            messages,
+            ["`&` binds tighter than `==`; add parentheses to clarify"]
+        );
+
+        for input in ["(6 & 3) == 2", "1 + 1 == 2", "6 & 3"] {
+            parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert!(parser.warnings().is_empty(), "{input}");
+        }
+
+        parser.set_precedence_lint(false);
+        parser
+            .parse_str("1 == 2 | 3")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert!(parser.warnings().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn empty_input_is_distinct_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for input in ["", "   \n"] {
+            let err = match parser.parse_str(input) {
+                Err(e) => e,
+                Ok(_) => panic!("empty input {input:?} must fail"),
+            };
+            assert_eq!(err.message(), "empty expression");
+        }
+        let err = match parser.parse_str("+") {
+            Err(e) => e,
+            Ok(_) => panic!("malformed input must fail"),
+        };
+        assert_ne!(err.message(), "empty expression");
+    }
+
+    #[test]
+    fn compiled_segment_records_op_labels() -> anyhow::Result<()> {
+        // A registered scope may shadow any operator, which keeps the operators from folding.
+        let mut lookup = OpLookup::new();
+        lookup.push_scope(|_, _, _, _| Ok(false));
+        let mut segment = CELParser::new(lookup)
+            .parse_str("10 + 20 * 3")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.op_labels(), ["just", "just", "just", "*", "+"]);
+        assert_eq!(segment.call0::<i32>()?, 70);
+        Ok(())
+    }
+
+    #[test]
+    fn conditional_with_null_branch_is_optional() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        let mut segment = parser.parse_str("true ? 5 : null").unwrap();
+        assert_eq!(segment.call0::<Option<i32>>()?, Some(5));
+        let mut segment = parser.parse_str("1 > 2 ? 5 : null").unwrap();
+        assert_eq!(segment.call0::<Option<i32>>()?, None);
+        let mut segment = parser
+            .parse_str(r#"false ? null : true ? "a" : null"#)
+            .unwrap();
+        assert_eq!(segment.call0::<Option<String>>()?, Some("a".to_string()));
+        let mut segment = parser.parse_str("if false { 1u8 } else { null }").unwrap();
+        assert_eq!(segment.call0::<Option<u8>>()?, None);
+        let mut segment = parser.parse_str("false ? 1 : 2").unwrap();
+        assert_eq!(segment.call0::<i32>()?, 2);
+
+        let err = match parser.parse_str("true ? 1") {
+            Err(e) => e,
+            Ok(_) => panic!("conditional without `:` must fail"),
+        };
+        assert_eq!(err.message(), "expected `:` in conditional expression");
+        Ok(())
+    }
+
+    #[test]
+    fn constant_operators_fold_to_one_op() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        let mut segment = parser.parse_str("2 + 3 * 4").unwrap();
+        assert_eq!(segment.op_count(), 1);
+        assert_eq!(segment.call0::<i32>()?, 14);
+
+        let mut segment = parser
+            .parse_str(r#"size("ab" + "c") == 3 && !false"#)
+            .unwrap();
+        // `&&` isn't folded, but each of its operands is: the left one to `true`, leaving one op
+        // for it and one joining the branches.
+        assert_eq!(segment.op_count(), 2);
+        assert!(segment.call0::<bool>()?);
+
+        // A failing operator is left for execution to report.
+        let mut segment = parser.parse_str("1 + 1 / 0").unwrap();
+        assert_eq!(segment.op_count(), 5);
+        assert!(segment.call0::<i32>().is_err());
+
+        // Any registered scope may shadow an operator, so nothing is folded.
+        let mut lookup = OpLookup::new();
+        lookup.push_scope(|_, _, _, _| Ok(false));
+        let mut segment = CELParser::new(lookup).parse_str("2 + 3 * 4").unwrap();
+        assert_eq!(segment.op_count(), 5);
+        assert_eq!(segment.call0::<i32>()?, 14);
+        Ok(())
+    }
+
+    #[test]
+    fn string_addition_concatenates() -> anyhow::Result<()> {
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str(r#""foo" + "bar" + "!""#)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<String>()?, "foobar!");
+        Ok(())
+    }
+
+    #[test]
+    fn string_addition_with_integer_is_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        let err = match parser.parse_str(r#""foo" + 1"#) {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for string plus integer"),
+        };
+        assert!(err.message().contains("no operation"), "{}", err.message());
+    }
+
+    #[test]
+    fn string_comparison_uses_ordering() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, expected) in [
+            (r#""a" < "b""#, true),
+            (r#""b" <= "a""#, false),
+            (r#""a" == "a""#, true),
+            (r#""a" != "a""#, false),
+        ] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<bool>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bool_ordering_comparison_is_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        let err = match parser.parse_str("true < false") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for ordering bools"),
+        };
+        assert!(err.message().contains("no operation"), "{}", err.message());
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() -> anyhow::Result<()> {
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str("2 - 3 - 4")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, -5);
+        Ok(())
+    }
+
+    #[test]
+    fn exponentiation_is_right_associative() -> anyhow::Result<()> {
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str("2 ** 3 ** 2")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 512);
+        Ok(())
+    }
+
+    #[test]
+    fn exponentiation_binds_tighter_than_multiplication() -> anyhow::Result<()> {
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str("2 * 3 ** 2")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 18);
+        Ok(())
+    }
+
+    #[test]
+    fn exponentiation_binds_tighter_than_prefix_minus() -> anyhow::Result<()> {
+        for (source, expected) in [
+            ("-2 ** 2", -4),
+            ("(-2) ** 2", 4),
+            ("-2 ** 2 ** 3", -256),
+            ("--2 ** 2", 4),
+            ("-3 ** 2 * 2", -18),
+        ] {
+            let mut segment = CELParser::new(OpLookup::new())
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{source}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn exponentiation_with_negative_exponent_is_runtime_error() -> anyhow::Result<()> {
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str("2 ** -1")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let message = format!("{:#}", segment.call0::<i32>().unwrap_err());
+        assert!(message.contains("exponent out of range"), "got: {message}");
+        Ok(())
+    }
+
+    #[test]
+    fn missing_exponent_reports_power_expression() {
+        let mut parser = CELParser::new(OpLookup::new());
+        let err = match parser.parse_str("2 **") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for missing exponent"),
+        };
+        assert_eq!(err.message(), "expected power_expression");
+    }
+
+    #[test]
+    fn elvis_selects_left_operand_unless_false() -> anyhow::Result<()> {
+        for (source, expected) in [
+            ("true ?: false", true),
+            ("false ?: true", true),
+            ("false ?: false", false),
+            ("false ?: false ?: true", true),
+        ] {
+            let mut segment = CELParser::new(OpLookup::new())
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<bool>()?, expected, "{source}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn elvis_tests_truthiness_of_non_bool_operands() -> anyhow::Result<()> {
+        for (source, expected) in [("0 ?: 5", 5), ("3 ?: 5", 3), ("0 ?: 0 ?: 7", 7)] {
+            let mut segment = CELParser::new(OpLookup::new())
+                .parse_str(source)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{source}");
+        }
+        let mut segment = CELParser::new(OpLookup::new())
+            .parse_str(r#""" ?: "fallback""#)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<String>()?, "fallback");
+        Ok(())
+    }
+
+    #[test]
+    fn elvis_with_fallback_of_another_type_is_error() {
+        for source in ["true ?: 5", "false ?: 5"] {
+            let mut parser = CELParser::new(OpLookup::new());
+            let err = match parser.parse_str(source) {
+                Err(e) => e,
+                Ok(_) => panic!("expected parse error for `{source}`"),
+            };
+            assert_eq!(
+                err.message(),
+                "fallback must produce `bool`, but produces `i32`"
+            );
+        }
+    }
+
+    #[test]
+    fn size_counts_string_characters() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, expected) in [(r#"size("abc")"#, 3), (r#"size("héllo" + "!")"#, 6)] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn size_counts_list_elements() -> anyhow::Result<()> {
+        let mut lookup = OpLookup::new();
+        lookup.register_list_type::<Vec<i32>>();
+        let mut parser = CELParser::new(lookup);
+        for (input, expected) in [
+            ("size([1, 2, 3])", 3),
+            (r#"size(["a"] + ["b", "c"]) + 1"#, 4),
+            ("size([[1, 2], [3]])", 2),
+        ] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn size_of_unsized_type_is_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        let err = match parser.parse_str("size(3)") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for size of an integer"),
+        };
+        assert_eq!(err.message(), "no operation `size` for types [`i32`]");
+    }
+
+    #[test]
+    fn let_binding_is_reused_as_a_group() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, expected) in [
+            ("let x = 2 + 3; x * x", 25),
+            ("let x = 2; let y = x * 10; let x = 5; y + x", 25),
+            ("let t = (1, 2); t.0 + t.1", 3),
+        ] {
+            let mut segment = parser
+                .parse_str(input)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            assert_eq!(segment.call0::<i32>()?, expected, "{input}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn let_binding_is_compiled_once() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.register_function("one", 0, |seg, _span| {
+            seg.op0(|| 1i64);
+            Ok(())
+        });
+        // Each binding refers to the one before it twice, so parsing a binding again for each
+        // reference would take 2^64 steps.
+        let mut source = String::from("let b0 = one();");
+        for i in 1..64 {
+            source += &format!(" let b{i} = b{} + b{};", i - 1, i - 1);
+        }
+        source += " b63";
+        let segment = parser
+            .parse_str(&source)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.op_count(), 1);
+
+        let mut segment = parser
+            .parse_str("let a = one(); let b = a + a; let c = b * b; c + a")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i64>()?, 5);
+
+        // A constant binding still folds with the operators applied to it.
+        let segment = parser
+            .parse_str("let x = 2 + 3; x * x")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.op_count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn let_binding_errors() {
+        let mut parser = CELParser::new(OpLookup::new());
+        for (input, message) in [
+            ("let = 1; 2", "expected identifier after `let`"),
+            ("let x 1; x", "expected `=` after let binding name"),
+            ("let x = 1 x", "expected `;` after let binding"),
+            ("let x = ; x", "expected expression in let binding"),
+            (
+                "let x = 1 + true; 2",
+                "no operation `+` for types [`i32`, `bool`]",
+            ),
+            ("let x = 1; y", "undefined identifier: `y`"),
+        ] {
+            let err = match parser.parse_str(input) {
+                Err(e) => e,
+                Ok(_) => panic!("expected parse error for {input:?}"),
+            };
+            assert_eq!(err.message(), message, "{input}");
+        }
+    }
+
+    #[test]
+    fn registered_function_call_executes() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.register_function("double", 1, |seg, _span| seg.op1(|x: i32| x * 2));
+        let mut segment = parser
+            .parse_str("double(21)")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn registered_function_call_composes_with_operators() -> anyhow::Result<()> {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.register_function("double", 1, |seg, _span| seg.op1(|x: i32| x * 2));
+        parser.register_function("now", 0, |seg, _span| {
+            seg.op0(|| 100i32);
+            Ok(())
+        });
+        let mut segment = parser
+            .parse_str("double(now() + 1) - 2")
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        assert_eq!(segment.call0::<i32>()?, 200);
+        Ok(())
+    }
+
+    #[test]
+    fn registered_function_wrong_argument_count_is_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.register_function("double", 1, |seg, _span| seg.op1(|x: i32| x * 2));
+        let err = match parser.parse_str("double(1, 2)") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for wrong argument count"),
+        };
+        assert_eq!(
+            err.message(),
+            "function `double` expects 1 argument(s), got 2"
+        );
+    }
+
+    #[test]
+    fn registered_function_without_call_is_error() {
+        let mut parser = CELParser::new(OpLookup::new());
+        parser.register_function("double", 1, |seg, _span| seg.op1(|x: i32| x * 2));
+        let err = match parser.parse_str("double + 1") {
+            Err(e) => e,
+            Ok(_) => panic!("expected parse error for function name without call"),
+        };
+        assert_eq!(err.message(), "expected `(` after function `double`");
+    }
 }
 
 #[cfg(test)]
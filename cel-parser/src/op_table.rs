@@ -21,16 +21,34 @@
 //!
 //! Built-in operations follow Rust language semantics. Deviations are:
 //!
-//! - **Signed integer overflow**: CEL returns `Err` rather than panicking (debug) or wrapping
-//!   (release). Use wrapping arithmetic explicitly if overflow is intended.
+//! - **Integer overflow**: binary `+`, `-`, and `*` return `Err` on overflow rather than
+//!   panicking (debug) or wrapping (release). Select another [`OverflowMode`] with
+//!   [`OpLookup::set_overflow_mode`] to wrap or panic instead, or call `wrapping_add`,
+//!   `wrapping_sub`, or `wrapping_mul` explicitly if overflow is intended.
 //! - **Bit-shift with out-of-range count**: CEL returns `Err` rather than panicking (debug)
 //!   or masking the shift count (release).
+//! - **`as` casts**: CEL returns `Err` for a value the target type can't represent rather than
+//!   truncating, and float targets only accept exactly representable source types. A cast is
+//!   the unary operator `"as <type>"` (e.g. `"as i32"`).
+//! - **Map field access**: `m.key` is the unary operator `".key"` and returns `Err` if `key` is
+//!   absent; `has(m.key)` is the unary operator `"has .key"` and returns a `bool`. Both apply to
+//!   a `HashMap<String, V>` whose `V` is a built-in scalar type or was registered with
+//!   [`OpLookup::register_map_type`].
+//! - **Mixed integer and `f64` arithmetic**: binary `+`, `-`, `*`, and `/` with one `f64` operand
+//!   and one integer operand that `f64` represents exactly (up to 32 bits) convert the integer to
+//!   `f64` first, so `3 + 2.5` is `5.5f64`. Wider integers are not promoted.
+//! - **List literals**: `[a, b, ...]` builds a `Vec<T>` with the operator `"[]"`, which wraps a
+//!   single `T` operand in a new list, or appends a `T` to a `Vec<T>` operand. `T` must be a
+//!   built-in scalar type or registered with [`OpLookup::register_list_type`]; only such lists
+//!   support the `all`/`exists` comprehensions and concatenation with binary `+`.
 
 use anyhow::{Result, anyhow};
-use cel_runtime::{DynSegment, DynTuple};
+use cel_runtime::ops::{CheckedArithmetic, WrappingArithmetic};
+use cel_runtime::{DynSegment, DynTuple, ElementRef, Quantifier, ops, promote_operands_to_f64};
 use once_cell::sync::Lazy;
 use phf::phf_map;
 use std::any::TypeId;
+use std::collections::HashMap;
 
 use crate::SourceSpan;
 
@@ -90,6 +108,135 @@ pub struct TupleOpSignature {
     pub op_fn: OpFn,
 }
 
+/// A function that pushes a field access (or presence test, if the `bool` is `true`) of the
+/// given key onto a DynSegment, for one registered map type.
+type MapFieldFn = fn(&mut DynSegment, String, bool, SourceSpan) -> Result<()>;
+
+/// A `HashMap<String, V>` type whose fields can be accessed with `.key` and tested with `has`.
+struct MapTypeSignature {
+    /// `TypeId` of the map type.
+    type_id: TypeId,
+    /// Function that pushes the access or presence test onto the segment.
+    field_fn: MapFieldFn,
+}
+
+/// Returns the signature for `HashMap<String, V>`.
+fn map_type_signature<V: 'static>() -> MapTypeSignature {
+    MapTypeSignature {
+        type_id: TypeId::of::<HashMap<String, V>>(),
+        field_fn: map_field::<V>,
+    }
+}
+
+/// Maps of the built-in scalar types, searched after the map types registered on an `OpLookup`.
+static BUILTIN_MAP_TYPES: Lazy<Vec<MapTypeSignature>> = Lazy::new(|| {
+    vec![
+        map_type_signature::<u8>(),
+        map_type_signature::<u16>(),
+        map_type_signature::<u32>(),
+        map_type_signature::<u64>(),
+        map_type_signature::<u128>(),
+        map_type_signature::<usize>(),
+        map_type_signature::<i8>(),
+        map_type_signature::<i16>(),
+        map_type_signature::<i32>(),
+        map_type_signature::<i64>(),
+        map_type_signature::<i128>(),
+        map_type_signature::<isize>(),
+        map_type_signature::<f32>(),
+        map_type_signature::<f64>(),
+        map_type_signature::<bool>(),
+        map_type_signature::<String>(),
+    ]
+});
+
+/// Pushes an access of `key` in a `HashMap<String, V>` (an error if absent), or, if `presence`,
+/// a test of whether `key` is present.
+fn map_field<V: 'static>(
+    segment: &mut DynSegment,
+    key: String,
+    presence: bool,
+    span: SourceSpan,
+) -> Result<()> {
+    if presence {
+        return segment.op1(move |map: HashMap<String, V>| map.contains_key(&key));
+    }
+    segment.op1r(move |mut map: HashMap<String, V>| {
+        map.remove(&key)
+            .ok_or_else(|| span_err(span, anyhow!("no key `{key}` in map")))
+    })
+}
+
+/// A `Vec<T>` type that list literals build, `+` concatenates, `size` counts, and the
+/// `all`/`exists` comprehensions iterate.
+struct ListTypeSignature {
+    /// `TypeId` of the element type `T`.
+    element_type_id: TypeId,
+    /// `TypeId` of the list type `Vec<T>`.
+    list_type_id: TypeId,
+    /// Pushes a list literal's `"[]"` op for 1 (wrap) or 2 (append) operands.
+    element_fn: fn(&mut DynSegment, usize) -> Result<()>,
+    /// Pushes the `+` op concatenating two lists.
+    concat_fn: fn(&mut DynSegment) -> Result<()>,
+    /// Pushes the `size` op counting a list's elements.
+    size_fn: fn(&mut DynSegment) -> Result<()>,
+    /// Creates the fragment a predicate over the list on top of the segment is built in.
+    fragment_fn: fn(&DynSegment) -> (DynSegment, ElementRef),
+    /// Folds a predicate fragment over the list on top of the segment.
+    quantify_fn: fn(&mut DynSegment, DynSegment, Quantifier) -> Result<()>,
+}
+
+/// Returns the signature for `Vec<T>`.
+fn list_type_signature<T: Clone + 'static>() -> ListTypeSignature {
+    ListTypeSignature {
+        element_type_id: TypeId::of::<T>(),
+        list_type_id: TypeId::of::<Vec<T>>(),
+        element_fn: list_element::<T>,
+        concat_fn: |segment| segment.op2(ops::concat::<T>()),
+        size_fn: |segment| {
+            segment.op1r(|list: Vec<T>| {
+                i32::try_from(list.len()).map_err(|_| anyhow!("size out of range"))
+            })
+        },
+        fragment_fn: DynSegment::new_element_fragment::<T>,
+        quantify_fn: DynSegment::quantify::<T>,
+    }
+}
+
+/// Lists of the built-in scalar types, searched after the list types registered on an `OpLookup`.
+static BUILTIN_LIST_TYPES: Lazy<Vec<ListTypeSignature>> = Lazy::new(|| {
+    vec![
+        list_type_signature::<u8>(),
+        list_type_signature::<u16>(),
+        list_type_signature::<u32>(),
+        list_type_signature::<u64>(),
+        list_type_signature::<u128>(),
+        list_type_signature::<usize>(),
+        list_type_signature::<i8>(),
+        list_type_signature::<i16>(),
+        list_type_signature::<i32>(),
+        list_type_signature::<i64>(),
+        list_type_signature::<i128>(),
+        list_type_signature::<isize>(),
+        list_type_signature::<f32>(),
+        list_type_signature::<f64>(),
+        list_type_signature::<bool>(),
+        list_type_signature::<String>(),
+    ]
+});
+
+/// Pushes a list literal element: wraps a `T` in a new list, or appends it to a `Vec<T>`.
+fn list_element<T: 'static>(segment: &mut DynSegment, num_operands: usize) -> Result<()> {
+    if num_operands == 1 {
+        segment.op1(|element: T| vec![element])
+    } else {
+        segment.op2(|mut list: Vec<T>, element: T| {
+            list.push(element);
+            list
+        })
+    }
+}
+
 /// A scope function that attempts to resolve and apply an operation.
 ///
 /// Receives the operation name, the segment, the number of operands on top of the stack,
@@ -102,6 +249,22 @@ pub struct TupleOpSignature {
 pub type ScopeFn =
     Box<dyn Fn(&str, &mut DynSegment, usize, SourceSpan) -> Result<bool> + Send + Sync>;
 
+/// A function that pushes a registered named function's operation onto a DynSegment.
+///
+/// Receives the segment, with the function's arguments on top of the stack in source order, and
+/// the source span of the call. Unlike [`OpFn`], this may capture state.
+pub type FunctionFn = Box<dyn Fn(&mut DynSegment, SourceSpan) -> Result<()> + Send + Sync>;
+
+/// A named function registered via [`OpLookup::register_function`].
+struct FunctionSignature {
+    /// Function name as written at the call site.
+    name: String,
+    /// Number of arguments the function accepts.
+    arity: usize,
+    /// Function that pushes the operation onto the segment.
+    push_fn: FunctionFn,
+}
+
 /// A signature for a built-in operation.
 ///
 /// For homogeneous ops (e.g. `u32 + u32`) `rhs_type_id_index` equals `type_id_index`.
@@ -196,48 +359,9 @@ macro_rules! sig_het {
     };
 }
 
-// Addition signatures
+// Addition signatures. Integer `+`, `-`, and `*` are applied by `lookup_integer_arithmetic` with
+// the lookup's overflow mode, so these tables hold only the other operand types.
 static ADD_SIGNATURES: &[OpSignature] = &[
-    sig!(TYPE_U8, 2, |seg, _span| seg
-        .op2(|a: u8, b: u8| a.wrapping_add(b))),
-    sig!(TYPE_U16, 2, |seg, _span| seg
-        .op2(|a: u16, b: u16| a.wrapping_add(b))),
-    sig!(TYPE_U32, 2, |seg, _span| seg
-        .op2(|a: u32, b: u32| a.wrapping_add(b))),
-    sig!(TYPE_U64, 2, |seg, _span| seg
-        .op2(|a: u64, b: u64| a.wrapping_add(b))),
-    sig!(TYPE_U128, 2, |seg, _span| seg
-        .op2(|a: u128, b: u128| a.wrapping_add(b))),
-    sig!(TYPE_USIZE, 2, |seg, _span| seg
-        .op2(|a: usize, b: usize| a.wrapping_add(b))),
-    sig!(TYPE_I8, 2, |seg, span| seg.op2r(move |a: i8, b: i8| a
-        .checked_add(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I16, 2, |seg, span| seg.op2r(move |a: i16, b: i16| a
-        .checked_add(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I32, 2, |seg, span| seg.op2r(move |a: i32, b: i32| a
-        .checked_add(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I64, 2, |seg, span| seg.op2r(move |a: i64, b: i64| a
-        .checked_add(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I128, 2, |seg, span| seg.op2r(
-        move |a: i128, b: i128| a
-            .checked_add(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
-    sig!(TYPE_ISIZE, 2, |seg, span| seg.op2r(
-        move |a: isize, b: isize| a
-            .checked_add(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
     sig!(TYPE_F32, 2, |seg, _span| seg.op2(|a: f32, b: f32| a + b)),
     sig!(TYPE_F64, 2, |seg, _span| seg.op2(|a: f64, b: f64| a + b)),
     sig!(TYPE_STR, 2, |seg, _span| seg
@@ -247,46 +371,6 @@ static ADD_SIGNATURES: &[OpSignature] = &[
 // Subtraction signatures (both binary and unary)
 static SUB_SIGNATURES: &[OpSignature] = &[
     // Binary subtraction
-    sig!(TYPE_U8, 2, |seg, _span| seg
-        .op2(|a: u8, b: u8| a.wrapping_sub(b))),
-    sig!(TYPE_U16, 2, |seg, _span| seg
-        .op2(|a: u16, b: u16| a.wrapping_sub(b))),
-    sig!(TYPE_U32, 2, |seg, _span| seg
-        .op2(|a: u32, b: u32| a.wrapping_sub(b))),
-    sig!(TYPE_U64, 2, |seg, _span| seg
-        .op2(|a: u64, b: u64| a.wrapping_sub(b))),
-    sig!(TYPE_U128, 2, |seg, _span| seg
-        .op2(|a: u128, b: u128| a.wrapping_sub(b))),
-    sig!(TYPE_USIZE, 2, |seg, _span| seg
-        .op2(|a: usize, b: usize| a.wrapping_sub(b))),
-    sig!(TYPE_I8, 2, |seg, span| seg.op2r(move |a: i8, b: i8| a
-        .checked_sub(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I16, 2, |seg, span| seg.op2r(move |a: i16, b: i16| a
-        .checked_sub(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I32, 2, |seg, span| seg.op2r(move |a: i32, b: i32| a
-        .checked_sub(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I64, 2, |seg, span| seg.op2r(move |a: i64, b: i64| a
-        .checked_sub(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I128, 2, |seg, span| seg.op2r(
-        move |a: i128, b: i128| a
-            .checked_sub(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
-    sig!(TYPE_ISIZE, 2, |seg, span| seg.op2r(
-        move |a: isize, b: isize| a
-            .checked_sub(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
     sig!(TYPE_F32, 2, |seg, _span| seg.op2(|a: f32, b: f32| a - b)),
     sig!(TYPE_F64, 2, |seg, _span| seg.op2(|a: f64, b: f64| a - b)),
     // Unary negation
@@ -320,50 +404,47 @@ static SUB_SIGNATURES: &[OpSignature] = &[
 
 // Multiplication signatures
 static MUL_SIGNATURES: &[OpSignature] = &[
-    sig!(TYPE_U8, 2, |seg, _span| seg
-        .op2(|a: u8, b: u8| a.wrapping_mul(b))),
-    sig!(TYPE_U16, 2, |seg, _span| seg
-        .op2(|a: u16, b: u16| a.wrapping_mul(b))),
-    sig!(TYPE_U32, 2, |seg, _span| seg
-        .op2(|a: u32, b: u32| a.wrapping_mul(b))),
-    sig!(TYPE_U64, 2, |seg, _span| seg
-        .op2(|a: u64, b: u64| a.wrapping_mul(b))),
-    sig!(TYPE_U128, 2, |seg, _span| seg
-        .op2(|a: u128, b: u128| a.wrapping_mul(b))),
-    sig!(TYPE_USIZE, 2, |seg, _span| seg
-        .op2(|a: usize, b: usize| a.wrapping_mul(b))),
-    sig!(TYPE_I8, 2, |seg, span| seg.op2r(move |a: i8, b: i8| a
-        .checked_mul(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I16, 2, |seg, span| seg.op2r(move |a: i16, b: i16| a
-        .checked_mul(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I32, 2, |seg, span| seg.op2r(move |a: i32, b: i32| a
-        .checked_mul(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I64, 2, |seg, span| seg.op2r(move |a: i64, b: i64| a
-        .checked_mul(b)
-        .ok_or_else(|| anyhow!("arithmetic overflow"))
-        .map_err(|e| span_err(span, e)))),
-    sig!(TYPE_I128, 2, |seg, span| seg.op2r(
-        move |a: i128, b: i128| a
-            .checked_mul(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
-    sig!(TYPE_ISIZE, 2, |seg, span| seg.op2r(
-        move |a: isize, b: isize| a
-            .checked_mul(b)
-            .ok_or_else(|| anyhow!("arithmetic overflow"))
-            .map_err(|e| span_err(span, e))
-    )),
     sig!(TYPE_F32, 2, |seg, _span| seg.op2(|a: f32, b: f32| a * b)),
     sig!(TYPE_F64, 2, |seg, _span| seg.op2(|a: f64, b: f64| a * b)),
 ];
 
+// Exponentiation signatures
+//
+// Integer exponentiation uses `checked_pow` via `op2r`. The exponent must fit a `u32`, so a
+// negative or oversized exponent is an error rather than a truncation.
+macro_rules! pow_sig {
+    ($type_idx:expr, $ty:ty) => {
+        sig!($type_idx, 2, |seg, span| seg.op2r(move |a: $ty, b: $ty| {
+            u32::try_from(b)
+                .map_err(|_| anyhow!("exponent out of range"))
+                .and_then(|e| {
+                    a.checked_pow(e)
+                        .ok_or_else(|| anyhow!("arithmetic overflow"))
+                })
+                .map_err(|e| span_err(span, e))
+        }))
+    };
+}
+
+static POW_SIGNATURES: &[OpSignature] = &[
+    pow_sig!(TYPE_U8, u8),
+    pow_sig!(TYPE_U16, u16),
+    pow_sig!(TYPE_U32, u32),
+    pow_sig!(TYPE_U64, u64),
+    pow_sig!(TYPE_U128, u128),
+    pow_sig!(TYPE_USIZE, usize),
+    pow_sig!(TYPE_I8, i8),
+    pow_sig!(TYPE_I16, i16),
+    pow_sig!(TYPE_I32, i32),
+    pow_sig!(TYPE_I64, i64),
+    pow_sig!(TYPE_I128, i128),
+    pow_sig!(TYPE_ISIZE, isize),
+    sig!(TYPE_F32, 2, |seg, _span| seg
+        .op2(|a: f32, b: f32| a.powf(b))),
+    sig!(TYPE_F64, 2, |seg, _span| seg
+        .op2(|a: f64, b: f64| a.powf(b))),
+];
+
 // Division signatures
 //
 // Integer division uses `checked_div` via `op2r` so that division by zero returns an error
@@ -916,6 +997,256 @@ static GREATER_THAN_OR_EQUAL_SIGNATURES: &[OpSignature] = &[
         .op2(|a: String, b: String| a >= b)),
 ];
 
+// Size signatures, one per sized argument type
+static SIZE_SIGNATURES: &[OpSignature] =
+    &[sig!(TYPE_STR, 1, |seg, _span| seg.op1r(|s: String| {
+        i32::try_from(s.chars().count()).map_err(|_| anyhow::anyhow!("size out of range"))
+    }))];
+
+// Wrapping addition signatures, integer types only
+static WRAPPING_ADD_SIGNATURES: &[OpSignature] = &[
+    sig!(TYPE_U8, 2, |seg, _span| seg.op2(ops::wrapping_add::<u8>())),
+    sig!(TYPE_U16, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<u16>())),
+    sig!(TYPE_U32, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<u32>())),
+    sig!(TYPE_U64, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<u64>())),
+    sig!(TYPE_U128, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<u128>())),
+    sig!(TYPE_USIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<usize>())),
+    sig!(TYPE_I8, 2, |seg, _span| seg.op2(ops::wrapping_add::<i8>())),
+    sig!(TYPE_I16, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<i16>())),
+    sig!(TYPE_I32, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<i32>())),
+    sig!(TYPE_I64, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<i64>())),
+    sig!(TYPE_I128, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<i128>())),
+    sig!(TYPE_ISIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_add::<isize>())),
+];
+
+// Wrapping subtraction signatures, integer types only
+static WRAPPING_SUB_SIGNATURES: &[OpSignature] = &[
+    sig!(TYPE_U8, 2, |seg, _span| seg.op2(ops::wrapping_sub::<u8>())),
+    sig!(TYPE_U16, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<u16>())),
+    sig!(TYPE_U32, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<u32>())),
+    sig!(TYPE_U64, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<u64>())),
+    sig!(TYPE_U128, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<u128>())),
+    sig!(TYPE_USIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<usize>())),
+    sig!(TYPE_I8, 2, |seg, _span| seg.op2(ops::wrapping_sub::<i8>())),
+    sig!(TYPE_I16, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<i16>())),
+    sig!(TYPE_I32, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<i32>())),
+    sig!(TYPE_I64, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<i64>())),
+    sig!(TYPE_I128, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<i128>())),
+    sig!(TYPE_ISIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_sub::<isize>())),
+];
+
+// Wrapping multiplication signatures, integer types only
+static WRAPPING_MUL_SIGNATURES: &[OpSignature] = &[
+    sig!(TYPE_U8, 2, |seg, _span| seg.op2(ops::wrapping_mul::<u8>())),
+    sig!(TYPE_U16, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<u16>())),
+    sig!(TYPE_U32, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<u32>())),
+    sig!(TYPE_U64, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<u64>())),
+    sig!(TYPE_U128, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<u128>())),
+    sig!(TYPE_USIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<usize>())),
+    sig!(TYPE_I8, 2, |seg, _span| seg.op2(ops::wrapping_mul::<i8>())),
+    sig!(TYPE_I16, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<i16>())),
+    sig!(TYPE_I32, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<i32>())),
+    sig!(TYPE_I64, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<i64>())),
+    sig!(TYPE_I128, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<i128>())),
+    sig!(TYPE_ISIZE, 2, |seg, _span| seg
+        .op2(ops::wrapping_mul::<isize>())),
+];
+
+// Helper macros for `as` cast signatures: `cast_signatures!` builds one checked conversion to `$to`
+// per listed source type; `integer_cast_signatures!` lists every integer source type.
+macro_rules! cast_signatures {
+    ($to:ty; $($from_idx:expr => $from:ty),* $(,)?) => {
+        &[$(sig!($from_idx, 1, |seg, span| seg.op1r(move |a: $from| {
+            ops::cast::<$from, $to>()(a).map_err(|e| span_err(span, e))
+        }))),*]
+    };
+}
+
+macro_rules! integer_cast_signatures {
+    ($to:ty) => {
+        cast_signatures!(
+            $to;
+            TYPE_U8 => u8,
+            TYPE_U16 => u16,
+            TYPE_U32 => u32,
+            TYPE_U64 => u64,
+            TYPE_U128 => u128,
+            TYPE_USIZE => usize,
+            TYPE_I8 => i8,
+            TYPE_I16 => i16,
+            TYPE_I32 => i32,
+            TYPE_I64 => i64,
+            TYPE_I128 => i128,
+            TYPE_ISIZE => isize,
+        )
+    };
+}
+
+// Cast signatures, one table per target type, registered as `"as <type>"`. Integer targets accept
+// every integer source; float targets accept only sources they represent exactly.
+static CAST_U8_SIGNATURES: &[OpSignature] = integer_cast_signatures!(u8);
+static CAST_U16_SIGNATURES: &[OpSignature] = integer_cast_signatures!(u16);
+static CAST_U32_SIGNATURES: &[OpSignature] = integer_cast_signatures!(u32);
+static CAST_U64_SIGNATURES: &[OpSignature] = integer_cast_signatures!(u64);
+static CAST_U128_SIGNATURES: &[OpSignature] = integer_cast_signatures!(u128);
+static CAST_USIZE_SIGNATURES: &[OpSignature] = integer_cast_signatures!(usize);
+static CAST_I8_SIGNATURES: &[OpSignature] = integer_cast_signatures!(i8);
+static CAST_I16_SIGNATURES: &[OpSignature] = integer_cast_signatures!(i16);
+static CAST_I32_SIGNATURES: &[OpSignature] = integer_cast_signatures!(i32);
+static CAST_I64_SIGNATURES: &[OpSignature] = integer_cast_signatures!(i64);
+static CAST_I128_SIGNATURES: &[OpSignature] = integer_cast_signatures!(i128);
+static CAST_ISIZE_SIGNATURES: &[OpSignature] = integer_cast_signatures!(isize);
+static CAST_F32_SIGNATURES: &[OpSignature] = cast_signatures!(
+    f32;
+    TYPE_U8 => u8,
+    TYPE_U16 => u16,
+    TYPE_I8 => i8,
+    TYPE_I16 => i16,
+    TYPE_F32 => f32,
+);
+static CAST_F64_SIGNATURES: &[OpSignature] = cast_signatures!(
+    f64;
+    TYPE_U8 => u8,
+    TYPE_U16 => u16,
+    TYPE_U32 => u32,
+    TYPE_I8 => i8,
+    TYPE_I16 => i16,
+    TYPE_I32 => i32,
+    TYPE_F32 => f32,
+    TYPE_F64 => f64,
+);
+
+/// Built-in functions, called as `name(args...)`, with their arity. Each is dispatched on its
+/// argument types through [`BUILTINS`] like an operator; a registered function of the same name
+/// shadows it.
+const BUILTIN_FUNCTIONS: &[(&str, usize)] = &[
+    ("size", 1),
+    ("wrapping_add", 2),
+    ("wrapping_sub", 2),
+    ("wrapping_mul", 2),
+];
+
+/// What binary `+`, `-`, and `*` do when an integer result overflows its type. Set with
+/// [`OpLookup::set_overflow_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowMode {
+    /// Wrap around at the boundary of the type.
+    Wrap,
+    /// Fail with an `arithmetic overflow` error.
+    #[default]
+    Checked,
+    /// Panic with `arithmetic overflow`, in release builds as well as debug builds.
+    Panic,
+}
+
+/// The integer arithmetic operators whose overflow behavior [`OverflowMode`] selects.
+const OVERFLOW_OPERATORS: &[&str] = &["+", "-", "*"];
+
+/// Pushes the integer operator `name` (one of [`OVERFLOW_OPERATORS`]) on two `T`s, using the
+/// `cel_runtime::ops` factory `mode` selects.
+fn push_integer_arithmetic<T: WrappingArithmetic + CheckedArithmetic>(
+    name: &'static str,
+    mode: OverflowMode,
+    segment: &mut DynSegment,
+    span: SourceSpan,
+) -> Result<()> {
+    let (wrapping, checked, panicking) = match name {
+        "+" => (
+            ops::wrapping_add::<T>(),
+            ops::checked_add::<T>(),
+            ops::panicking_add::<T>(),
+        ),
+        "-" => (
+            ops::wrapping_sub::<T>(),
+            ops::checked_sub::<T>(),
+            ops::panicking_sub::<T>(),
+        ),
+        _ => (
+            ops::wrapping_mul::<T>(),
+            ops::checked_mul::<T>(),
+            ops::panicking_mul::<T>(),
+        ),
+    };
+    match mode {
+        OverflowMode::Wrap => segment.op2(wrapping),
+        OverflowMode::Checked => {
+            segment.op2r(move |a: T, b: T| checked(a, b).map_err(|e| span_err(span, e)))
+        }
+        OverflowMode::Panic => segment.op2(panicking),
+    }
+}
+
+/// Applies the integer operator `name` with `mode`'s overflow behavior if `name` is one of
+/// [`OVERFLOW_OPERATORS`] and both operands are the same integer type.
+///
+/// Returns `Ok(true)` if applied, `Ok(false)` if not.
+fn lookup_integer_arithmetic(
+    name: &str,
+    mode: OverflowMode,
+    segment: &mut DynSegment,
+    num_operands: usize,
+    span: SourceSpan,
+) -> Result<bool> {
+    let Some(name) = OVERFLOW_OPERATORS
+        .iter()
+        .find(|operator| **operator == name)
+    else {
+        return Ok(false);
+    };
+    if num_operands != 2 {
+        return Ok(false);
+    }
+    let [lhs, rhs] = segment.peek_stack_infos(2) else {
+        return Ok(false);
+    };
+    let type_id = lhs.type_id;
+    if rhs.type_id != type_id {
+        return Ok(false);
+    }
+    macro_rules! dispatch {
+        ($($t:ty),*) => {
+            $(if type_id == TypeId::of::<$t>() {
+                segment.push_label(name);
+                push_integer_arithmetic::<$t>(name, mode, segment, span)?;
+                return Ok(true);
+            })*
+        };
+    }
+    dispatch!(
+        u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize
+    );
+    Ok(false)
+}
+
 /// Compile-time perfect hash map for built-in operations.
 ///
 /// Maps operator symbols to their signature arrays for O(1) lookup.
@@ -923,6 +1254,7 @@ static BUILTINS: phf::Map<&'static str, &'static [OpSignature]> = phf_map! {
     "+" => ADD_SIGNATURES,
     "-" => SUB_SIGNATURES,
     "*" => MUL_SIGNATURES,
+    "**" => POW_SIGNATURES,
     "/" => DIV_SIGNATURES,
     "%" => MOD_SIGNATURES,
     "&" => BITWISE_AND_SIGNATURES,
@@ -935,6 +1267,24 @@ static BUILTINS: phf::Map<&'static str, &'static [OpSignature]> = phf_map! {
     "<=" => LESS_THAN_OR_EQUAL_SIGNATURES,
     ">" => GREATER_THAN_SIGNATURES,
     ">=" => GREATER_THAN_OR_EQUAL_SIGNATURES,
+    "size" => SIZE_SIGNATURES,
+    "wrapping_add" => WRAPPING_ADD_SIGNATURES,
+    "wrapping_sub" => WRAPPING_SUB_SIGNATURES,
+    "wrapping_mul" => WRAPPING_MUL_SIGNATURES,
+    "as u8" => CAST_U8_SIGNATURES,
+    "as u16" => CAST_U16_SIGNATURES,
+    "as u32" => CAST_U32_SIGNATURES,
+    "as u64" => CAST_U64_SIGNATURES,
+    "as u128" => CAST_U128_SIGNATURES,
+    "as usize" => CAST_USIZE_SIGNATURES,
+    "as i8" => CAST_I8_SIGNATURES,
+    "as i16" => CAST_I16_SIGNATURES,
+    "as i32" => CAST_I32_SIGNATURES,
+    "as i64" => CAST_I64_SIGNATURES,
+    "as i128" => CAST_I128_SIGNATURES,
+    "as isize" => CAST_ISIZE_SIGNATURES,
+    "as f32" => CAST_F32_SIGNATURES,
+    "as f64" => CAST_F64_SIGNATURES,
 };
 
 /// A single built-in overload's declared operand types, exposed for the static type checker
@@ -972,27 +1322,38 @@ pub struct OperandTypes {
 /// assert!(builtin_operand_types("not_an_operator").is_empty());
 /// ```
 pub fn builtin_operand_types(name: &str) -> Vec<OperandTypes> {
-    let Some(signatures) = signatures_for(name) else {
+    let Some((_, signatures)) = signatures_for(name) else {
         return Vec::new();
     };
-    signatures
-        .iter()
-        .map(|sig| OperandTypes {
+    let integer_types = if OVERFLOW_OPERATORS.contains(&name) {
+        TYPE_U8..TYPE_ISIZE + 1
+    } else {
+        0..0
+    };
+    integer_types
+        .map(|index| OperandTypes {
+            arity: 2,
+            lhs: TYPE_IDS[index],
+            rhs: TYPE_IDS[index],
+        })
+        .chain(signatures.iter().map(|sig| OperandTypes {
             arity: sig.arity,
             lhs: sig.lhs_type_id(),
             rhs: sig.rhs_type_id(),
-        })
+        }))
         .collect()
 }
 
-/// Routes an operator name to its static signature table, or `None` if `name` names no built-in
-/// operator. Shared by [`builtin_operand_types`] and [`BuiltinScope::lookup`] so a future
+/// Routes an operator name to its static name and signature table, or `None` if `name` names no
+/// built-in operator. Shared by [`builtin_operand_types`] and [`BuiltinScope::lookup`] so a future
 /// heterogeneous operator only needs its routing added in one place.
-fn signatures_for(name: &str) -> Option<&'static [OpSignature]> {
+fn signatures_for(name: &str) -> Option<(&'static str, &'static [OpSignature])> {
     match name {
-        "<<" => Some(&LEFT_SHIFT_SIGNATURES),
-        ">>" => Some(&RIGHT_SHIFT_SIGNATURES),
-        _ => BUILTINS.get(name).copied(),
+        "<<" => Some(("<<", LEFT_SHIFT_SIGNATURES.as_slice())),
+        ">>" => Some((">>", RIGHT_SHIFT_SIGNATURES.as_slice())),
+        _ => BUILTINS
+            .get_entry(name)
+            .map(|(name, signatures)| (*name, *signatures)),
     }
 }
 
@@ -1015,7 +1376,7 @@ impl BuiltinScope {
         span: SourceSpan,
     ) -> Result<bool> {
         let stack_infos = segment.peek_stack_infos(num_operands);
-        let Some(signatures) = signatures_for(name) else {
+        let Some((label, signatures)) = signatures_for(name) else {
             return Ok(false);
         };
         for sig in signatures {
@@ -1024,6 +1385,7 @@ impl BuiltinScope {
                 && stack_infos[0].type_id == sig.lhs_type_id()
                 && (arity < 2 || stack_infos[1].type_id == sig.rhs_type_id());
             if matches {
+                segment.push_label(label);
                 (sig.op_fn)(segment, span)?;
                 return Ok(true);
             }
@@ -1057,6 +1419,11 @@ pub struct OpLookup {
     scopes: Vec<ScopeFn>,
     builtin_scope: BuiltinScope,
     tuple_signatures: Vec<TupleOpSignature>,
+    map_types: Vec<MapTypeSignature>,
+    list_types: Vec<ListTypeSignature>,
+    functions: Vec<FunctionSignature>,
+    /// What binary `+`, `-`, and `*` on integers do on overflow.
+    overflow_mode: OverflowMode,
 }
 
 impl OpLookup {
@@ -1074,9 +1441,115 @@ impl OpLookup {
             scopes: Vec::new(),
             builtin_scope: BuiltinScope,
             tuple_signatures: Vec::new(),
+            map_types: Vec::new(),
+            list_types: Vec::new(),
+            functions: Vec::new(),
+            overflow_mode: OverflowMode::Checked,
         }
     }
 
+    /// Selects what binary `+`, `-`, and `*` on integer operands do on overflow. Other operand
+    /// types are unaffected. [`OverflowMode::Checked`] by default.
+    ///
+    /// Under [`OverflowMode::Panic`] these operators are never folded while parsing, so an
+    /// overflowing constant expression panics when the segment runs rather than in the parser.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup, OverflowMode};
+    ///
+    /// let mut lookup = OpLookup::new();
+    /// lookup.set_overflow_mode(OverflowMode::Wrap);
+    /// let mut segment = CELParser::new(lookup).parse_str("200u8 + 100u8").unwrap();
+    /// assert_eq!(segment.call0::<u8>().unwrap(), 44);
+    ///
+    /// // The wrapping functions are always available by name.
+    /// let mut segment = CELParser::new(OpLookup::new())
+    ///     .parse_str("wrapping_add(255u8, 1u8)")
+    ///     .unwrap();
+    /// assert_eq!(segment.call0::<u8>().unwrap(), 0);
+    /// ```
+    pub fn set_overflow_mode(&mut self, mode: OverflowMode) {
+        self.overflow_mode = mode;
+    }
+
+    /// Returns the mode set with [`set_overflow_mode`](Self::set_overflow_mode).
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    /// Registers a named function taking `arity` arguments, called as `name(args...)`.
+    ///
+    /// When the parser sees a call to `name`, it parses the arguments and then invokes
+    /// `push_fn` with the arguments on top of the segment's stack. Registering a name again
+    /// shadows the earlier registration. Error messages returned by `push_fn` surface verbatim.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    ///
+    /// let mut lookup = OpLookup::new();
+    /// lookup.register_function("answer", 0, |seg, _span| {
+    ///     seg.op0(|| 42i32);
+    ///     Ok(())
+    /// });
+    /// let mut segment = CELParser::new(lookup).parse_str("answer()").unwrap();
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 42);
+    /// ```
+    pub fn register_function<F>(&mut self, name: &str, arity: usize, push_fn: F)
+    where
+        F: Fn(&mut DynSegment, SourceSpan) -> Result<()> + Send + Sync + 'static,
+    {
+        self.functions.push(FunctionSignature {
+            name: name.to_string(),
+            arity,
+            push_fn: Box::new(push_fn),
+        });
+    }
+
+    /// Returns the arity of the function registered as `name`, or of the built-in function
+    /// `name` (e.g. `size`), or `None` if there is neither.
+    ///
+    /// - Complexity: O(f) in the number of registered functions.
+    pub fn function_arity(&self, name: &str) -> Option<usize> {
+        self.find_function(name)
+            .map(|function| function.arity)
+            .or_else(|| {
+                BUILTIN_FUNCTIONS
+                    .iter()
+                    .find(|(builtin, _)| *builtin == name)
+                    .map(|(_, arity)| *arity)
+            })
+    }
+
+    /// Returns whether applying `name` to `num_operands` operands can only resolve to a built-in
+    /// operation, whose result depends on nothing but its operands. A registered scope may
+    /// resolve any name, so this is `false` while any scope is registered.
+    ///
+    /// - Complexity: O(f + t) in the number of registered functions and tuple signatures.
+    pub fn resolves_to_builtin(&self, name: &str, num_operands: usize) -> bool {
+        self.scopes.is_empty()
+            && self
+                .find_function(name)
+                .is_none_or(|function| function.arity != num_operands)
+            && !self
+                .tuple_signatures
+                .iter()
+                .any(|signature| signature.name == name)
+    }
+
+    /// Returns the most recent function registered as `name`.
+    ///
+    /// - Complexity: O(f) in the number of registered functions.
+    fn find_function(&self, name: &str) -> Option<&FunctionSignature> {
+        self.functions
+            .iter()
+            .rev()
+            .find(|function| function.name == name)
+    }
+
     /// Registers a tuple-shaped operator signature, matched by element
     /// `TypeId` sequence the same way built-in operators are matched by flat
     /// `TypeId`.
@@ -1084,6 +1557,194 @@ impl OpLookup {
         self.tuple_signatures.push(signature);
     }
 
+    /// Registers `HashMap<String, V>` as a map type whose fields can be accessed with `m.key`
+    /// and tested with `has(m.key)`. Maps of the built-in scalar types are always
+    /// available; register a map type as a value type to access nested maps (`m.a.b`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    /// use std::collections::HashMap;
+    ///
+    /// type Inner = HashMap<String, i64>;
+    ///
+    /// let mut lookup = OpLookup::new();
+    /// lookup.register_map_type::<Inner>();
+    /// lookup.push_scope(|name, seg, n, _span| {
+    ///     if name != "m" || n != 0 {
+    ///         return Ok(false);
+    ///     }
+    ///     seg.op0(|| HashMap::from([("a".to_string(), Inner::from([("b".to_string(), 7)]))]));
+    ///     Ok(true)
+    /// });
+    /// let mut segment = CELParser::new(lookup).parse_str("m.a.b").unwrap();
+    /// assert_eq!(segment.call0::<i64>().unwrap(), 7);
+    /// ```
+    pub fn register_map_type<V: 'static>(&mut self) {
+        self.map_types.push(map_type_signature::<V>());
+    }
+
+    /// Registers `Vec<T>` as a list type, so `[a, b, ...]` with `T` elements builds one, `+`
+    /// concatenates two, `size` counts its elements, and the `all`/`exists` comprehensions
+    /// iterate it. Lists of the built-in scalar types are always available.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup};
+    ///
+    /// let mut lookup = OpLookup::new();
+    /// lookup.register_list_type::<Vec<i32>>();
+    /// let source = "[[1], [2, 3]].exists(l, l.all(x, x > 1))";
+    /// let mut segment = CELParser::new(lookup).parse_str(source).unwrap();
+    /// assert!(segment.call0::<bool>().unwrap());
+    /// ```
+    pub fn register_list_type<T: Clone + 'static>(&mut self) {
+        self.list_types.push(list_type_signature::<T>());
+    }
+
+    /// Returns the registered list type whose list is on top of `segment`.
+    ///
+    /// - Complexity: O(l) where l is the number of registered and built-in list types.
+    fn list_on_top(&self, segment: &DynSegment) -> Option<&ListTypeSignature> {
+        let type_id = segment.peek_output_type_id()?;
+        self.list_types
+            .iter()
+            .chain(BUILTIN_LIST_TYPES.iter())
+            .find(|list| list.list_type_id == type_id)
+    }
+
+    /// Creates the fragment a comprehension's predicate is built in, with the current element
+    /// of the list on top of `segment` as its only value, and a reference to that element.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the top of `segment` isn't a registered list type.
+    pub fn new_predicate_fragment(&self, segment: &DynSegment) -> Result<(DynSegment, ElementRef)> {
+        let list = self.list_on_top(segment).ok_or_else(|| {
+            anyhow!(
+                "comprehension requires a list, found `{}`",
+                top_type_name(segment)
+            )
+        })?;
+        Ok((list.fragment_fn)(segment))
+    }
+
+    /// Replaces the list on top of `segment` with whether `predicate`, a fragment created by
+    /// [`new_predicate_fragment`](Self::new_predicate_fragment), holds for all or any of its
+    /// elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the top of `segment` isn't a registered list type, or if `predicate`
+    /// doesn't produce a `bool`.
+    pub fn quantify(
+        &self,
+        segment: &mut DynSegment,
+        predicate: DynSegment,
+        quantifier: Quantifier,
+    ) -> Result<()> {
+        let list = self.list_on_top(segment).ok_or_else(|| {
+            anyhow!(
+                "comprehension requires a list, found `{}`",
+                top_type_name(segment)
+            )
+        })?;
+        (list.quantify_fn)(segment, predicate, quantifier)
+    }
+
+    /// Attempts to apply a list literal's `"[]"` op to a registered list or element type, `+`
+    /// to two lists of a registered list type, or `size` to one.
+    ///
+    /// Returns `Ok(true)` if applied, `Ok(false)` if `name` isn't `"[]"`, `+`, or `size`, or the
+    /// operands aren't a registered element type, optionally preceded by its list type, two of
+    /// the same registered list type, or one, respectively.
+    ///
+    /// - Complexity: O(l) where l is the number of registered and built-in list types.
+    fn lookup_list_element(
+        &self,
+        name: &str,
+        segment: &mut DynSegment,
+        num_operands: usize,
+    ) -> Result<bool> {
+        if name == "+" && num_operands == 2 {
+            let infos = segment.peek_stack_infos(2);
+            let found = self
+                .list_types
+                .iter()
+                .chain(BUILTIN_LIST_TYPES.iter())
+                .find(|list| infos.iter().all(|info| info.type_id == list.list_type_id));
+            return match found {
+                Some(list) => (list.concat_fn)(segment).map(|()| true),
+                None => Ok(false),
+            };
+        }
+        if name == "size" && num_operands == 1 {
+            return match self.list_on_top(segment) {
+                Some(list) => (list.size_fn)(segment).map(|()| true),
+                None => Ok(false),
+            };
+        }
+        if name != "[]" || !(1..=2).contains(&num_operands) {
+            return Ok(false);
+        }
+        let infos = segment.peek_stack_infos(num_operands);
+        let Some(element) = infos.last() else {
+            return Ok(false);
+        };
+        let found = self
+            .list_types
+            .iter()
+            .chain(BUILTIN_LIST_TYPES.iter())
+            .find(|list| {
+                list.element_type_id == element.type_id
+                    && (num_operands == 1 || infos[0].type_id == list.list_type_id)
+            });
+        match found {
+            Some(list) => {
+                (list.element_fn)(segment, num_operands)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Attempts to apply a field access (`".key"`) or presence test (`"has .key"`) to a
+    /// registered map type.
+    ///
+    /// Returns `Ok(true)` if applied, `Ok(false)` if `name` isn't a field operator or the operand
+    /// isn't a registered map type.
+    ///
+    /// - Complexity: O(m) where m is the number of registered and built-in map types.
+    fn lookup_map_field(
+        &self,
+        name: &str,
+        segment: &mut DynSegment,
+        num_operands: usize,
+        span: SourceSpan,
+    ) -> Result<bool> {
+        let (key, presence) = match name.strip_prefix("has .") {
+            Some(key) => (key, true),
+            None => match name.strip_prefix('.') {
+                Some(key) => (key, false),
+                None => return Ok(false),
+            },
+        };
+        if num_operands != 1 {
+            return Ok(false);
+        }
+        let type_id = segment.peek_stack_infos(1)[0].type_id;
+        let mut map_types = self.map_types.iter().chain(BUILTIN_MAP_TYPES.iter());
+        match map_types.find(|map| map.type_id == type_id) {
+            Some(map) => {
+                (map.field_fn)(segment, key.to_string(), presence, span)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
     /// Attempts to find and apply a registered tuple-shaped signature.
     ///
     /// Returns `Ok(true)` if found and applied, `Ok(false)` if not found.
@@ -1145,7 +1806,8 @@ impl OpLookup {
 
     /// Looks up and applies an operation, attaching the expression span to any error.
     ///
-    /// Searches scopes in LIFO order, then falls back to built-in operations.
+    /// Searches scopes in LIFO order, then registered functions, then falls back to built-in
+    /// operations.
     ///
     /// # Errors
     ///
@@ -1187,6 +1849,13 @@ impl OpLookup {
             }
         }
 
+        if let Some(function) = self.find_function(name)
+            && function.arity == num_operands
+        {
+            return (function.push_fn)(segment, source_span)
+                .map_err(|e| crate::ParseError::new_range(e.to_string(), start, end));
+        }
+
         match self.lookup_tuple_signature(name, segment, num_operands, source_span) {
             Ok(true) => return Ok(()),
             Ok(false) => {}
@@ -1199,10 +1868,52 @@ impl OpLookup {
             }
         }
 
-        match self
-            .builtin_scope
-            .lookup(name, segment, num_operands, source_span)
+        match self.lookup_map_field(name, segment, num_operands, source_span) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => {
+                return Err(crate::ParseError::new_range(
+                    format!("operation error: {}", e),
+                    start,
+                    end,
+                ));
+            }
+        }
+
+        match self.lookup_list_element(name, segment, num_operands) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {}
+            Err(e) => {
+                return Err(crate::ParseError::new_range(
+                    format!("operation error: {}", e),
+                    start,
+                    end,
+                ));
+            }
+        }
+
+        if num_operands == 2
+            && is_float_promoting_operator(name)
+            && let Err(e) = promote_operands_to_f64(segment)
         {
+            return Err(crate::ParseError::new_range(
+                format!("operation error: {}", e),
+                start,
+                end,
+            ));
+        }
+
+        // Integer `+`, `-`, and `*` follow the overflow mode; other operand types fall through
+        // to the built-in table.
+        let applied =
+            lookup_integer_arithmetic(name, self.overflow_mode, segment, num_operands, source_span)
+                .and_then(|applied| {
+                    Ok(applied
+                        || self
+                            .builtin_scope
+                            .lookup(name, segment, num_operands, source_span)?)
+                });
+        match applied {
             Ok(true) => return Ok(()),
             Ok(false) => {}
             Err(e) => {
@@ -1238,6 +1949,20 @@ impl OpLookup {
     }
 }
 
+/// Returns whether the binary operator `name` promotes an integer operand mixed with an `f64`
+/// operand to `f64`.
+pub(crate) fn is_float_promoting_operator(name: &str) -> bool {
+    matches!(name, "+" | "-" | "*" | "/")
+}
+
+/// Returns the type name of the value on top of `segment`, or `()` if there is none.
+fn top_type_name(segment: &DynSegment) -> &str {
+    segment
+        .peek_stack_infos(1)
+        .first()
+        .map_or("()", |info| info.type_name.as_ref())
+}
+
 impl Default for OpLookup {
     fn default() -> Self {
         Self::new()
@@ -1251,6 +1976,42 @@ mod tests {
 
     type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+    #[test]
+    fn wrap_overflow_mode_reroutes_integer_operators() -> Result<()> {
+        let mut lookup = OpLookup::new();
+        let mut segment = DynSegment::new::<()>();
+        segment.just(i32::MAX);
+        segment.just(1i32);
+        lookup.lookup("+", &mut segment, 2, Span::call_site(), Span::call_site())?;
+        assert!(segment.call0::<i32>().is_err());
+
+        lookup.set_overflow_mode(OverflowMode::Wrap);
+        let mut segment = DynSegment::new::<()>();
+        segment.just(i32::MAX);
+        segment.just(1i32);
+        lookup.lookup("+", &mut segment, 2, Span::call_site(), Span::call_site())?;
+        assert_eq!(segment.call0::<i32>()?, i32::MIN);
+
+        let mut segment = DynSegment::new::<()>();
+        segment.just(u8::MAX);
+        segment.just(1u8);
+        lookup.lookup(
+            "wrapping_add",
+            &mut segment,
+            2,
+            Span::call_site(),
+            Span::call_site(),
+        )?;
+        assert_eq!(segment.call0::<u8>()?, 0);
+
+        let mut segment = DynSegment::new::<()>();
+        segment.just(1.5f64);
+        segment.just(2.0f64);
+        lookup.lookup("*", &mut segment, 2, Span::call_site(), Span::call_site())?;
+        assert_eq!(segment.call0::<f64>()?, 3.0);
+        Ok(())
+    }
+
     #[test]
     fn test_addition_u32() -> Result<()> {
         let lookup = OpLookup::new();
@@ -1547,6 +2308,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn registered_function_dispatches_by_name_and_arity() -> Result<()> {
+        let mut lookup = OpLookup::new();
+        lookup.register_function("double", 1, |seg, _span| seg.op1(|a: i32| a * 2));
+        assert_eq!(lookup.function_arity("double"), Some(1));
+        assert_eq!(lookup.function_arity("triple"), None);
+
+        let mut segment = DynSegment::new::<()>();
+        segment.just(21i32);
+        lookup.lookup(
+            "double",
+            &mut segment,
+            1,
+            Span::call_site(),
+            Span::call_site(),
+        )?;
+        assert_eq!(segment.call0::<i32>()?, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn registered_function_error_surfaces_verbatim() {
+        let mut lookup = OpLookup::new();
+        lookup.register_function("double", 1, |seg, _span| seg.op1(|a: i32| a * 2));
+        let mut segment = DynSegment::new::<()>();
+        segment.just(21u8);
+        let err = lookup
+            .lookup(
+                "double",
+                &mut segment,
+                1,
+                Span::call_site(),
+                Span::call_site(),
+            )
+            .unwrap_err();
+        assert_eq!(err.message(), "stack type ids do not match");
+    }
+
     #[test]
     fn test_scope_pop() -> Result<()> {
         let mut lookup = OpLookup::new();
@@ -1584,7 +2383,7 @@ mod tests {
     fn lookup_not_found_error_carries_span() {
         let lookup = OpLookup::new();
         let mut segment = DynSegment::new::<()>();
-        segment.just(10u32);
+        segment.just(10u64);
         segment.just(20.0f64);
         let err = lookup
             .lookup("+", &mut segment, 2, Span::call_site(), Span::call_site())
@@ -1595,7 +2394,7 @@ mod tests {
             err.message()
         );
         assert!(err.message().contains("`+`"));
-        assert!(err.message().contains("`u32`"));
+        assert!(err.message().contains("`u64`"));
         assert!(err.message().contains("`f64`"));
     }
 
@@ -1603,7 +2402,7 @@ mod tests {
     fn lookup_not_found_error_has_range() {
         let lookup = OpLookup::new();
         let mut segment = DynSegment::new::<()>();
-        segment.just(10u32);
+        segment.just(10u64);
         segment.just(20.0f64);
         let err = lookup
             .lookup("+", &mut segment, 2, Span::call_site(), Span::call_site())
@@ -6,10 +6,14 @@
 //! wrapping a [`DynSegment`] one-for-one. A future AST-building context (for the language
 //! server, formatter, and eventual macro-compilation backend) is expected to be the second.
 
-use cel_runtime::DynSegment;
+use cel_runtime::{
+    DynSegment, ElementRef, OpFailure, Quantifier, SegmentMark, SharedSegment, elvis,
+};
 use proc_macro2::Span;
+use std::any::Any;
 
-use crate::op_table::OpLookup;
+use crate::SourceSpan;
+use crate::op_table::{OpLookup, OverflowMode};
 
 /// The pluggable target a grammar production emits into.
 ///
@@ -17,6 +21,14 @@ use crate::op_table::OpLookup;
 /// "emitting" means: [`DynSegmentContext`] executes immediately into a stack machine; a future
 /// AST-building context would instead record a tree node.
 pub trait ParserContext: Sized {
+    /// A comprehension variable in scope, as returned by
+    /// [`new_predicate_fragment`](Self::new_predicate_fragment).
+    type Variable: Clone;
+
+    /// A `let` binding compiled once by [`compile_binding`](Self::compile_binding), which each
+    /// reference to it pushes with [`push_binding`](Self::push_binding).
+    type Binding;
+
     /// Creates a fresh, empty context with no operations recorded yet.
     fn new_context() -> Self;
 
@@ -32,7 +44,7 @@ pub trait ParserContext: Sized {
     fn push_literal<T: 'static + Clone>(&mut self, value: T, span: Span);
 
     /// Applies a named operator or zero-arity identifier lookup, using `op_lookup` to resolve it
-    /// against whatever this context currently holds.
+    /// against whatever this context currently holds, from source at `span`.
     ///
     /// # Errors
     ///
@@ -42,20 +54,20 @@ pub trait ParserContext: Sized {
         op_lookup: &OpLookup,
         name: &str,
         arity: usize,
-        start: Span,
-        end: Span,
+        span: OperatorSpan,
     ) -> crate::Result<()>;
 
-    /// Applies a short-circuiting logical operator (`"||"` or `"&&"`), consuming a leading
-    /// condition value already present on `self` and folding in `rhs`, the already-parsed
-    /// right-hand-side fragment.
+    /// Applies a short-circuiting logical operator (`"||"`, `"&&"`, or `"?:"`), consuming a
+    /// leading condition value already present on `self` and folding in `rhs`, the
+    /// already-parsed right-hand-side fragment.
     ///
-    /// - Precondition: `name` is `"||"` or `"&&"`, and `rhs` produces exactly one value.
+    /// - Precondition: `name` is `"||"`, `"&&"`, or `"?:"`, and `rhs` produces exactly one value.
     ///
     /// # Errors
     ///
     /// Implementations that validate operand types during parsing (e.g. [`DynSegmentContext`])
-    /// return `Err` if the leading condition value isn't a `bool`. Implementations that defer
+    /// return `Err` if the leading condition value isn't a `bool`, or for `"?:"`, if it has no
+    /// truthiness or `rhs` doesn't produce the result type. Implementations that defer
     /// type validation to a later phase (e.g. [`crate::ast::AstContext`]) never return `Err`
     /// here.
     fn apply_logical(&mut self, name: &str, rhs: Self, start: Span, end: Span)
@@ -98,11 +110,99 @@ pub trait ParserContext: Sized {
 
     /// Returns the current stack offset, used to compute tuple layouts.
     fn current_stack_offset(&self) -> usize;
+
+    /// Appends the value on top to the list below it or, if `first`, wraps it in a new
+    /// one-element list. `start`/`end` cover the list literal through the `,` or `]` following
+    /// the element.
+    ///
+    /// # Errors
+    ///
+    /// Implementations that validate operand types during parsing (e.g. [`DynSegmentContext`])
+    /// return `Err` if the value isn't of the list's element type, or isn't of a registered list
+    /// element type. Implementations that defer type validation to a later phase (e.g.
+    /// [`crate::ast::AstContext`]) never return `Err` here.
+    fn push_list_element(
+        &mut self,
+        op_lookup: &OpLookup,
+        first: bool,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()>;
+
+    /// Creates an empty fragment for building the predicate of a comprehension over the list on
+    /// top of `self`, and the variable each element is bound to. `span` is the comprehension's
+    /// macro name.
+    ///
+    /// # Errors
+    ///
+    /// Implementations that validate operand types during parsing (e.g. [`DynSegmentContext`])
+    /// return `Err` if the top value isn't a registered list type.
+    fn new_predicate_fragment(
+        &self,
+        op_lookup: &OpLookup,
+        span: Span,
+    ) -> crate::Result<(Self, Self::Variable)>;
+
+    /// Pushes the current value of `variable`, a reference to it named `name`.
+    ///
+    /// - Precondition: `self` is the predicate fragment `variable` was created with, or a
+    ///   fragment nested within it.
+    fn push_predicate_variable(&mut self, variable: &Self::Variable, name: &str, span: Span);
+
+    /// Replaces the list on top with whether `predicate`, which binds each element to
+    /// `variable`, holds for all or any of its elements. `start`/`end` cover the list through the
+    /// comprehension's closing `)`.
+    ///
+    /// # Errors
+    ///
+    /// Implementations that validate operand types during parsing (e.g. [`DynSegmentContext`])
+    /// return `Err` if `predicate` doesn't produce exactly one `bool`.
+    fn apply_comprehension(
+        &mut self,
+        op_lookup: &OpLookup,
+        quantifier: Quantifier,
+        variable: &str,
+        predicate: Self,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()>;
+
+    /// Compiles `value`, the expression of a `let` binding parsed into a context created with
+    /// [`new_context`](Self::new_context), so references to the binding don't parse it again.
+    ///
+    /// - Precondition: `value` produces exactly one value.
+    fn compile_binding(value: Self) -> Self::Binding;
+
+    /// Pushes the value of `binding`, referenced by the identifier at `span`.
+    fn push_binding(&mut self, binding: &Self::Binding, span: Span);
+}
+
+/// The source of an operator application, for [`ParserContext::apply_op`].
+#[derive(Clone, Copy, Debug)]
+pub struct OperatorSpan {
+    /// The operator token, or the name of an identifier or function, or the field of a field
+    /// access.
+    pub operator: Span,
+    /// Start of the first token of the whole expression.
+    pub start: Span,
+    /// End of the last token of the whole expression.
+    pub end: Span,
 }
 
 /// [`ParserContext`] implementation that executes directly into a [`DynSegment`], reproducing
 /// the runtime-execution behavior `CELParser` always had before this trait existed.
 ///
+/// Built-in operators applied only to constants are folded: the operator runs on a scratch
+/// segment while parsing, and the constants' ops are replaced by a single op pushing the
+/// result. An operator whose evaluation fails (e.g. `1 / 0`) is emitted unfolded, so the error
+/// still surfaces when the segment runs.
+///
+/// Each op pushed is attributed to the source span it was emitted for, so the op a call fails
+/// in can be mapped back to source with [`error_span`](Self::error_span). Operators and
+/// identifiers are attributed to their own token; a construct that runs fragments (a
+/// short-circuiting operator, an `if`, or a comprehension) is one op attributed to the whole
+/// construct.
+///
 /// # Examples
 ///
 /// ```rust
@@ -112,13 +212,165 @@ pub trait ParserContext: Sized {
 /// let mut ctx = DynSegmentContext::new_context();
 /// ctx.push_literal(10i32, Span::call_site());
 /// ```
-pub struct DynSegmentContext(pub(crate) DynSegment);
+pub struct DynSegmentContext(pub(crate) DynSegment, ConstantRun);
+
+/// A constant that can be pushed onto any segment, by cloning.
+trait Constant {
+    fn push_onto(&self, segment: &mut DynSegment);
+
+    fn boxed(&self) -> Box<dyn Constant>;
+}
+
+impl<T: 'static + Clone> Constant for T {
+    fn push_onto(&self, segment: &mut DynSegment) {
+        segment.push_label("just");
+        segment.just(self.clone());
+    }
+
+    fn boxed(&self) -> Box<dyn Constant> {
+        Box::new(self.clone())
+    }
+}
+
+/// A `let` binding compiled by [`DynSegmentContext`]: its value, if constant, so expressions
+/// using the binding still fold, and otherwise the segment computing it.
+pub struct CompiledBinding(BindingValue);
+
+enum BindingValue {
+    Constant(Box<dyn Constant>),
+    Shared(SharedSegment),
+}
+
+/// Boxes a folded result as a [`Constant`], or returns `None` if its type isn't one a literal
+/// or a built-in operator on literals produces.
+fn into_constant(value: Box<dyn Any>) -> Option<Box<dyn Constant>> {
+    macro_rules! downcast {
+        ($($t:ty),*) => {
+            $(if value.is::<$t>() {
+                return value.downcast::<$t>().ok().map(|v| Box::new(*v) as Box<dyn Constant>);
+            })*
+        };
+    }
+    downcast!(
+        bool, char, String, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32,
+        f64
+    );
+    None
+}
+
+/// The constants on top of a [`DynSegmentContext`]'s stack, bottom first, each with the mark
+/// taken before its op was pushed.
+#[derive(Default)]
+pub(crate) struct ConstantRun {
+    constants: Vec<(SegmentMark, Box<dyn Constant>)>,
+    /// Op count and stack offset right after the last constant was pushed; if the segment no
+    /// longer matches, something else was pushed since and the run is stale.
+    end: (usize, usize),
+}
+
+impl ConstantRun {
+    /// Returns the number of constants on top of `segment`'s stack.
+    fn len(&self, segment: &DynSegment) -> usize {
+        if self.end == (segment.op_count(), segment.current_stack_offset()) {
+            self.constants.len()
+        } else {
+            0
+        }
+    }
+
+    fn push(&mut self, segment: &mut DynSegment, mark: SegmentMark, constant: Box<dyn Constant>) {
+        constant.push_onto(segment);
+        self.constants.push((mark, constant));
+        self.end = (segment.op_count(), segment.current_stack_offset());
+    }
+
+    fn clear(&mut self) {
+        self.constants.clear();
+    }
+}
 
 impl DynSegmentContext {
     /// Returns the wrapped [`DynSegment`], consuming `self`.
     pub fn into_inner(self) -> DynSegment {
         self.0
     }
+
+    /// Returns the source span each op was emitted for, indexed like
+    /// [`DynSegment::op_count`].
+    #[must_use]
+    pub fn op_spans(&self) -> Vec<SourceSpan> {
+        self.0
+            .op_records()
+            .iter()
+            .map(|record| record.span.map(SourceSpan::from).unwrap_or_default())
+            .collect()
+    }
+
+    /// Returns the source span of the op that `error`, returned by calling the segment, failed
+    /// in, or `None` if it didn't come from an op of this segment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_parser::{CELParser, OpLookup, SourceSpan};
+    ///
+    /// let mut ctx = CELParser::new(OpLookup::new()).parse_str_ctx("1 / 0").unwrap();
+    /// let error = ctx.call0::<i32>().unwrap_err();
+    /// assert_eq!(ctx.error_span(&error), Some(SourceSpan::new(1, 2, 1, 3)));
+    /// ```
+    #[must_use]
+    pub fn error_span(&self, error: &anyhow::Error) -> Option<SourceSpan> {
+        let failure = error.downcast_ref::<OpFailure>()?;
+        self.0
+            .op_records()
+            .get(failure.index())?
+            .span
+            .map(SourceSpan::from)
+    }
+
+    /// Pushes `constant`, extending the run of constants on top, from source at `span`.
+    fn push_constant(&mut self, constant: Box<dyn Constant>, span: Span) {
+        if self.1.len(&self.0) == 0 {
+            self.1.clear();
+        }
+        let mark = self.0.mark();
+        self.1.push(&mut self.0, mark, constant);
+        self.attribute_ops(SourceSpan::from_proc_macro2(span));
+    }
+
+    /// Attributes the ops pushed since the last call to `span`.
+    fn attribute_ops(&mut self, span: SourceSpan) {
+        self.0.attribute_ops(span.into());
+    }
+
+    /// Evaluates `name` on the `arity` constants on top of the stack, or returns `None` if they
+    /// aren't all constants, `name` might not be a built-in, the evaluation might panic, or the
+    /// evaluation fails.
+    fn fold(
+        &self,
+        op_lookup: &OpLookup,
+        name: &str,
+        arity: usize,
+        start: Span,
+        end: Span,
+    ) -> Option<Box<dyn Constant>> {
+        let available = self.1.len(&self.0);
+        if arity == 0 || arity > available || !op_lookup.resolves_to_builtin(name, arity) {
+            return None;
+        }
+        // Evaluating an operator that may panic would panic the parser rather than the segment.
+        if op_lookup.overflow_mode() == OverflowMode::Panic && matches!(name, "+" | "-" | "*") {
+            return None;
+        }
+        let mut scratch = DynSegment::new::<()>();
+        for (_, constant) in &self.1.constants[available - arity..] {
+            constant.as_ref().push_onto(&mut scratch);
+        }
+        op_lookup
+            .lookup(name, &mut scratch, arity, start, end)
+            .ok()?;
+        into_constant(scratch.call0_any().ok()?)
+    }
 }
 
 impl std::ops::Deref for DynSegmentContext {
@@ -136,16 +388,19 @@ impl std::ops::DerefMut for DynSegmentContext {
 }
 
 impl ParserContext for DynSegmentContext {
+    type Variable = ElementRef;
+    type Binding = CompiledBinding;
+
     fn new_context() -> Self {
-        DynSegmentContext(DynSegment::new::<()>())
+        DynSegmentContext(DynSegment::new::<()>(), ConstantRun::default())
     }
 
     fn new_fragment(&self) -> Self {
-        DynSegmentContext(self.0.new_fragment())
+        DynSegmentContext(self.0.new_fragment(), ConstantRun::default())
     }
 
-    fn push_literal<T: 'static + Clone>(&mut self, value: T, _span: Span) {
-        self.0.just(value);
+    fn push_literal<T: 'static + Clone>(&mut self, value: T, span: Span) {
+        self.push_constant(Box::new(value), span);
     }
 
     fn apply_op(
@@ -153,10 +408,29 @@ impl ParserContext for DynSegmentContext {
         op_lookup: &OpLookup,
         name: &str,
         arity: usize,
-        start: Span,
-        end: Span,
+        span: OperatorSpan,
     ) -> crate::Result<()> {
-        op_lookup.lookup(name, &mut self.0, arity, start, end)
+        let OperatorSpan {
+            operator,
+            start,
+            end,
+        } = span;
+        if let Some(constant) = self.fold(op_lookup, name, arity, start, end) {
+            let first = self.1.constants.len() - arity;
+            let (mark, _) = self
+                .1
+                .constants
+                .drain(first..)
+                .next()
+                .expect("operand is constant");
+            self.0.rewind(mark.clone());
+            self.1.push(&mut self.0, mark, constant);
+        } else {
+            self.1.clear();
+            op_lookup.lookup(name, &mut self.0, arity, start, end)?;
+        }
+        self.attribute_ops(SourceSpan::from_proc_macro2(operator));
+        Ok(())
     }
 
     fn apply_logical(
@@ -166,8 +440,10 @@ impl ParserContext for DynSegmentContext {
         start: Span,
         end: Span,
     ) -> crate::Result<()> {
+        self.1.clear();
         let mut bypass = self.new_fragment();
         let result = match name {
+            "?:" => elvis(&mut self.0, rhs.0),
             "||" => {
                 bypass.0.just(true);
                 self.0.join2(bypass.0, rhs.0)
@@ -178,34 +454,111 @@ impl ParserContext for DynSegmentContext {
             }
             other => unreachable!("apply_logical called with unsupported operator `{other}`"),
         };
-        result.map_err(|e| crate::ParseError::new_range(e.to_string(), start, end))
+        result.map_err(|e| crate::ParseError::new_range(e.to_string(), start, end))?;
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
+        Ok(())
     }
 
     fn join2(
         &mut self,
         then_fragment: Self,
         else_fragment: Self,
-        _start: Span,
-        _end: Span,
+        start: Span,
+        end: Span,
     ) -> anyhow::Result<()> {
-        self.0.join2(then_fragment.0, else_fragment.0)
+        self.1.clear();
+        self.0.join2_nullable(then_fragment.0, else_fragment.0)?;
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
+        Ok(())
     }
 
-    fn make_tuple(&mut self, n: usize, ambient_start: usize, _start: Span, _end: Span) {
+    fn make_tuple(&mut self, n: usize, ambient_start: usize, start: Span, end: Span) {
+        self.1.clear();
         self.0.make_tuple(n, ambient_start);
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
     }
 
     fn peek_tuple_arity(&self) -> Option<usize> {
         self.0.peek_tuple_arity()
     }
 
-    fn tuple_index(&mut self, index: usize, _start: Span, _end: Span) {
+    fn tuple_index(&mut self, index: usize, start: Span, end: Span) {
+        self.1.clear();
         self.0.tuple_index(index);
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
     }
 
     fn current_stack_offset(&self) -> usize {
         self.0.current_stack_offset()
     }
+
+    fn push_list_element(
+        &mut self,
+        op_lookup: &OpLookup,
+        first: bool,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()> {
+        self.1.clear();
+        op_lookup.lookup("[]", &mut self.0, if first { 1 } else { 2 }, start, end)?;
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
+        Ok(())
+    }
+
+    fn new_predicate_fragment(
+        &self,
+        op_lookup: &OpLookup,
+        span: Span,
+    ) -> crate::Result<(Self, ElementRef)> {
+        let (fragment, element) = op_lookup
+            .new_predicate_fragment(&self.0)
+            .map_err(|e| crate::ParseError::new(e.to_string(), span))?;
+        Ok((DynSegmentContext(fragment, ConstantRun::default()), element))
+    }
+
+    fn push_predicate_variable(&mut self, variable: &ElementRef, _name: &str, span: Span) {
+        self.1.clear();
+        // SAFETY: per the precondition, `self` runs while the element is on the stack.
+        unsafe { self.0.push_element(*variable) };
+        self.attribute_ops(SourceSpan::from_proc_macro2(span));
+    }
+
+    fn apply_comprehension(
+        &mut self,
+        op_lookup: &OpLookup,
+        quantifier: Quantifier,
+        _variable: &str,
+        predicate: Self,
+        start: Span,
+        end: Span,
+    ) -> crate::Result<()> {
+        self.1.clear();
+        op_lookup
+            .quantify(&mut self.0, predicate.0, quantifier)
+            .map_err(|e| crate::ParseError::new_range(e.to_string(), start, end))?;
+        self.attribute_ops(SourceSpan::from_proc_macro2_range(start, end));
+        Ok(())
+    }
+
+    fn compile_binding(mut value: Self) -> CompiledBinding {
+        if value.1.len(&value.0) == 1 && value.0.op_count() == 1 {
+            let (_, constant) = value.1.constants.pop().expect("one constant");
+            return CompiledBinding(BindingValue::Constant(constant));
+        }
+        let shared = SharedSegment::new(value.0).expect("binding produces one value");
+        CompiledBinding(BindingValue::Shared(shared))
+    }
+
+    fn push_binding(&mut self, binding: &CompiledBinding, span: Span) {
+        match &binding.0 {
+            BindingValue::Constant(constant) => self.push_constant(constant.as_ref().boxed(), span),
+            BindingValue::Shared(shared) => {
+                self.1.clear();
+                self.0.push_shared(shared);
+                self.attribute_ops(SourceSpan::from_proc_macro2(span));
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -227,8 +580,17 @@ mod tests {
         ctx.push_literal(10i32, Span::call_site());
         ctx.push_literal(20i32, Span::call_site());
         let lookup = OpLookup::new();
-        ctx.apply_op(&lookup, "+", 2, Span::call_site(), Span::call_site())
-            .unwrap();
+        ctx.apply_op(
+            &lookup,
+            "+",
+            2,
+            OperatorSpan {
+                operator: Span::call_site(),
+                start: Span::call_site(),
+                end: Span::call_site(),
+            },
+        )
+        .unwrap();
         assert_eq!(ctx.into_inner().call0::<i32>().unwrap(), 30);
     }
 
@@ -239,7 +601,16 @@ mod tests {
         ctx.push_literal("hi".to_string(), Span::call_site());
         let lookup = OpLookup::new();
         let err = ctx
-            .apply_op(&lookup, "+", 2, Span::call_site(), Span::call_site())
+            .apply_op(
+                &lookup,
+                "+",
+                2,
+                OperatorSpan {
+                    operator: Span::call_site(),
+                    start: Span::call_site(),
+                    end: Span::call_site(),
+                },
+            )
             .expect_err("mismatched operand types must fail");
         assert!(err.message().starts_with("no operation"));
     }
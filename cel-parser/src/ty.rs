@@ -6,8 +6,10 @@
 
 use std::any::TypeId;
 
-use crate::op_table::builtin_operand_types;
-use crate::{Expr, ExprSpan, Literal, ParseError};
+use cel_runtime::{Quantifier, promotes_to_f64};
+
+use crate::op_table::{builtin_operand_types, is_float_promoting_operator};
+use crate::{Expr, ExprSpan, Literal, LogicalOp, ParseError};
 
 /// A static type: one of the built-in primitives, or [`Ty::Any`] for anything pm-lang/CEL's
 /// extensible type system doesn't statically know about.
@@ -82,7 +84,11 @@ impl Ty {
             Literal::F64(_) => Ty::F64,
             Literal::Bool(_) => Ty::Bool,
             Literal::Str(_) => Ty::String,
-            Literal::Char(_) | Literal::ByteStr(_) | Literal::CStr(_) | Literal::Unit => Ty::Any,
+            Literal::Char(_)
+            | Literal::ByteStr(_)
+            | Literal::CStr(_)
+            | Literal::Unit
+            | Literal::Null => Ty::Any,
         }
     }
 
@@ -227,7 +233,8 @@ impl Ty {
 ///
 /// Only [`Expr::Op`] (via [`builtin_operand_types`]) and [`Expr::Logical`] (CEL's fixed `&&`/`||`
 /// semantics: both operands must unify with `bool`) are checked directly. [`Expr::Apply`],
-/// [`Expr::Tuple`], [`Expr::TupleIndex`], and [`Expr::If`] are recursed into — so an `Op` nested
+/// [`Expr::Comprehension`] predicates must unify with `bool`. [`Expr::Apply`], [`Expr::Tuple`],
+/// [`Expr::TupleIndex`], [`Expr::List`], and [`Expr::If`] are recursed into — so an `Op` nested
 /// inside one is still checked — but the node itself always infers as [`Ty::Any`]: checking call
 /// return types, tuple shapes, and if/else branch agreement is deferred to a later phase (see the
 /// design doc's "Type checking (v1)" section).
@@ -261,7 +268,7 @@ pub fn check_expr(expr: &Expr, resolve_ident: &impl Fn(&str) -> Ty) -> (Ty, Vec<
             operands,
             span,
         } => check_op(name, operands, *span, resolve_ident),
-        Expr::Logical { lhs, rhs, span, .. } => check_logical(lhs, rhs, *span, resolve_ident),
+        Expr::Logical { op, lhs, rhs, span } => check_logical(*op, lhs, rhs, *span, resolve_ident),
         Expr::Apply { callee, args, .. } => {
             let mut diagnostics = check_expr(callee, resolve_ident).1;
             for arg in args {
@@ -277,6 +284,27 @@ pub fn check_expr(expr: &Expr, resolve_ident: &impl Fn(&str) -> Ty) -> (Ty, Vec<
             (Ty::Any, diagnostics)
         }
         Expr::TupleIndex { base, .. } => (Ty::Any, check_expr(base, resolve_ident).1),
+        Expr::List { elements, .. } => {
+            let mut diagnostics = Vec::new();
+            for element in elements {
+                diagnostics.extend(check_expr(element, resolve_ident).1);
+            }
+            (Ty::Any, diagnostics)
+        }
+        Expr::Comprehension {
+            quantifier,
+            range,
+            variable,
+            predicate,
+            span,
+        } => check_comprehension(
+            *quantifier,
+            range,
+            variable,
+            predicate,
+            *span,
+            resolve_ident,
+        ),
         Expr::If {
             cond,
             then_branch,
@@ -292,7 +320,8 @@ pub fn check_expr(expr: &Expr, resolve_ident: &impl Fn(&str) -> Ty) -> (Ty, Vec<
 }
 
 /// Checks an [`Expr::Op`] node: infers each operand, then (only if every operand resolved to a
-/// concrete type) matches them against [`builtin_operand_types`]. An operator
+/// concrete type, and after promoting an integer mixed with an `f64` in arithmetic, as
+/// [`OpLookup`](crate::OpLookup) does) matches them against [`builtin_operand_types`]. An operator
 /// `builtin_operand_types` doesn't recognize at all (e.g. a tuple-shaped custom op registered
 /// only at runtime) can't be checked here and infers as `Ty::Any` — not an error.
 fn check_op(
@@ -302,7 +331,7 @@ fn check_op(
     resolve_ident: &impl Fn(&str) -> Ty,
 ) -> (Ty, Vec<ParseError>) {
     let mut diagnostics = Vec::new();
-    let operand_tys: Vec<Ty> = operands
+    let mut operand_tys: Vec<Ty> = operands
         .iter()
         .map(|operand| {
             let (ty, operand_diags) = check_expr(operand, resolve_ident);
@@ -313,6 +342,15 @@ fn check_op(
     if operand_tys.contains(&Ty::Any) {
         return (Ty::Any, diagnostics);
     }
+    if operand_tys.len() == 2 && is_float_promoting_operator(name) {
+        for (integer, float) in [(0, 1), (1, 0)] {
+            if operand_tys[float] == Ty::F64
+                && operand_tys[integer].type_id().is_some_and(promotes_to_f64)
+            {
+                operand_tys[integer] = Ty::F64;
+            }
+        }
+    }
     let signatures = builtin_operand_types(name);
     if signatures.is_empty() {
         return (Ty::Any, diagnostics); // unregistered/custom operator: nothing to check
@@ -340,21 +378,48 @@ fn check_op(
     }
 }
 
+/// Every type an `as` cast can target.
+const CAST_TARGETS: [Ty; 14] = [
+    Ty::I8,
+    Ty::I16,
+    Ty::I32,
+    Ty::I64,
+    Ty::I128,
+    Ty::Isize,
+    Ty::U8,
+    Ty::U16,
+    Ty::U32,
+    Ty::U64,
+    Ty::U128,
+    Ty::Usize,
+    Ty::F32,
+    Ty::F64,
+];
+
 /// Returns the result type of a matched built-in operator application: `Ty::Bool` for the
-/// comparison operators, otherwise the (matched, homogeneous) operand type — every built-in
-/// signature is either a comparison (returning `bool`) or same-type-in-same-type-out (arithmetic,
+/// comparison operators, the target type for an `as` cast, otherwise the (matched, homogeneous)
+/// operand type — every other built-in signature is same-type-in-same-type-out (arithmetic,
 /// bitwise, shifts, unary negation, logical not).
 fn result_ty_for_op(name: &str, operand_ty: Ty) -> Ty {
+    if let Some(target) = name.strip_prefix("as ") {
+        return CAST_TARGETS
+            .into_iter()
+            .find(|ty| ty.name() == target)
+            .unwrap_or(Ty::Any);
+    }
     match name {
         "==" | "!=" | "<" | "<=" | ">" | ">=" => Ty::Bool,
         _ => operand_ty,
     }
 }
 
-/// Checks an [`Expr::Logical`] (`&&`/`||`) node: both operands should unify with `Ty::Bool` (CEL's
-/// fixed short-circuit semantics, not table-driven like [`Expr::Op`]); the node's own type is
-/// always `Ty::Bool` regardless of whether a diagnostic was recorded.
+/// Checks an [`Expr::Logical`] (`&&`/`||`/`?:`) node: both operands of `&&`/`||` should unify
+/// with `Ty::Bool` (CEL's fixed short-circuit semantics, not table-driven like [`Expr::Op`]), and
+/// the node's own type is always `Ty::Bool` regardless of whether a diagnostic was recorded. `?:`
+/// instead tests its left operand's truthiness, so its right operand should unify with the left
+/// operand's type, which is the node's type.
 fn check_logical(
+    op: LogicalOp,
     lhs: &Expr,
     rhs: &Expr,
     span: ExprSpan,
@@ -363,11 +428,26 @@ fn check_logical(
     let (lhs_ty, mut diagnostics) = check_expr(lhs, resolve_ident);
     let (rhs_ty, rhs_diags) = check_expr(rhs, resolve_ident);
     diagnostics.extend(rhs_diags);
+    if op == LogicalOp::Elvis {
+        if !rhs_ty.unifies_with(&lhs_ty) {
+            diagnostics.push(ParseError::new_range(
+                format!(
+                    "`?:` requires `{}` on the right to match the left, found `{}`",
+                    lhs_ty.name(),
+                    rhs_ty.name()
+                ),
+                span.start,
+                span.end,
+            ));
+        }
+        return (lhs_ty, diagnostics);
+    }
     for (side, ty) in [("left", lhs_ty), ("right", rhs_ty)] {
         if !ty.unifies_with(&Ty::Bool) {
             diagnostics.push(ParseError::new_range(
                 format!(
-                    "`&&`/`||` requires `bool`, found `{}` on the {side}",
+                    "`{}` requires `bool`, found `{}` on the {side}",
+                    op.symbol(),
                     ty.name()
                 ),
                 span.start,
@@ -378,10 +458,47 @@ fn check_logical(
     (Ty::Bool, diagnostics)
 }
 
+/// Checks an [`Expr::Comprehension`] node: the predicate, in which `variable` is [`Ty::Any`],
+/// should unify with `Ty::Bool`; the node's own type is always `Ty::Bool`.
+fn check_comprehension(
+    quantifier: Quantifier,
+    range: &Expr,
+    variable: &str,
+    predicate: &Expr,
+    span: ExprSpan,
+    resolve_ident: &impl Fn(&str) -> Ty,
+) -> (Ty, Vec<ParseError>) {
+    let mut diagnostics = check_expr(range, resolve_ident).1;
+    // A `dyn` resolver keeps nested comprehensions from instantiating `check_expr` unboundedly.
+    let resolve_in_predicate: &dyn Fn(&str) -> Ty = &|name| {
+        if name == variable {
+            Ty::Any
+        } else {
+            resolve_ident(name)
+        }
+    };
+    let (predicate_ty, predicate_diags) = check_expr(predicate, &resolve_in_predicate);
+    diagnostics.extend(predicate_diags);
+    if !predicate_ty.unifies_with(&Ty::Bool) {
+        let name = match quantifier {
+            Quantifier::All => "all",
+            Quantifier::Exists => "exists",
+        };
+        diagnostics.push(ParseError::new_range(
+            format!(
+                "`{name}` requires a `bool` predicate, found `{}`",
+                predicate_ty.name()
+            ),
+            span.start,
+            span.end,
+        ));
+    }
+    (Ty::Bool, diagnostics)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::LogicalOp;
 
     #[test]
     fn from_literal_maps_every_concrete_variant() {
@@ -566,6 +683,14 @@ mod tests {
         assert!(diags.is_empty());
     }
 
+    #[test]
+    fn cast_op_infers_the_target_type() {
+        let expr = op("as f64", vec![lit_i32(1)]);
+        let (ty, diags) = check_expr(&expr, &any_resolver);
+        assert_eq!(ty, Ty::F64);
+        assert!(diags.is_empty());
+    }
+
     #[test]
     fn unary_negation_preserves_the_operand_type() {
         let expr = op("-", vec![lit_i32(1)]);
@@ -582,6 +707,20 @@ mod tests {
         assert_eq!(diags.len(), 1);
     }
 
+    #[test]
+    fn arithmetic_promotes_integer_mixed_with_f64() {
+        let float = || Expr::Literal {
+            value: Literal::F64(2.5),
+            span: point(proc_macro2::Span::call_site()),
+        };
+        let (ty, diags) = check_expr(&op("+", vec![lit_i32(3), float()]), &any_resolver);
+        assert_eq!(ty, Ty::F64);
+        assert!(diags.is_empty());
+        let (ty, diags) = check_expr(&op("==", vec![lit_i32(3), float()]), &any_resolver);
+        assert_eq!(ty, Ty::Any);
+        assert_eq!(diags.len(), 1);
+    }
+
     #[test]
     fn op_with_an_any_operand_produces_no_diagnostic() {
         let expr = op(
@@ -635,6 +774,35 @@ mod tests {
         assert_eq!(diags.len(), 1);
     }
 
+    #[test]
+    fn elvis_with_mismatched_operands_names_the_operator() {
+        let expr = Expr::Logical {
+            op: LogicalOp::Elvis,
+            lhs: Box::new(lit_bool(false)),
+            rhs: Box::new(lit_i32(5)),
+            span: point(proc_macro2::Span::call_site()),
+        };
+        let (ty, diags) = check_expr(&expr, &any_resolver);
+        assert_eq!(ty, Ty::Bool);
+        assert_eq!(
+            diags[0].message(),
+            "`?:` requires `bool` on the right to match the left, found `i32`"
+        );
+    }
+
+    #[test]
+    fn elvis_has_the_type_of_its_left_operand() {
+        let expr = Expr::Logical {
+            op: LogicalOp::Elvis,
+            lhs: Box::new(lit_i32(0)),
+            rhs: Box::new(lit_i32(5)),
+            span: point(proc_macro2::Span::call_site()),
+        };
+        let (ty, diags) = check_expr(&expr, &any_resolver);
+        assert_eq!(ty, Ty::I32);
+        assert!(diags.is_empty());
+    }
+
     #[test]
     fn a_broken_op_nested_inside_a_tuple_still_surfaces_a_diagnostic() {
         let expr = Expr::Tuple {
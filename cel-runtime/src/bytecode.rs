@@ -0,0 +1,476 @@
+//! A flat, serializable alternative to closure-based segments: a
+//! [`Bytecode`](crate::bytecode::Bytecode) program is a list of
+//! [`Instruction`](crate::bytecode::Instruction)s over dynamically typed
+//! [`Value`](crate::bytecode::Value)s, run by an interpreter.
+//!
+//! Unlike a [`RawSegment`](crate::RawSegment), whose ops are closures, a program is plain data,
+//! so it can be serialized with `serde`, cached, and sent to another process. The instruction
+//! set covers the CEL built-in scalar operators; operand types are checked as instructions
+//! execute. Integer arithmetic fails on overflow or division by zero, like the CEL parser's
+//! default integer arithmetic, and an `i32` or `u32` operand of `+`, `-`, `*`, or `/` is promoted
+//! to `f64` when the other operand is an `f64`, as the CEL parser does.
+//!
+//! Available with the `bytecode` feature.
+
+use anyhow::{Result, anyhow, bail, ensure};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// A value on a [`Bytecode`] program's stack.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    /// A `bool`.
+    Bool(bool),
+    /// An `i32`.
+    I32(i32),
+    /// An `i64`.
+    I64(i64),
+    /// A `u32`.
+    U32(u32),
+    /// A `u64`.
+    U64(u64),
+    /// An `f64`.
+    F64(f64),
+    /// A `String`.
+    String(String),
+}
+
+impl Value {
+    /// Returns the name of the value's Rust type, e.g. `"i32"`.
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::I32(_) => "i32",
+            Value::I64(_) => "i64",
+            Value::U32(_) => "u32",
+            Value::U64(_) => "u64",
+            Value::F64(_) => "f64",
+            Value::String(_) => "String",
+        }
+    }
+
+    /// Returns whether the value is truthy, as the `?:` operator tests it: every value is
+    /// truthy but `false`, zero, and the empty string.
+    #[must_use]
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            Value::I32(value) => *value != 0,
+            Value::I64(value) => *value != 0,
+            Value::U32(value) => *value != 0,
+            Value::U64(value) => *value != 0,
+            Value::F64(value) => *value != 0.0,
+            Value::String(value) => !value.is_empty(),
+        }
+    }
+}
+
+/// One instruction of a [`Bytecode`] program.
+///
+/// Binary instructions pop `b`, then `a`, and push `a op b`; both operands must be of the same
+/// type, except that arithmetic on an `f64` and an `i32` or `u32` promotes the integer to `f64`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Instruction {
+    /// Pushes a value.
+    Push(Value),
+    /// Pushes the argument with the given name.
+    Load(String),
+    /// Pops a signed integer or `f64` and pushes its negation.
+    Neg,
+    /// Pops a `bool` and pushes its complement.
+    Not,
+    /// Adds numbers or concatenates strings.
+    Add,
+    /// Subtracts numbers.
+    Sub,
+    /// Multiplies numbers.
+    Mul,
+    /// Divides numbers, truncating integers toward zero.
+    Div,
+    /// Takes the remainder of dividing numbers.
+    Rem,
+    /// Compares for equality.
+    Eq,
+    /// Compares for inequality.
+    Ne,
+    /// Compares for less than.
+    Lt,
+    /// Compares for less than or equal.
+    Le,
+    /// Compares for greater than.
+    Gt,
+    /// Compares for greater than or equal.
+    Ge,
+    /// Skips the given number of following instructions.
+    Jump(usize),
+    /// Pops a `bool` and, if it is `false`, skips the given number of following instructions.
+    BranchIfFalse(usize),
+    /// Skips the given number of following instructions, keeping the value on top, if it is
+    /// [truthy](Value::is_truthy), and otherwise pops it.
+    JumpIfTruthy(usize),
+}
+
+impl Instruction {
+    /// Returns the CEL operator the instruction implements, for error messages.
+    fn symbol(&self) -> &'static str {
+        match self {
+            Instruction::Neg | Instruction::Sub => "-",
+            Instruction::Not => "!",
+            Instruction::Add => "+",
+            Instruction::Mul => "*",
+            Instruction::Div => "/",
+            Instruction::Rem => "%",
+            Instruction::Eq => "==",
+            Instruction::Ne => "!=",
+            Instruction::Lt => "<",
+            Instruction::Le => "<=",
+            Instruction::Gt => ">",
+            Instruction::Ge => ">=",
+            Instruction::Push(_)
+            | Instruction::Load(_)
+            | Instruction::Jump(_)
+            | Instruction::BranchIfFalse(_)
+            | Instruction::JumpIfTruthy(_) => "",
+        }
+    }
+}
+
+/// A program of [`Instruction`]s that leaves one [`Value`].
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::bytecode::{Bytecode, Instruction, Value};
+/// use std::collections::HashMap;
+///
+/// let program = Bytecode::new(vec![
+///     Instruction::Load("x".to_string()),
+///     Instruction::Push(Value::I64(2)),
+///     Instruction::Mul,
+/// ]);
+/// let args = HashMap::from([("x".to_string(), Value::I64(21))]);
+/// assert_eq!(program.execute(&args)?, Value::I64(42));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bytecode {
+    instructions: Vec<Instruction>,
+}
+
+impl Bytecode {
+    /// Creates a program from its instructions.
+    #[must_use]
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Bytecode { instructions }
+    }
+
+    /// Returns the program's instructions.
+    #[must_use]
+    pub fn instructions(&self) -> &[Instruction] {
+        &self.instructions
+    }
+
+    /// Runs the program, with `args` supplying the values of [`Instruction::Load`], and returns
+    /// the value it leaves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an instruction fails (an operand of the wrong type, an unknown
+    /// argument, arithmetic overflow, or division by zero), or if the program is malformed: an
+    /// instruction lacks operands, a jump leaves the program, or the program doesn't leave
+    /// exactly one value.
+    ///
+    /// - Complexity: O(n) in the number of instructions, since jumps only go forward.
+    pub fn execute(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let mut stack = Vec::new();
+        let mut pc = 0;
+        while let Some(instruction) = self.instructions.get(pc) {
+            pc += 1;
+            match instruction {
+                Instruction::Push(value) => stack.push(value.clone()),
+                Instruction::Load(name) => stack.push(
+                    args.get(name)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("unknown argument `{name}`"))?,
+                ),
+                Instruction::Neg | Instruction::Not => {
+                    let operand = pop(&mut stack)?;
+                    stack.push(unary(instruction, operand)?);
+                }
+                Instruction::Add
+                | Instruction::Sub
+                | Instruction::Mul
+                | Instruction::Div
+                | Instruction::Rem => {
+                    let rhs = pop(&mut stack)?;
+                    let lhs = pop(&mut stack)?;
+                    stack.push(arithmetic(instruction, lhs, rhs)?);
+                }
+                Instruction::Eq
+                | Instruction::Ne
+                | Instruction::Lt
+                | Instruction::Le
+                | Instruction::Gt
+                | Instruction::Ge => {
+                    let rhs = pop(&mut stack)?;
+                    let lhs = pop(&mut stack)?;
+                    stack.push(compare(instruction, &lhs, &rhs)?);
+                }
+                Instruction::Jump(skip) => pc = pc.saturating_add(*skip),
+                Instruction::BranchIfFalse(skip) => match pop(&mut stack)? {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => pc = pc.saturating_add(*skip),
+                    other => bail!("expected `bool` condition, found `{}`", other.type_name()),
+                },
+                Instruction::JumpIfTruthy(skip) => {
+                    let value = pop(&mut stack)?;
+                    if value.is_truthy() {
+                        stack.push(value);
+                        pc = pc.saturating_add(*skip);
+                    }
+                }
+            }
+        }
+        ensure!(pc == self.instructions.len(), "jump past end of bytecode");
+        ensure!(
+            stack.len() == 1,
+            "bytecode must leave exactly 1 value, but leaves {}",
+            stack.len()
+        );
+        Ok(stack.remove(0))
+    }
+}
+
+/// Pops the top value, or fails if the stack is empty.
+fn pop(stack: &mut Vec<Value>) -> Result<Value> {
+    stack
+        .pop()
+        .ok_or_else(|| anyhow!("bytecode stack underflow"))
+}
+
+/// Applies [`Instruction::Neg`] or [`Instruction::Not`] to `operand`.
+fn unary(instruction: &Instruction, operand: Value) -> Result<Value> {
+    let overflow = || anyhow!("arithmetic overflow");
+    match (instruction, operand) {
+        (Instruction::Neg, Value::I32(a)) => a.checked_neg().map(Value::I32).ok_or_else(overflow),
+        (Instruction::Neg, Value::I64(a)) => a.checked_neg().map(Value::I64).ok_or_else(overflow),
+        (Instruction::Neg, Value::F64(a)) => Ok(Value::F64(-a)),
+        (Instruction::Not, Value::Bool(a)) => Ok(Value::Bool(!a)),
+        (_, operand) => Err(anyhow!(
+            "`{}` is not defined for `{}`",
+            instruction.symbol(),
+            operand.type_name()
+        )),
+    }
+}
+
+/// Promotes an `i32` or `u32` operand to `f64` if the other operand is an `f64`.
+fn promote_to_f64(lhs: Value, rhs: Value) -> (Value, Value) {
+    let promote = |value| match value {
+        Value::I32(value) => Value::F64(value.into()),
+        Value::U32(value) => Value::F64(value.into()),
+        other => other,
+    };
+    match (&lhs, &rhs) {
+        (Value::F64(_), _) => (lhs, promote(rhs)),
+        (_, Value::F64(_)) => (promote(lhs), rhs),
+        _ => (lhs, rhs),
+    }
+}
+
+/// Applies an arithmetic instruction to `lhs` and `rhs`.
+fn arithmetic(instruction: &Instruction, lhs: Value, rhs: Value) -> Result<Value> {
+    let (lhs, rhs) = match instruction {
+        Instruction::Rem => (lhs, rhs),
+        _ => promote_to_f64(lhs, rhs),
+    };
+    macro_rules! integers {
+        ($($variant:ident),*) => {
+            match (lhs, rhs) {
+                $((Value::$variant(a), Value::$variant(b)) => {
+                    ensure!(
+                        b != 0 || !matches!(instruction, Instruction::Div | Instruction::Rem),
+                        "division by zero"
+                    );
+                    match instruction {
+                        Instruction::Add => a.checked_add(b),
+                        Instruction::Sub => a.checked_sub(b),
+                        Instruction::Mul => a.checked_mul(b),
+                        Instruction::Div => a.checked_div(b),
+                        _ => a.checked_rem(b),
+                    }
+                    .map(Value::$variant)
+                    .ok_or_else(|| anyhow!("arithmetic overflow"))
+                })*
+                (Value::F64(a), Value::F64(b)) => Ok(Value::F64(match instruction {
+                    Instruction::Add => a + b,
+                    Instruction::Sub => a - b,
+                    Instruction::Mul => a * b,
+                    Instruction::Div => a / b,
+                    _ => a % b,
+                })),
+                (Value::String(a), Value::String(b)) if *instruction == Instruction::Add => {
+                    Ok(Value::String(a + &b))
+                }
+                (lhs, rhs) => Err(anyhow!(
+                    "`{}` is not defined for `{}` and `{}`",
+                    instruction.symbol(),
+                    lhs.type_name(),
+                    rhs.type_name()
+                )),
+            }
+        };
+    }
+    integers!(I32, I64, U32, U64)
+}
+
+/// Applies a comparison instruction to `lhs` and `rhs`. Every comparison but `!=` is `false`
+/// for a NaN operand.
+fn compare(instruction: &Instruction, lhs: &Value, rhs: &Value) -> Result<Value> {
+    let ordering = match (lhs, rhs) {
+        (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+        (Value::I32(a), Value::I32(b)) => a.partial_cmp(b),
+        (Value::I64(a), Value::I64(b)) => a.partial_cmp(b),
+        (Value::U32(a), Value::U32(b)) => a.partial_cmp(b),
+        (Value::U64(a), Value::U64(b)) => a.partial_cmp(b),
+        (Value::F64(a), Value::F64(b)) => a.partial_cmp(b),
+        (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+        _ => bail!(
+            "`{}` is not defined for `{}` and `{}`",
+            instruction.symbol(),
+            lhs.type_name(),
+            rhs.type_name()
+        ),
+    };
+    Ok(Value::Bool(match instruction {
+        Instruction::Eq => ordering == Some(Ordering::Equal),
+        Instruction::Ne => ordering != Some(Ordering::Equal),
+        Instruction::Lt => ordering == Some(Ordering::Less),
+        Instruction::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        Instruction::Gt => ordering == Some(Ordering::Greater),
+        _ => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Instruction::*;
+    use super::*;
+
+    fn run(instructions: Vec<Instruction>) -> Result<Value> {
+        Bytecode::new(instructions).execute(&HashMap::new())
+    }
+
+    #[test]
+    fn execute_evaluates_operators() -> Result<()> {
+        assert_eq!(
+            run(vec![Push(Value::I32(1)), Push(Value::I32(2)), Add])?,
+            Value::I32(3)
+        );
+        assert_eq!(
+            run(vec![Push(Value::U64(7)), Push(Value::U64(2)), Rem])?,
+            Value::U64(1)
+        );
+        assert_eq!(run(vec![Push(Value::F64(1.5)), Neg])?, Value::F64(-1.5));
+        assert_eq!(
+            run(vec![
+                Push(Value::String("a".to_string())),
+                Push(Value::String("b".to_string())),
+                Add
+            ])?,
+            Value::String("ab".to_string())
+        );
+        assert_eq!(
+            run(vec![Push(Value::F64(f64::NAN)), Push(Value::F64(1.0)), Ge])?,
+            Value::Bool(false)
+        );
+        assert_eq!(
+            run(vec![Push(Value::I64(1)), Push(Value::I64(2)), Lt, Not])?,
+            Value::Bool(false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn execute_branches_forward() -> Result<()> {
+        // if flag { 1 } else { 2 }
+        let program = Bytecode::new(vec![
+            Load("flag".to_string()),
+            BranchIfFalse(2),
+            Push(Value::I32(1)),
+            Jump(1),
+            Push(Value::I32(2)),
+        ]);
+        for (flag, expected) in [(true, 1), (false, 2)] {
+            let args = HashMap::from([("flag".to_string(), Value::Bool(flag))]);
+            assert_eq!(program.execute(&args)?, Value::I32(expected));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn execute_promotes_integers_to_f64() -> Result<()> {
+        assert_eq!(
+            run(vec![Push(Value::I32(3)), Push(Value::F64(2.5)), Add])?,
+            Value::F64(5.5)
+        );
+        assert_eq!(
+            run(vec![Push(Value::F64(1.5)), Push(Value::U32(2)), Mul])?,
+            Value::F64(3.0)
+        );
+        assert_eq!(
+            run(vec![Push(Value::I64(3)), Push(Value::F64(2.5)), Add])
+                .unwrap_err()
+                .to_string(),
+            "`+` is not defined for `i64` and `f64`"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn execute_jumps_over_fallback_if_truthy() -> Result<()> {
+        // lhs ?: 5
+        for (lhs, expected) in [(0, 5), (3, 3)] {
+            let program = vec![Push(Value::I32(lhs)), JumpIfTruthy(1), Push(Value::I32(5))];
+            assert_eq!(run(program)?, Value::I32(expected), "lhs = {lhs}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn execute_reports_failures() {
+        let message = |instructions| run(instructions).unwrap_err().to_string();
+        assert_eq!(
+            message(vec![Push(Value::I32(1)), Push(Value::I32(0)), Div]),
+            "division by zero"
+        );
+        assert_eq!(
+            message(vec![Push(Value::I32(i32::MIN)), Neg]),
+            "arithmetic overflow"
+        );
+        assert_eq!(
+            message(vec![Push(Value::I32(1)), Push(Value::I64(2)), Add]),
+            "`+` is not defined for `i32` and `i64`"
+        );
+        assert_eq!(
+            message(vec![
+                Push(Value::U32(1)),
+                BranchIfFalse(0),
+                Push(Value::U32(1))
+            ]),
+            "expected `bool` condition, found `u32`"
+        );
+        assert_eq!(message(vec![Load("x".to_string())]), "unknown argument `x`");
+        assert_eq!(message(vec![Add]), "bytecode stack underflow");
+        assert_eq!(
+            message(vec![Push(Value::Bool(true)), Jump(2)]),
+            "jump past end of bytecode"
+        );
+        assert_eq!(
+            message(vec![]),
+            "bytecode must leave exactly 1 value, but leaves 0"
+        );
+    }
+}
@@ -126,6 +126,14 @@ impl<T: IntoList> IntoCStackList for T {
     }
 }
 
+/// A list that is already a `CStackList` converts to itself.
+impl<H: 'static, T: List + CStackListHeadLimit> IntoCStackList for CStackList<H, T> {
+    type Output = Self;
+    fn into_c_stack_list(self) -> Self::Output {
+        self
+    }
+}
+
 impl<H: 'static, T: List + CStackListHeadLimit> ListIndex<RangeFrom<U0>> for CStackList<H, T> {
     type Output = CStackList<H, T>;
     fn index(&self, _index: RangeFrom<U0>) -> &Self::Output {
@@ -308,13 +316,126 @@ impl<P: ListTypeProperty, H: 'static, T: ListTypeIteratorAdvance<P> + CStackList
     }
 }
 
+/// A function generic over the element type, applied to each element of a list by
+/// [`MapInto::map_into`].
+pub trait ElementMapper {
+    /// The result of mapping an element of type `T`.
+    type Output<T: 'static>: 'static;
+    /// Maps one element.
+    fn map<T: 'static>(&mut self, value: T) -> Self::Output<T>;
+}
+
+/// Builds a new [`CStackList`] by applying an [`ElementMapper`] to each element.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::*;
+///
+/// struct Wrap;
+///
+/// impl ElementMapper for Wrap {
+///     type Output<T: 'static> = Box<T>;
+///     fn map<T: 'static>(&mut self, value: T) -> Box<T> {
+///         Box::new(value)
+///     }
+/// }
+///
+/// let list = (1, "two").into_c_stack_list().map_into(&mut Wrap);
+/// assert_eq!(list, (Box::new(1), Box::new("two")).into_c_stack_list());
+/// ```
+pub trait MapInto {
+    /// The list of mapped elements.
+    type Output<F: ElementMapper>: List + CStackListHeadLimit;
+    /// Consumes the list, mapping each element with `f` in list order.
+    fn map_into<F: ElementMapper>(self, f: &mut F) -> Self::Output<F>;
+}
+
+impl<T: CStackListHeadLimit> MapInto for CNil<T> {
+    type Output<F: ElementMapper> = CNil<()>;
+    fn map_into<F: ElementMapper>(self, _f: &mut F) -> Self::Output<F> {
+        CNil(())
+    }
+}
+
+impl<H: 'static, T: MapInto + CStackListHeadLimit> MapInto for CStackList<H, T> {
+    type Output<F: ElementMapper> = CStackList<F::Output<H>, T::Output<F>>;
+    fn map_into<F: ElementMapper>(self, f: &mut F) -> Self::Output<F> {
+        let head = f.map(self.1);
+        CStackList(self.0.map_into(f), head)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use typenum::{U1, U2, U5};
 
-    use crate::list_traits::Item;
+    use crate::list_traits::{Item, TypeAt, Undefined};
 
     use super::*;
+    use crate::list_traits::ListSplitAt;
+
+    #[test]
+    fn get_dyn_walks_to_runtime_index() {
+        let list = (1, 2.5, "x").into_c_stack_list();
+        assert_eq!(list.get_dyn(0).unwrap().downcast_ref::<i32>(), Some(&1));
+        assert_eq!(list.get_dyn(1).unwrap().downcast_ref::<f64>(), Some(&2.5));
+        assert_eq!(list.get_dyn(2).unwrap().downcast_ref::<&str>(), Some(&"x"));
+        assert!(list.get_dyn(3).is_none());
+    }
+
+    #[test]
+    fn rev_view_indexes_back_to_front() {
+        let list = (1, 2.5, "x").into_c_stack_list();
+        let rev = list.rev_view();
+        assert_eq!(rev.len(), 3);
+        assert_eq!(rev.get::<&str>(0), Some(&"x"));
+        assert_eq!(rev.get::<f64>(1), Some(&2.5));
+        assert_eq!(rev.get::<i32>(2), Some(&1));
+        assert_eq!(rev.get::<i32>(1), None);
+        assert!(rev.get_dyn(3).is_none());
+        assert!(rev.get_dyn(usize::MAX).is_none());
+        assert!(().into_c_stack_list().rev_view().get_dyn(0).is_none());
+    }
+
+    #[test]
+    fn c_stack_list_converts_to_itself() {
+        fn len_of<L: IntoCStackList>(list: L) -> usize {
+            list.into_c_stack_list().len()
+        }
+        let list = (1, 2.5, "x").into_c_stack_list();
+        assert_eq!(len_of((1, 2.5, "x").into_c_stack_list()), 3);
+        assert_eq!(list.into_c_stack_list(), (1, 2.5, "x").into_c_stack_list());
+    }
+
+    #[test]
+    fn map_into_applies_generic_function_per_element() {
+        struct WrapSome(usize);
+
+        impl ElementMapper for WrapSome {
+            type Output<T: 'static> = Option<T>;
+            fn map<T: 'static>(&mut self, value: T) -> Option<T> {
+                self.0 += 1;
+                Some(value)
+            }
+        }
+
+        let mut wrap = WrapSome(0);
+        let list = (1, 2.5).into_c_stack_list().map_into(&mut wrap);
+        assert_eq!(list, (Some(1), Some(2.5)).into_c_stack_list());
+        assert_eq!(wrap.0, 2);
+    }
+
+    #[test]
+    fn split_at_partitions_list() {
+        let list = (1, 2.5, "x", 4).into_c_stack_list();
+        let (prefix, suffix) = list.split_at(U2::new());
+        assert_eq!(*prefix, (1, 2.5).into_c_stack_list());
+        assert_eq!(*suffix, ("x", 4).into_c_stack_list());
+        assert_eq!(prefix.len(), 2);
+        assert_eq!(*suffix.head(), "x");
+    }
+
     #[test]
     fn into_c_stack_list() {
         let list = (1, 2.5, 3, 4, "world", "Hello").into_c_stack_list();
@@ -373,6 +494,21 @@ mod tests {
         assert_eq!(type_name::<Two>(), "&str");
     }
 
+    #[test]
+    fn type_at() {
+        use std::any::TypeId;
+
+        type List = <(i32, f64, &'static str) as IntoList>::Output<CNil<()>>;
+        let one: TypeAt<List, U1> = 2.5f64;
+        assert_eq!(one, 2.5);
+        assert_eq!(TypeId::of::<TypeAt<List, U0>>(), TypeId::of::<i32>());
+        assert_eq!(TypeId::of::<TypeAt<List, U2>>(), TypeId::of::<&str>());
+        assert_eq!(
+            TypeId::of::<TypeAt<List, typenum::U3>>(),
+            TypeId::of::<Undefined>()
+        );
+    }
+
     #[test]
     fn cstack_list() {
         let list = CStackList(CNil(()), 32i32).push("Hello").push(42.5);
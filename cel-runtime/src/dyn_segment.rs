@@ -1,7 +1,9 @@
 use crate::c_stack_list::{CNil, CStackList, IntoCStackList};
 use crate::list_traits::{List, ListTypeIteratorAdvance, TypeIdIterator};
 use crate::memory::align_index;
-use crate::raw_segment::RawSegment;
+use crate::nullable::{CelNull, unify_null};
+use crate::numeric_promotion::{common_integer_type, widen_integer};
+use crate::raw_segment::{Limits, OpRecord, OpSpan, RawSegment, RawSegmentMark};
 use crate::raw_stack::RawStack;
 use crate::{CStackListHeadLimit, CStackListHeadPadded, ReverseList};
 use anyhow::Result;
@@ -9,9 +11,10 @@ use anyhow::anyhow;
 use anyhow::ensure;
 use std::any::{Any, TypeId};
 use std::borrow::Cow;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::cmp::max;
 use std::mem::MaybeUninit;
+use std::rc::Rc;
 
 thread_local! {
     // Safety: valid only during the execution of `call_dyn` on this thread.
@@ -55,6 +58,16 @@ pub type RawDropper = unsafe fn(*mut u8, &[AssociatedType]);
 /// of `ptr` instead of cloning causes a double-drop.
 pub type BoxExtractor = unsafe fn(*const u8) -> Box<dyn Any>;
 
+/// Moves a value of some fixed type out of `ptr`, boxing it as `Box<dyn Any>`. A
+/// tuple value is boxed as a `Vec<Box<dyn Any>>` holding its boxed elements.
+///
+/// # Safety
+/// `ptr` must point to a valid, live, properly aligned value of the type this
+/// function was generated for; `associated` must be that same value's own
+/// element list (empty for non-tuple values). The value is moved out, so the
+/// caller must not drop the original bytes afterward.
+pub type RawBoxer = unsafe fn(*const u8, &[AssociatedType]) -> Box<dyn Any>;
+
 /// Recursive type node carrying a [`TypeId`], display name, byte layout, and
 /// an in-place dropper — describes one element of a tuple (or, nested, one
 /// element of a tuple element).
@@ -72,6 +85,8 @@ pub struct AssociatedType {
     pub align: usize,
     /// In-place dropper for this element, callable at `base + offset`.
     pub dropper: RawDropper,
+    /// Moves this element out into a box, callable at `base + offset`.
+    pub boxer: RawBoxer,
     /// Child types, for a nested tuple element.
     pub associated: Vec<AssociatedType>,
 }
@@ -95,6 +110,19 @@ unsafe fn drop_tuple(ptr: *mut u8, associated: &[AssociatedType]) {
     }
 }
 
+/// `RawBoxer` for a tuple value: moves each element at `ptr + element.offset`
+/// out into its own box, recursing into nested tuples via their own boxers.
+///
+/// # Safety
+/// `ptr` must point to a live tuple value whose layout matches `associated`.
+unsafe fn box_tuple(ptr: *const u8, associated: &[AssociatedType]) -> Box<dyn Any> {
+    let elements: Vec<Box<dyn Any>> = associated
+        .iter()
+        .map(|elem| unsafe { (elem.boxer)(ptr.add(elem.offset), &elem.associated) })
+        .collect();
+    Box::new(elements)
+}
+
 /// Returns whether every element `TypeId` in `a` and `b` matches, in order —
 /// recursing into nested tuple elements' own `associated` shapes rather than
 /// stopping at their shared [`DynTuple`] marker `TypeId`.
@@ -192,6 +220,7 @@ unsafe fn extract_tuple_element(
 /// Holds metadata for a value pushed onto the stack: runtime type id, display
 /// name for errors, padding, size/alignment, an in-place dropper, and an
 /// optional list of associated element types (populated for tuples).
+#[derive(Clone)]
 pub struct StackInfo {
     /// Runtime type id for this stack slot (e.g. for scope matching).
     pub type_id: TypeId,
@@ -205,10 +234,43 @@ pub struct StackInfo {
     pub align: usize,
     /// In-place dropper for this value, callable at its own start address.
     pub(crate) raw_dropper: RawDropper,
+    /// Moves this value out into a box, callable at its own start address.
+    pub(crate) raw_boxer: RawBoxer,
     /// Associated element types (populated for tuples; empty otherwise).
     pub associated: Vec<AssociatedType>,
 }
 
+/// The arity and result type of one op pushed through an `op#` method of [`DynSegment`], as
+/// returned by [`DynSegment::dump_ops`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpInfo {
+    /// Number of stack values the op consumes.
+    pub arity: usize,
+    /// Name of the type the op pushes.
+    pub result_type: Cow<'static, str>,
+}
+
+/// Shows the type name rather than the opaque `TypeId`, and omits the function pointers.
+impl std::fmt::Debug for StackInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("StackInfo");
+        debug
+            .field("type_name", &self.type_name)
+            .field("padding", &self.padding)
+            .field("size", &self.size)
+            .field("align", &self.align);
+        if !self.associated.is_empty() {
+            let names: Vec<&str> = self
+                .associated
+                .iter()
+                .map(|elem| elem.type_name.as_ref())
+                .collect();
+            debug.field("associated", &names);
+        }
+        debug.finish()
+    }
+}
+
 /// Trait for converting a type list into a list of stack information.
 ///
 /// This trait allows compile-time type lists to be converted into runtime
@@ -239,6 +301,7 @@ impl<H: 'static, T: ToTypeIdList + 'static + CStackListHeadLimit> ToTypeIdList
             size: size_of::<H>(),
             align: align_of::<H>(),
             raw_dropper: |ptr, _associated| unsafe { std::ptr::drop_in_place(ptr.cast::<H>()) },
+            raw_boxer: |ptr, _associated| unsafe { Box::new(std::ptr::read(ptr.cast::<H>())) },
             associated: Vec::new(),
         });
         list
@@ -274,14 +337,124 @@ pub struct DynSegment {
     pub(crate) argument_ids: Vec<TypeId>,
     /// Type names for each argument slot, for error reporting (parallel to `argument_ids`).
     pub(crate) argument_names: Vec<Cow<'static, str>>,
+    /// Caller-supplied names for each argument slot, for error reporting; empty unless created
+    /// with [`new_with_names`](DynSegment::new_with_names).
+    argument_labels: Vec<String>,
     pub(crate) stack_ids: Vec<StackInfo>,
+    /// Number of leading `stack_ids` entries that are still the untouched arguments, which
+    /// [`arg_ref`](DynSegment::arg_ref) may copy.
+    live_arguments: usize,
     /// Fixed byte offset `stack_ids[0]` is laid out relative to; established
-    /// once at construction (post-argument space for a full segment, or the
-    /// as-if-already-popped ambient offset for a fragment — see
-    /// [`new_fragment`](Self::new_fragment)). The current top-of-stack offset
-    /// is always recomputed from this plus `stack_ids`, never cached, so it
-    /// can never drift out of sync after ops consume stack entries.
+    /// once at construction (zero for a full segment, whose `stack_ids` begin
+    /// with its arguments, or the as-if-already-popped ambient offset for a
+    /// fragment — see [`new_fragment`](Self::new_fragment)). The current
+    /// top-of-stack offset is always recomputed from this plus `stack_ids`,
+    /// never cached, so it can never drift out of sync after ops consume stack
+    /// entries.
     base_stack_index: usize,
+    /// High-water mark of the stack byte offset reached by any op built so
+    /// far, including ops inside joined fragments.
+    pub(crate) max_stack_offset: usize,
+    /// Whether any op pushed so far, including ops inside joined or sequenced fragments, can
+    /// fail; see [`is_fallible`](DynSegment::is_fallible).
+    fallible: bool,
+}
+
+/// How [`DynSegment::quantify`] folds a predicate over the elements of a list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    /// Whether the predicate holds for every element.
+    All,
+    /// Whether the predicate holds for at least one element.
+    Exists,
+}
+
+/// The current element of a predicate fragment created by [`DynSegment::new_element_fragment`],
+/// which [`DynSegment::push_element`] copies.
+#[derive(Clone, Copy, Debug)]
+pub struct ElementRef {
+    /// Absolute stack byte offset of the element.
+    offset: usize,
+    /// Pushes an op copying the element at the given offset, and records its type.
+    push: fn(&mut DynSegment, usize),
+}
+
+/// A segment taking no arguments and producing one value, compiled once and run by every op
+/// [`DynSegment::push_shared`] pushes for it.
+#[derive(Clone)]
+pub struct SharedSegment(Rc<DynSegment>);
+
+impl SharedSegment {
+    /// Shares `segment`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `segment` takes arguments or doesn't return exactly one value.
+    pub fn new(segment: DynSegment) -> Result<Self> {
+        ensure!(
+            segment.argument_ids.is_empty(),
+            "shared segment cannot take arguments, but has {} argument(s)",
+            segment.argument_ids.len()
+        );
+        ensure!(
+            segment.stack_ids.len() == 1,
+            "shared segment must have exactly 1 result, but has {}",
+            segment.stack_ids.len()
+        );
+        Ok(SharedSegment(Rc::new(segment)))
+    }
+}
+
+/// The context a [`DynSegment`] call adds to the error of a failing op, naming the op by its
+/// index and the segment by its argument types.
+///
+/// Retrieve it from the returned error with `error.downcast_ref::<OpFailure>()`, e.g. to map
+/// the op back to the source it was compiled from.
+#[derive(Clone, Debug)]
+pub struct OpFailure {
+    index: usize,
+    arguments: String,
+}
+
+impl OpFailure {
+    /// Returns the index of the failing op, as counted by [`DynSegment::op_count`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{DynSegment, OpFailure};
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 1u32);
+    /// segment.op1r(|_: u32| -> anyhow::Result<u32> { anyhow::bail!("failed") })?;
+    /// let error = segment.call0::<u32>().unwrap_err();
+    /// assert_eq!(error.downcast_ref::<OpFailure>().map(OpFailure::index), Some(1));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl std::fmt::Display for OpFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "op {} failed in segment with arguments ({})",
+            self.index, self.arguments
+        )
+    }
+}
+
+/// A point in a [`DynSegment`] that [`DynSegment::rewind`] returns to.
+#[derive(Clone, Debug)]
+pub struct SegmentMark {
+    segment: RawSegmentMark,
+    stack_ids: Vec<StackInfo>,
+    live_arguments: usize,
+    max_stack_offset: usize,
+    fallible: bool,
 }
 
 impl DynSegment {
@@ -296,11 +469,47 @@ impl DynSegment {
             segment: RawSegment::new(),
             argument_ids: stack_ids.iter().map(|s| s.type_id).collect(),
             argument_names: stack_ids.iter().map(|s| s.type_name.clone()).collect(),
+            argument_labels: Vec::new(),
+            live_arguments: stack_ids.len(),
             stack_ids,
-            base_stack_index: size_of::<ReverseList<Args::Output>>(),
+            base_stack_index: 0,
+            max_stack_offset: size_of::<ReverseList<Args::Output>>(),
+            fallible: false,
         }
     }
 
+    /// Creates a new empty segment like [`new`](Self::new), naming each argument slot so that
+    /// argument mismatches in [`call1`](Self::call1) and [`call2`](Self::call2) name the argument.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `names` doesn't have one name per argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new_with_names::<(u32,)>(&["count"]).unwrap();
+    /// segment.op1(|n: u32| n + 1).unwrap();
+    /// let err = segment.call1::<i64, u32>(1).unwrap_err();
+    /// assert_eq!(err.to_string(), "argument 'count' expected u32, got i64");
+    /// ```
+    pub fn new_with_names<Args: IntoCStackList>(names: &[&str]) -> Result<Self>
+    where
+        ReverseList<Args::Output>: ToTypeIdList,
+    {
+        let mut segment = Self::new::<Args>();
+        ensure!(
+            names.len() == segment.argument_ids.len(),
+            "expected {} argument name(s), got {}",
+            segment.argument_ids.len(),
+            names.len()
+        );
+        segment.argument_labels = names.iter().map(|name| (*name).to_string()).collect();
+        Ok(segment)
+    }
+
     /// Create a DynSegment that is a fragment of a larger segment, it may
     /// be used to implement conditional execution.
     ///
@@ -317,15 +526,64 @@ impl DynSegment {
             !self.stack_ids.is_empty(),
             "new_fragment requires a condition value on top of the stack"
         );
+        let base_stack_index = self.stack_offset_after(self.stack_ids.len().saturating_sub(1));
+        DynSegment {
+            segment: RawSegment::new(),
+            argument_ids: Vec::new(),
+            argument_names: Vec::new(),
+            argument_labels: Vec::new(),
+            stack_ids: Vec::new(),
+            live_arguments: 0,
+            base_stack_index,
+            max_stack_offset: base_stack_index,
+            fallible: false,
+        }
+    }
+
+    /// Creates an empty fragment whose ops will run on top of this segment's current stack, to be
+    /// appended with [`sequence`](Self::sequence).
+    #[must_use]
+    pub fn new_sequence_fragment(&self) -> Self {
+        let base_stack_index = self.current_stack_offset();
         DynSegment {
             segment: RawSegment::new(),
             argument_ids: Vec::new(),
             argument_names: Vec::new(),
+            argument_labels: Vec::new(),
             stack_ids: Vec::new(),
-            base_stack_index: self.stack_offset_after(self.stack_ids.len().saturating_sub(1)),
+            live_arguments: 0,
+            base_stack_index,
+            max_stack_offset: base_stack_index,
+            fallible: false,
         }
     }
 
+    /// Creates a fragment that [`quantify`](Self::quantify) runs once per element of the
+    /// `Vec<T>` on top of the stack. The fragment starts with the current element as its only
+    /// stack value; the returned [`ElementRef`] copies it with
+    /// [`push_element`](Self::push_element).
+    ///
+    /// - Precondition: the top of the stack holds the `Vec<T>` that `quantify` will pop before
+    ///   the fragment's ops run.
+    #[must_use]
+    pub fn new_element_fragment<T: Clone + 'static>(&self) -> (Self, ElementRef) {
+        debug_assert_eq!(
+            self.peek_output_type_id(),
+            Some(TypeId::of::<Vec<T>>()),
+            "new_element_fragment requires a list on top of the stack"
+        );
+        let mut fragment = self.new_fragment();
+        let element = ElementRef {
+            offset: align_index(align_of::<T>(), fragment.base_stack_index),
+            push: |segment, offset| {
+                unsafe { segment.segment.push_clone_at::<T>(offset) };
+                segment.push_type::<T>();
+            },
+        };
+        fragment.push_type::<T>();
+        (fragment, element)
+    }
+
     /// Verifies that the argument types match the expected types on the type stack.
     ///
     /// Returns an error if the argument types don't match the expected types or if
@@ -335,6 +593,15 @@ impl DynSegment {
     /// is done in argument order, not stack order.
     // REVISIT: pop_types should just return the last n padding values
     fn pop_types<L: ListTypeIteratorAdvance<TypeId> + 'static>(&mut self) -> Result<()> {
+        let start = self.check_top_types::<L>()?;
+        self.stack_ids.truncate(start);
+        self.retire_arguments(start);
+        Ok(())
+    }
+
+    /// Checks, as [`pop_types`](Self::pop_types) does, that the top of the type stack holds the
+    /// types of `L` in argument order, and returns the index of the first of them.
+    fn check_top_types<L: ListTypeIteratorAdvance<TypeId> + 'static>(&self) -> Result<usize> {
         ensure!(
             L::LENGTH <= self.stack_ids.len(),
             "wrong number of arguments: expected {}, got {}",
@@ -346,8 +613,13 @@ impl DynSegment {
             TypeIdIterator::<L>::new().eq(self.stack_ids[start..].iter().map(|info| info.type_id)),
             "stack type ids do not match"
         );
-        self.stack_ids.truncate(start);
-        Ok(())
+        Ok(start)
+    }
+
+    /// Records that stack entries from `first` up have been consumed or relabeled, so arguments
+    /// among them can no longer be referenced.
+    fn retire_arguments(&mut self, first: usize) {
+        self.live_arguments = self.live_arguments.min(first);
     }
 
     /// Computes the top-of-stack byte offset after the first `count` entries
@@ -369,6 +641,42 @@ impl DynSegment {
         offset
     }
 
+    /// Raises the stack high-water mark to the current top-of-stack offset.
+    ///
+    /// - Complexity: O(n) in the number of stack entries.
+    fn update_max_stack_offset(&mut self) {
+        self.max_stack_offset = max(self.max_stack_offset, self.current_stack_offset());
+    }
+
+    /// Returns the maximum stack byte offset any op built so far reaches during execution.
+    ///
+    /// This is the number of bytes a stack needs to run the segment without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 1u32);
+    /// segment.op0(|| 2u32);
+    /// segment.op2(|a: u32, b: u32| a + b).unwrap();
+    /// assert_eq!(segment.max_stack_offset(), 8);
+    /// ```
+    #[must_use]
+    pub fn max_stack_offset(&self) -> usize {
+        self.max_stack_offset
+    }
+
+    /// Records the op pushed last as an op of `arity` pushing an `R`, for
+    /// [`dump_ops`](Self::dump_ops).
+    fn push_op_info<R: 'static>(&mut self, arity: usize) {
+        self.segment.last_op_record_mut().info = Some(OpInfo {
+            arity,
+            result_type: Cow::Borrowed(std::any::type_name::<R>()),
+        });
+    }
+
     /// Push type to stack and register dropper.
     fn push_type<T>(&mut self)
     where
@@ -385,8 +693,10 @@ impl DynSegment {
             size: size_of::<T>(),
             align: align_of::<T>(),
             raw_dropper: |ptr, _associated| unsafe { std::ptr::drop_in_place(ptr.cast::<T>()) },
+            raw_boxer: |ptr, _associated| unsafe { Box::new(std::ptr::read(ptr.cast::<T>())) },
             associated: Vec::new(),
         });
+        self.update_max_stack_offset();
     }
 
     /// Returns the current parse-time stack byte offset.
@@ -429,6 +739,7 @@ impl DynSegment {
         debug_assert!(n <= self.stack_ids.len());
         let start = self.stack_ids.len() - n;
         let elems: Vec<StackInfo> = self.stack_ids.drain(start..).collect();
+        self.retire_arguments(start);
 
         let mut ambient_offset = ambient_start;
         let mut offset = 0usize;
@@ -456,6 +767,7 @@ impl DynSegment {
                 size: elem.size,
                 align: elem.align,
                 dropper: elem.raw_dropper,
+                boxer: elem.raw_boxer,
                 associated: elem.associated.clone(),
             });
 
@@ -492,8 +804,10 @@ impl DynSegment {
             size: total_size,
             align: tuple_align,
             raw_dropper: drop_tuple,
+            raw_boxer: box_tuple,
             associated,
         });
+        self.update_max_stack_offset();
     }
 
     /// Extracts element `index` from the tuple on top of the stack, replacing
@@ -550,6 +864,7 @@ impl DynSegment {
             size: target.size,
             align: target.align,
             raw_dropper: target.dropper,
+            raw_boxer: target.boxer,
             associated: target.associated,
         });
     }
@@ -592,10 +907,12 @@ impl DynSegment {
         match result {
             Ok(r) => Ok(r),
             Err(e) => {
-                for (size, padding, raw_dropper, associated) in unwind.iter().rev() {
-                    unsafe {
-                        stack.drop_sized(*size, *padding, |ptr| raw_dropper(ptr, associated));
-                    }
+                unsafe {
+                    stack.drop_all(unwind.iter().map(
+                        |(size, padding, raw_dropper, associated)| {
+                            (*size, *padding, |ptr| raw_dropper(ptr, associated))
+                        },
+                    ));
                 }
                 Err(e)
             }
@@ -623,6 +940,82 @@ impl DynSegment {
         &self.stack_ids[start..]
     }
 
+    /// Returns whether an op consuming values of the types in `L`, in argument order, could be
+    /// pushed onto the current stack, without changing the segment; e.g. to gray out
+    /// inapplicable ops in a palette.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 1u32);
+    /// segment.op0(|| "two");
+    /// assert!(segment.can_apply::<(u32, (&str, ()))>());
+    /// assert!(!segment.can_apply::<(&str, (u32, ()))>());
+    /// ```
+    #[must_use]
+    pub fn can_apply<L: ListTypeIteratorAdvance<TypeId> + 'static>(&self) -> bool {
+        self.check_top_types::<L>().is_ok()
+    }
+
+    /// Pushes an operation that copies argument `index` onto the top of the stack, leaving the
+    /// argument in place so it can be used again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of range, if the argument is not a `T`, or if an op has
+    /// already consumed the argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// // x * x + 1, reading x twice
+    /// let mut segment = DynSegment::new::<(i32,)>();
+    /// segment.arg_ref::<i32>(0).unwrap();
+    /// segment.op2(|a: i32, b: i32| a * b).unwrap();
+    /// segment.op0(|| 1i32);
+    /// segment.op2(|a: i32, b: i32| a + b).unwrap();
+    /// assert_eq!(segment.call1::<i32, i32>(6).unwrap(), 37);
+    /// ```
+    pub fn arg_ref<T: Clone + 'static>(&mut self, index: usize) -> Result<()> {
+        ensure!(
+            index < self.argument_ids.len(),
+            "argument index {index} out of range for {} argument(s)",
+            self.argument_ids.len()
+        );
+        ensure!(
+            self.argument_ids[index] == TypeId::of::<T>(),
+            "argument {index} is {}, not {}",
+            self.argument_names[index],
+            std::any::type_name::<T>()
+        );
+        ensure!(
+            index < self.live_arguments,
+            "argument {index} has already been consumed"
+        );
+        let offset = align_index(align_of::<T>(), self.stack_offset_after(index));
+        unsafe { self.segment.push_clone_at::<T>(offset) };
+        self.push_type::<T>();
+        Ok(())
+    }
+
+    /// Pushes an operation that copies the current element of a predicate fragment onto the top
+    /// of the stack.
+    ///
+    /// # Safety
+    ///
+    /// `self` must be the fragment `element` was created with by
+    /// [`new_element_fragment`](Self::new_element_fragment), or a fragment whose ops run while
+    /// that fragment's element is still on the stack (e.g. a branch joined into it, or the
+    /// predicate of a nested comprehension).
+    pub unsafe fn push_element(&mut self, element: ElementRef) {
+        (element.push)(self, element.offset);
+    }
+
     /// Pushes a nullary operation that takes no arguments and returns a value of type R.
     ///
     /// The return type is tracked in the type stack for subsequent operations.
@@ -633,6 +1026,7 @@ impl DynSegment {
     {
         self.segment.push_op0(op);
         self.push_type::<R>();
+        self.push_op_info::<R>(0);
     }
 
     /// Pushes a nullary operation that takes no arguments and returns a `Result<R>`.
@@ -647,7 +1041,9 @@ impl DynSegment {
         let unwind = self.capture_unwind();
         self.segment
             .raw0(move |stack| Self::unwind_on_err(&unwind, stack, op()));
+        self.fallible = true;
         self.push_type::<R>();
+        self.push_op_info::<R>(0);
     }
 
     /// Pushes a unary operation that takes one argument of type `T` and returns a `Result<R>`.
@@ -671,7 +1067,9 @@ impl DynSegment {
             move |stack, t| Self::unwind_on_err(&unwind, stack, op(t)),
             p0,
         );
+        self.fallible = true;
         self.push_type::<R>();
+        self.push_op_info::<R>(1);
         Ok(())
     }
 
@@ -698,10 +1096,200 @@ impl DynSegment {
             p0,
             p1,
         );
+        self.fallible = true;
         self.push_type::<R>();
+        self.push_op_info::<R>(2);
         Ok(())
     }
 
+    /// Records `label` as a human-readable name for the op pushed next. See
+    /// [`RawSegment::push_label`].
+    pub fn push_label(&mut self, label: &'static str) {
+        self.segment.push_label(label);
+    }
+
+    /// Returns the labels recorded with [`push_label`](Self::push_label), in op order, including
+    /// the labels recorded in fragments joined into this segment.
+    #[must_use]
+    pub fn op_labels(&self) -> Vec<&'static str> {
+        self.segment.op_labels()
+    }
+
+    /// Returns the metadata of each op, indexed like [`op_count`](Self::op_count) and
+    /// [`OpFailure::index`].
+    #[must_use]
+    pub fn op_records(&self) -> &[OpRecord] {
+        self.segment.op_records()
+    }
+
+    /// Attributes the ops pushed since the last call to `span`. See
+    /// [`RawSegment::attribute_ops`].
+    pub fn attribute_ops(&mut self, span: OpSpan) {
+        self.segment.attribute_ops(span);
+    }
+
+    /// Returns the arity and result type of each op pushed through an `op#` method, in push
+    /// order. Unlike [`op_count`](Self::op_count), this omits internal ops such as argument
+    /// copies and conditionals, and ops pushed within joined fragments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{DynSegment, OpInfo};
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 2u8);
+    /// segment.op1(|n: u8| n.to_string()).unwrap();
+    /// let ops = segment.dump_ops();
+    /// assert_eq!(ops[1], OpInfo { arity: 1, result_type: "alloc::string::String".into() });
+    /// ```
+    #[must_use]
+    pub fn dump_ops(&self) -> Vec<OpInfo> {
+        self.segment
+            .op_records()
+            .iter()
+            .filter_map(|record| record.info.clone())
+            .collect()
+    }
+
+    /// Returns whether executing the segment can fail because of an op: `true` once a fallible
+    /// op (`op0r`, `op1r`, `op2r`) has been pushed, directly or inside a fragment joined with
+    /// [`join2`](Self::join2) or appended with [`sequence`](Self::sequence). A segment for which
+    /// this is `false` can still fail to be called with mismatched argument or result types.
+    ///
+    /// Execution limits are not ops and are not counted here: run under a step budget or
+    /// deadline ([`RawSegment::call0_limited`](crate::RawSegment::call0_limited),
+    /// [`RawSegment::call0_with_deadline`](crate::RawSegment::call0_with_deadline)), even a
+    /// segment for which this is `false` fails once the limit is exceeded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 2i32);
+    /// assert!(!segment.is_fallible());
+    /// segment.op1r(|n: i32| Ok(n * 2)).unwrap();
+    /// assert!(segment.is_fallible());
+    /// ```
+    #[must_use]
+    pub fn is_fallible(&self) -> bool {
+        self.fallible
+    }
+
+    /// Returns the number of ops pushed so far, including the ops of joined fragments.
+    #[must_use]
+    pub fn op_count(&self) -> usize {
+        self.segment.op_count()
+    }
+
+    /// Returns whether `self` and `other` are structurally equivalent: they take the same argument
+    /// types, track the same types on the stack, and have the same number of ops. Ops are
+    /// closures, which can't be compared, so this is a best-effort check for tests of code
+    /// generation; two segments computing different values can still compare equal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut a = DynSegment::new::<(i32,)>();
+    /// a.op1(|x: i32| x + 1).unwrap();
+    /// let mut b = DynSegment::new::<(i32,)>();
+    /// b.op1(|x: i32| x * 2).unwrap();
+    /// assert!(a.structurally_eq(&b));
+    /// b.op1(|x: i32| x > 0).unwrap();
+    /// assert!(!a.structurally_eq(&b));
+    /// ```
+    #[must_use]
+    pub fn structurally_eq(&self, other: &DynSegment) -> bool {
+        self.argument_ids == other.argument_ids
+            && self
+                .stack_ids
+                .iter()
+                .map(|info| info.type_id)
+                .eq(other.stack_ids.iter().map(|info| info.type_id))
+            && self.op_count() == other.op_count()
+    }
+
+    /// Returns a mark for the current state of the segment, for [`rewind`](Self::rewind).
+    ///
+    /// - Complexity: O(n) in the current stack depth.
+    #[must_use]
+    pub fn mark(&self) -> SegmentMark {
+        SegmentMark {
+            segment: self.segment.mark(),
+            stack_ids: self.stack_ids.clone(),
+            live_arguments: self.live_arguments,
+            max_stack_offset: self.max_stack_offset,
+            fallible: self.fallible,
+        }
+    }
+
+    /// Removes every op pushed since `mark` and restores the segment to its state at `mark`,
+    /// including the type stack, so values the removed ops consumed are back on it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mark` is past the end of the op list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.just(1i32);
+    /// let mark = segment.mark();
+    /// segment.just(2i32);
+    /// segment.just(3i32);
+    /// segment.rewind(mark);
+    /// assert_eq!(segment.op_count(), 1);
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 1);
+    /// ```
+    pub fn rewind(&mut self, mark: SegmentMark) {
+        self.segment.rewind(mark.segment);
+        self.stack_ids = mark.stack_ids;
+        self.live_arguments = mark.live_arguments;
+        self.max_stack_offset = mark.max_stack_offset;
+        self.fallible = mark.fallible;
+    }
+
+    /// Runs `build` to push ops onto the segment as one transaction: if `build` fails, every op
+    /// it pushed is removed and the type stack is restored, so the segment is exactly as it was
+    /// before the call.
+    ///
+    /// Constants stored with [`RawSegment::push_constant`] by `build` stay in the constant table.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `build`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 2i32);
+    /// let result = segment.with_ops(|segment| {
+    ///     segment.op0(|| 3i32);
+    ///     segment.op2(|a: i32, b: i32| a * b)?;
+    ///     segment.op2(|a: i32, b: i32| a + b) // only one value left: fails
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 2);
+    /// ```
+    pub fn with_ops(&mut self, build: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        let mark = self.mark();
+        let result = build(self);
+        if result.is_err() {
+            self.rewind(mark);
+        }
+        result
+    }
+
     /// Pushes a value to the stack without any operations.
     pub fn just<T: 'static + Clone>(&mut self, value: T) {
         self.op0(move || value.clone());
@@ -773,9 +1361,13 @@ impl DynSegment {
         CALL_DYN_PTR.with(|c| c.set(inputs.as_ptr() as usize));
         CALL_DYN_LEN.with(|c| c.set(inputs.len()));
         let _guard = DynCallGuard;
+        let mut stack = self.new_stack();
         // Safety: type check above verified R matches stack top; bypasses pop_types
         // so stack_ids is not consumed, enabling repeated calls.
-        unsafe { self.segment.call0() }
+        unsafe {
+            self.run_on_stack(&mut stack)?;
+            Ok(stack.pop(false))
+        }
     }
 
     /// Executes the segment once and splits its tuple result into one boxed value
@@ -851,12 +1443,12 @@ impl DynSegment {
         CALL_DYN_LEN.with(|c| c.set(inputs.len()));
         let _guard = DynCallGuard;
 
-        let mut stack = RawStack::with_base_alignment(self.segment.base_alignment());
+        let mut stack = self.new_stack();
         // Safety: the checks above verified the segment builds exactly one tuple
         // value with `extractors.len()` matching elements; call_dyn's own argument
         // preconditions (no pre-loaded arguments) hold identically here.
         unsafe {
-            self.segment.call0_stack(&mut stack)?;
+            self.run_on_stack(&mut stack)?;
         }
 
         let tuple_base = stack.len() - tuple_size;
@@ -894,6 +1486,7 @@ impl DynSegment {
         self.pop_types::<(T, ())>()?;
         self.segment.push_op1(op, p0);
         self.push_type::<R>();
+        self.push_op_info::<R>(1);
         Ok(())
     }
 
@@ -916,6 +1509,51 @@ impl DynSegment {
         self.pop_types::<(T, (U, ()))>()?;
         self.segment.push_op2(op, p0, p1);
         self.push_type::<R>();
+        self.push_op_info::<R>(2);
+        Ok(())
+    }
+
+    /// Pushes a unary operation that converts the `T` beneath the `U` on top of the stack into an
+    /// `R`, leaving the `U` on top. Used to reconcile a binary operator's left operand after its
+    /// right operand has been pushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the top two types on the type stack aren't `T` and `U`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 3i32);
+    /// segment.op0(|| 2.5f64);
+    /// segment.op1_beneath::<i32, f64, _, _>(f64::from).unwrap();
+    /// segment.op2(|a: f64, b: f64| a + b).unwrap();
+    /// assert_eq!(segment.call0::<f64>().unwrap(), 5.5);
+    /// ```
+    pub fn op1_beneath<T, U, R, F>(&mut self, op: F) -> Result<()>
+    where
+        F: Fn(T) -> R + 'static,
+        T: 'static,
+        U: 'static,
+        R: 'static,
+    {
+        let [p0, p1] = self.get_last_n_padded::<2>();
+        self.pop_types::<(T, (U, ()))>()?;
+        self.segment.raw0_(move |stack| {
+            let top: U = unsafe { stack.pop(p1) };
+            let beneath: T = unsafe { stack.pop(p0) };
+            stack.push(op(beneath));
+            stack.push(top);
+            Ok(())
+        });
+        self.segment
+            .update_base_alignment(max(align_of::<R>(), align_of::<U>()));
+        self.push_type::<R>();
+        self.push_type::<U>();
+        self.push_op_info::<R>(1);
         Ok(())
     }
 
@@ -939,6 +1577,7 @@ impl DynSegment {
         self.pop_types::<(T, (U, (V, ())))>()?;
         self.segment.push_op3(op, p0, p1, p2);
         self.push_type::<R>();
+        self.push_op_info::<R>(3);
         Ok(())
     }
 
@@ -946,7 +1585,8 @@ impl DynSegment {
     ///
     /// This method creates a conditional operation that executes one of two fragments
     /// based on a boolean value on the stack. Both fragments must have no arguments
-    /// and return the same type.
+    /// and return the same type. If the executed fragment fails, the values beneath the
+    /// condition are dropped before the error propagates, as for any other failing op.
     ///
     /// # Arguments
     ///
@@ -990,95 +1630,648 @@ impl DynSegment {
             "fragment result types must match"
         );
 
+        let unwind = self.capture_unwind();
         self.stack_ids.push(fragment_0.stack_ids.pop().unwrap());
+        self.max_stack_offset = max(
+            self.max_stack_offset,
+            max(fragment_0.max_stack_offset, fragment_1.max_stack_offset),
+        );
         self.segment.update_base_alignment(max(
             fragment_0.segment.base_alignment(),
             fragment_1.segment.base_alignment(),
         ));
+        self.fallible |= fragment_0.fallible || fragment_1.fallible;
 
+        let nested = [
+            fragment_0.segment.op_records(),
+            fragment_1.segment.op_records(),
+        ]
+        .concat();
         let raw_segment_0 = fragment_0.segment;
         let raw_segment_1 = fragment_1.segment;
 
-        /*
-           - pass the stack to call0
-        */
+        // A failing branch has already dropped its own values; drop the ones beneath it.
         self.segment.raw0_(move |stack| {
             let conditional = unsafe { stack.pop(p0) };
-            if conditional {
-                unsafe {
-                    raw_segment_0.call0_stack(stack)?;
-                }
+            let result = if conditional {
+                unsafe { raw_segment_0.call0_stack(stack) }
             } else {
-                unsafe {
-                    raw_segment_1.call0_stack(stack)?;
-                }
-            }
-            Ok(())
+                unsafe { raw_segment_1.call0_stack(stack) }
+            };
+            Self::unwind_on_err(&unwind, stack, result)
         });
+        self.segment.last_op_record_mut().nested = nested;
         Ok(())
     }
 
-    /// Executes all operations in the segment and returns the final result.
+    /// Pops the `T` on top of the stack and passes it to `select`: a `Some` result is pushed, and
+    /// on `None` the `fallback` fragment runs to produce the result instead. This is the basis of
+    /// `a ?: b`, which keeps a truthy `a` and otherwise evaluates `b`.
     ///
-    /// # Returns
-    /// - `Ok(R)` if execution succeeds and the final value is of type R
+    /// - Precondition: `fallback` was created with [`new_fragment`](Self::new_fragment) while the
+    ///   `T` was on top of the stack.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    ///   - There are unexpected arguments (expected none)
-    ///   - The final type doesn't match R
-    ///   - There are remaining values on the stack after getting the result
-    ///
-    pub fn call0<R>(&mut self) -> Result<R>
-    where
-        R: 'static,
-    {
-        if !self.argument_ids.is_empty() {
-            return Err(anyhow::anyhow!(
-                "expected no arguments, but segment requires {} argument(s)",
-                self.argument_ids.len()
-            ));
-        }
-        self.pop_types::<(R, ())>()?;
-        if !self.stack_ids.is_empty() {
-            return Err(anyhow::anyhow!(
-                "{} value(s) left on execution stack",
-                self.stack_ids.len()
-            ));
-        }
-        unsafe { self.segment.call0() }
-    }
-
-    /// Executes all operations in the segment with one argument and returns the final result.
+    /// * The top of the stack is not a `T`
+    /// * `fallback` takes arguments
+    /// * `fallback` doesn't return exactly one `R`
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// Returns an error if:
-    ///   - The number of arguments doesn't match (expected one)
-    ///   - The argument type doesn't match the expected type
-    ///   - The final type doesn't match R
-    ///   - There are remaining values on the stack after getting the result
+    /// ```
+    /// use cel_runtime::DynSegment;
     ///
-    pub fn call1<A, R>(&mut self, arg: A) -> Result<R>
-    where
-        A: 'static,
-        R: 'static,
-    {
-        if self.argument_ids.len() != 1 {
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 0i32);
+    /// let mut fallback = segment.new_fragment();
+    /// fallback.op0(|| 5i32);
+    /// segment.coalesce(fallback, |n: i32| (n != 0).then_some(n)).unwrap();
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 5);
+    /// ```
+    pub fn coalesce<T: 'static, R: 'static>(
+        &mut self,
+        fallback: DynSegment,
+        select: fn(T) -> Option<R>,
+    ) -> Result<()> {
+        let [p0] = self.get_last_n_padded::<1>();
+        self.pop_types::<(T, ())>()?;
+        ensure!(
+            fallback.argument_ids.is_empty(),
+            "fallback cannot take arguments, but has {} argument(s)",
+            fallback.argument_ids.len()
+        );
+        ensure!(
+            fallback.stack_ids.len() == 1,
+            "fallback must have exactly 1 result, but has {}",
+            fallback.stack_ids.len()
+        );
+        ensure!(
+            fallback.stack_ids[0].type_id == TypeId::of::<R>(),
+            "fallback must produce `{}`, but produces `{}`",
+            std::any::type_name::<R>(),
+            fallback.stack_ids[0].type_name
+        );
+
+        let unwind = self.capture_unwind();
+        self.push_type::<R>();
+        self.max_stack_offset = max(self.max_stack_offset, fallback.max_stack_offset);
+        self.segment
+            .update_base_alignment(max(fallback.segment.base_alignment(), align_of::<R>()));
+        self.fallible |= fallback.fallible;
+
+        // A failing fallback has already dropped its own values; drop the ones beneath it.
+        let nested = fallback.segment.op_records().to_vec();
+        let raw_segment = fallback.segment;
+        self.segment.raw0_(move |stack| {
+            let value: T = unsafe { stack.pop(p0) };
+            match select(value) {
+                Some(result) => {
+                    stack.push(result);
+                    Ok(())
+                }
+                None => {
+                    let result = unsafe { raw_segment.call0_stack(stack) };
+                    Self::unwind_on_err(&unwind, stack, result)
+                }
+            }
+        });
+        self.segment.last_op_record_mut().nested = nested;
+        Ok(())
+    }
+
+    /// Appends `other`, a single-result producer, so that its result is pushed on top of the
+    /// current stack. Unlike [`join2`](Self::join2), nothing is popped: both the values already on
+    /// the stack and `other`'s result remain available to following ops.
+    ///
+    /// - Precondition: `other` was created with
+    ///   [`new_sequence_fragment`](Self::new_sequence_fragment) at the current stack top.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// * `other` takes arguments
+    /// * `other` doesn't return exactly one value
+    /// * `other` was not created at the current stack top
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 3i32);
+    /// let mut other = segment.new_sequence_fragment();
+    /// other.op0(|| 4i32);
+    /// segment.sequence(other).unwrap();
+    /// segment.op2(|a: i32, b: i32| a + b).unwrap();
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 7);
+    /// ```
+    pub fn sequence(&mut self, mut other: DynSegment) -> Result<()> {
+        ensure!(
+            other.argument_ids.is_empty(),
+            "sequenced segment cannot take arguments, but has {} argument(s)",
+            other.argument_ids.len()
+        );
+        ensure!(
+            other.stack_ids.len() == 1,
+            "sequenced segment must have exactly 1 result, but has {}",
+            other.stack_ids.len()
+        );
+        ensure!(
+            other.base_stack_index == self.current_stack_offset(),
+            "sequenced segment was not created at the current stack top"
+        );
+
+        let unwind = self.capture_unwind();
+        self.stack_ids.push(other.stack_ids.pop().unwrap());
+        self.max_stack_offset = max(self.max_stack_offset, other.max_stack_offset);
+        self.segment
+            .update_base_alignment(other.segment.base_alignment());
+        self.fallible |= other.fallible;
+
+        let nested = other.segment.op_records().to_vec();
+        let raw_segment = other.segment;
+        self.segment.raw0_(move |stack| {
+            let result = unsafe { raw_segment.call0_stack(stack) };
+            Self::unwind_on_err(&unwind, stack, result)
+        });
+        self.segment.last_op_record_mut().nested = nested;
+        Ok(())
+    }
+
+    /// Pushes an op that runs `shared` on a stack of its own and moves its result onto this
+    /// segment's stack. Unlike [`sequence`](Self::sequence), which consumes its fragment, any
+    /// number of ops can run one shared segment, so pushing one costs the same however large the
+    /// shared segment is. The shared segment's ops aren't listed by
+    /// [`op_labels`](Self::op_labels).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{DynSegment, SharedSegment};
+    ///
+    /// let mut shared = DynSegment::new::<()>();
+    /// shared.op0(|| 3i32);
+    /// shared.op1(|x: i32| x + 1).unwrap();
+    /// let shared = SharedSegment::new(shared).unwrap();
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.push_shared(&shared);
+    /// segment.push_shared(&shared);
+    /// segment.op2(|a: i32, b: i32| a * b).unwrap();
+    /// assert_eq!(segment.call0::<i32>().unwrap(), 16);
+    /// ```
+    pub fn push_shared(&mut self, shared: &SharedSegment) {
+        let other = &shared.0;
+        let current = self.current_stack_offset();
+        let mut info = other.stack_ids[0].clone();
+        info.padding = align_index(info.align, current) != current;
+        let (size, align) = (info.size, info.align);
+
+        let unwind = self.capture_unwind();
+        self.stack_ids.push(info);
+        self.update_max_stack_offset();
+        self.segment.update_base_alignment(align);
+        self.fallible |= other.fallible;
+
+        let scratch = RefCell::new(RawStack::with_base_alignment_and_capacity(
+            other.segment.base_alignment(),
+            other.max_stack_offset,
+        ));
+        let other = Rc::clone(other);
+        self.segment.raw0_(move |stack| {
+            let mut scratch = scratch.borrow_mut();
+            scratch.set_limits(stack.limits().clone());
+            let result = unsafe { other.segment.call0_stack(&mut scratch) };
+            scratch.set_limits(Limits::default());
+            if result.is_ok() {
+                // The result is moved, so its bytes are discarded from the scratch stack without
+                // being dropped.
+                let start = scratch.len() - size;
+                unsafe {
+                    scratch.read_at(start, |src| stack.push_raw(align, size, src.cast()));
+                }
+            }
+            unsafe { scratch.truncate_to(0, false) };
+            Self::unwind_on_err(&unwind, stack, result)
+        });
+    }
+
+    /// Replaces the `Vec<T>` on top of the stack with whether `predicate` holds for all or for
+    /// any of its elements, as selected by `quantifier`. Elements are tested in order, stopping
+    /// at the first one that decides the result, so `All` is `true` and `Exists` is `false` for
+    /// an empty list.
+    ///
+    /// - Precondition: `predicate` was created with
+    ///   [`new_element_fragment`](Self::new_element_fragment) for the list on top of the stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// * The top of the stack is not a `Vec<T>`
+    /// * `predicate` takes arguments or was not created for the list on top of the stack
+    /// * `predicate` doesn't leave its element and exactly one `bool`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{DynSegment, Quantifier};
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| vec![1i32, 2, 3]);
+    /// let (mut predicate, element) = segment.new_element_fragment::<i32>();
+    /// unsafe { predicate.push_element(element) };
+    /// predicate.op1(|x: i32| x > 1).unwrap();
+    /// segment.quantify::<i32>(predicate, Quantifier::Exists).unwrap();
+    /// assert!(segment.call0::<bool>().unwrap());
+    /// ```
+    pub fn quantify<T: 'static>(
+        &mut self,
+        predicate: DynSegment,
+        quantifier: Quantifier,
+    ) -> Result<()> {
+        let [p0] = self.get_last_n_padded::<1>();
+        self.pop_types::<(Vec<T>, ())>()?;
+        ensure!(
+            predicate.argument_ids.is_empty(),
+            "predicate cannot take arguments, but has {} argument(s)",
+            predicate.argument_ids.len()
+        );
+        ensure!(
+            predicate.base_stack_index == self.current_stack_offset(),
+            "predicate was not created for the list on top of the stack"
+        );
+        ensure!(
+            predicate.stack_ids.len() == 2 && predicate.stack_ids[0].type_id == TypeId::of::<T>(),
+            "predicate must leave its element and exactly 1 result, but leaves {} value(s)",
+            predicate.stack_ids.len()
+        );
+        ensure!(
+            predicate.stack_ids[1].type_id == TypeId::of::<bool>(),
+            "predicate must produce a `bool`, but produces `{}`",
+            predicate.stack_ids[1].type_name
+        );
+
+        let unwind = self.capture_unwind();
+        let result_padding = predicate.stack_ids[1].padding;
+        self.max_stack_offset = max(self.max_stack_offset, predicate.max_stack_offset);
+        self.segment
+            .update_base_alignment(max(predicate.segment.base_alignment(), align_of::<T>()));
+        self.fallible |= predicate.fallible;
+
+        // The result `Exists` stops at, and `All` stops at its negation.
+        let decisive = quantifier == Quantifier::Exists;
+        let nested = predicate.segment.op_records().to_vec();
+        let raw_segment = predicate.segment;
+        self.segment.raw0(move |stack| {
+            let list: Vec<T> = unsafe { stack.pop(p0) };
+            for element in list {
+                let element_padding = stack.push(element);
+                let result = unsafe { raw_segment.call0_stack(stack) };
+                Self::unwind_on_err(&unwind, stack, result)?;
+                let holds: bool = unsafe { stack.pop(result_padding) };
+                drop(unsafe { stack.pop::<T>(element_padding) });
+                if holds == decisive {
+                    return Ok(decisive);
+                }
+            }
+            Ok(!decisive)
+        });
+        self.segment.last_op_record_mut().nested = nested;
+        self.push_type::<bool>();
+        Ok(())
+    }
+
+    /// Joins two conditional fragments like [`join2`](Self::join2), first widening integer
+    /// results of different types to their common wider type.
+    ///
+    /// If the fragments produce different integer types (e.g. `i32` and `i64`), an op converting
+    /// the narrower result is appended to its fragment, using the table in
+    /// [`numeric_promotion`](crate::numeric_promotion). Results that already match, or that have
+    /// no common integer type, are passed to `join2` unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`join2`](Self::join2), after promotion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| false);
+    /// let mut then_fragment = segment.new_fragment();
+    /// then_fragment.op0(|| 1i32);
+    /// let mut else_fragment = segment.new_fragment();
+    /// else_fragment.op0(|| 2i64);
+    /// segment.join2_coerced(then_fragment, else_fragment).unwrap();
+    /// assert_eq!(segment.call0::<i64>().unwrap(), 2);
+    /// ```
+    pub fn join2_coerced(
+        &mut self,
+        mut fragment_0: DynSegment,
+        mut fragment_1: DynSegment,
+    ) -> Result<()> {
+        if let ([info_0], [info_1]) = (&fragment_0.stack_ids[..], &fragment_1.stack_ids[..])
+            && info_0.type_id != info_1.type_id
+            && let Some(common) = common_integer_type(info_0.type_id, info_1.type_id)
+        {
+            widen_integer(&mut fragment_0, common)?;
+            widen_integer(&mut fragment_1, common)?;
+        }
+        self.join2(fragment_0, fragment_1)
+    }
+
+    /// Joins two conditional fragments like [`join2`](Self::join2), first unifying a
+    /// [`CelNull`] result with the other fragment's `T` into `Option<T>`.
+    ///
+    /// If exactly one fragment produces a `CelNull`, ops are appended to both fragments so they
+    /// produce an `Option<T>`, using the table in [`nullable`](crate::nullable). Otherwise the
+    /// fragments are passed to `join2` unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the non-null fragment's type has no optional form, or under the same
+    /// conditions as [`join2`](Self::join2), after unification.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{CelNull, DynSegment};
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| false);
+    /// let mut then_fragment = segment.new_fragment();
+    /// then_fragment.op0(|| 5i32);
+    /// let mut else_fragment = segment.new_fragment();
+    /// else_fragment.op0(|| CelNull);
+    /// segment.join2_nullable(then_fragment, else_fragment).unwrap();
+    /// assert_eq!(segment.call0::<Option<i32>>().unwrap(), None);
+    /// ```
+    pub fn join2_nullable(
+        &mut self,
+        mut fragment_0: DynSegment,
+        mut fragment_1: DynSegment,
+    ) -> Result<()> {
+        let null = Some(TypeId::of::<CelNull>());
+        match (
+            fragment_0.peek_output_type_id() == null,
+            fragment_1.peek_output_type_id() == null,
+        ) {
+            (false, true) => unify_null(&mut fragment_0, &mut fragment_1)?,
+            (true, false) => unify_null(&mut fragment_1, &mut fragment_0)?,
+            _ => {}
+        }
+        self.join2(fragment_0, fragment_1)
+    }
+
+    /// Executes all operations in the segment and returns the final result.
+    ///
+    /// # Returns
+    /// - `Ok(R)` if execution succeeds and the final value is of type R
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - There are unexpected arguments (expected none)
+    ///   - The final type doesn't match R
+    ///   - There are remaining values on the stack after getting the result
+    ///
+    pub fn call0<R>(&mut self) -> Result<R>
+    where
+        R: 'static,
+    {
+        if !self.argument_ids.is_empty() {
+            return Err(anyhow::anyhow!(
+                "expected no arguments, but segment requires {} argument(s)",
+                self.argument_ids.len()
+            ));
+        }
+        self.pop_types::<(R, ())>()?;
+        if !self.stack_ids.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} value(s) left on execution stack",
+                self.stack_ids.len()
+            ));
+        }
+        let mut stack = self.new_stack();
+        unsafe {
+            self.run_on_stack(&mut stack)?;
+            Ok(stack.pop(false))
+        }
+    }
+
+    /// Returns whether the segment forms a callable program: it takes no more arguments than
+    /// [`call0`](Self::call0), [`call1`](Self::call1) and [`call2`](Self::call2) support, and
+    /// leaves exactly one value on the stack. Unlike those calls, this does not consume the
+    /// segment's type stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<(i32,)>();
+    /// assert!(segment.is_complete());
+    /// segment.op0(|| 1i32);
+    /// assert!(!segment.is_complete());
+    /// segment.op2(|a: i32, b: i32| a + b).unwrap();
+    /// assert!(segment.is_complete());
+    /// ```
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.argument_ids.len() <= Self::MAX_CALL_ARITY && self.stack_ids.len() == 1
+    }
+
+    /// The most arguments a `callN` method can supply.
+    const MAX_CALL_ARITY: usize = 2;
+
+    /// Executes all operations in the segment and returns every value left on the stack, boxed,
+    /// in stack order (bottom first). A tuple value is boxed as a `Vec<Box<dyn Any>>` of its
+    /// boxed elements.
+    ///
+    /// - Postcondition: the type stack is empty, as after [`call0`](Self::call0). If an op
+    ///   fails, the type stack is left as it was, so the segment can still be extended or run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - There are unexpected arguments (expected none)
+    ///   - Any op returns an error during execution
+    ///
+    /// - Complexity: O(n) in the number of ops, plus O(m) in the number of values drained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 1u8);
+    /// segment.op0(|| "two");
+    /// let results = segment.drain_results().unwrap();
+    /// assert_eq!(results[0].downcast_ref::<u8>(), Some(&1));
+    /// assert_eq!(results[1].downcast_ref::<&str>(), Some(&"two"));
+    /// ```
+    pub fn drain_results(&mut self) -> Result<Vec<Box<dyn Any>>> {
+        ensure!(
+            self.argument_ids.is_empty(),
+            "expected no arguments, but segment requires {} argument(s)",
+            self.argument_ids.len()
+        );
+        let infos = std::mem::take(&mut self.stack_ids);
+        let mut stack = self.new_stack();
+        // Safety: the stack is fresh and the segment takes no arguments.
+        if let Err(error) = unsafe { self.run_on_stack(&mut stack) } {
+            self.stack_ids = infos;
+            return Err(error);
+        }
+
+        let mut results = Vec::with_capacity(infos.len());
+        for info in infos.iter().rev() {
+            let base = stack.len() - info.size;
+            // Safety: `info` describes the value on top of the stack; its boxer moves the
+            // value out, so the bytes are truncated below without being dropped.
+            unsafe {
+                results.push(stack.read_at(base, |ptr| (info.raw_boxer)(ptr, &info.associated)));
+                stack.truncate_to(base, info.padding);
+            }
+        }
+        results.reverse();
+        Ok(results)
+    }
+
+    /// Executes all operations in the segment and returns the final result boxed, for callers
+    /// that don't know the result type statically. A tuple result is boxed as a
+    /// `Vec<Box<dyn Any>>` of its boxed elements, as in [`drain_results`](Self::drain_results).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - There are unexpected arguments (expected none)
+    ///   - The segment doesn't leave exactly one value on the stack
+    ///   - Any op returns an error during execution
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<()>();
+    /// segment.op0(|| 6i32);
+    /// segment.op1(|x: i32| x * 7).unwrap();
+    /// let result = segment.call0_any().unwrap();
+    /// assert_eq!(result.downcast_ref::<i32>(), Some(&42));
+    /// ```
+    pub fn call0_any(&mut self) -> Result<Box<dyn Any>> {
+        ensure!(
+            self.stack_ids.len() == 1,
+            "expected 1 value on execution stack, found {}",
+            self.stack_ids.len()
+        );
+        let mut results = self.drain_results()?;
+        Ok(results
+            .pop()
+            .expect("one value on the stack yields one result"))
+    }
+
+    /// Executes all operations in the segment with one argument and returns the final result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - The number of arguments doesn't match (expected one)
+    ///   - The argument type doesn't match the expected type
+    ///   - The final type doesn't match R
+    ///   - There are remaining values on the stack after getting the result
+    ///
+    pub fn call1<A, R>(&mut self, arg: A) -> Result<R>
+    where
+        A: 'static,
+        R: 'static,
+    {
+        if self.argument_ids.len() != 1 {
             return Err(anyhow::anyhow!(
                 "expected 1 argument, but segment requires {} argument(s)",
                 self.argument_ids.len()
             ));
         }
-        if self.argument_ids[0] != TypeId::of::<A>() {
-            let got = self.argument_names.first().map(Cow::as_ref).unwrap_or("?");
+        self.check_argument::<A>(0)?;
+        self.pop_types::<(R, ())>()?;
+        if !self.stack_ids.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} value(s) left on execution stack",
+                self.stack_ids.len()
+            ));
+        }
+        let mut stack = self.new_stack();
+        stack.push(arg);
+        unsafe {
+            self.run_on_stack(&mut stack)?;
+            Ok(stack.pop(false))
+        }
+    }
+
+    /// Executes all operations in the segment with a borrowed string argument, converted to the
+    /// owned `String` the segment takes, and returns the final result.
+    ///
+    /// A segment can't take a `&str` that only lives for one call: its ops are `'static`
+    /// closures that may keep a `&'static str` argument, for example in their result or in
+    /// captured state, past the call. Declaring the argument as `String` instead lets callers
+    /// pass borrowed data through this method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - The number of arguments doesn't match (expected one)
+    ///   - The argument type isn't `String`
+    ///   - The final type doesn't match R
+    ///   - There are remaining values on the stack after getting the result
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<(String,)>();
+    /// segment.op1(|s: String| s.len()).unwrap();
+    /// let text = String::from("borrowed");
+    /// assert_eq!(segment.call1_str::<usize>(&text[..3]).unwrap(), 3);
+    /// ```
+    pub fn call1_str<R>(&mut self, arg: &str) -> Result<R>
+    where
+        R: 'static,
+    {
+        self.call1(arg.to_owned())
+    }
+
+    /// Executes all operations in the segment with two arguments and returns the final result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    ///   - The number of arguments doesn't match (expected two)
+    ///   - An argument type doesn't match the expected type
+    ///   - The final type doesn't match R
+    ///   - There are remaining values on the stack after getting the result
+    ///
+    pub fn call2<A, B, R>(&mut self, args: (A, B)) -> Result<R>
+    where
+        A: 'static,
+        B: 'static,
+        R: 'static,
+    {
+        if self.argument_ids.len() != 2 {
             return Err(anyhow::anyhow!(
-                "argument type mismatch: expected {}, got {}",
-                std::any::type_name::<A>(),
-                got
+                "expected 2 arguments, but segment requires {} argument(s)",
+                self.argument_ids.len()
             ));
         }
+        self.check_argument::<A>(0)?;
+        self.check_argument::<B>(1)?;
         self.pop_types::<(R, ())>()?;
         if !self.stack_ids.is_empty() {
             return Err(anyhow::anyhow!(
@@ -1086,7 +2279,55 @@ impl DynSegment {
                 self.stack_ids.len()
             ));
         }
-        unsafe { self.segment.call1(arg) }
+        let mut stack = self.new_stack();
+        stack.push(args.0);
+        stack.push(args.1);
+        unsafe {
+            self.run_on_stack(&mut stack)?;
+            Ok(stack.pop(false))
+        }
+    }
+
+    /// Returns an empty stack with the segment's base alignment and room for its deepest stack.
+    fn new_stack(&self) -> RawStack {
+        RawStack::with_base_alignment_and_capacity(
+            self.segment.base_alignment(),
+            self.max_stack_offset,
+        )
+    }
+
+    /// Executes all operations in the segment on `stack`, adding the index of a failing op and
+    /// the segment's argument signature as context to its error.
+    ///
+    /// # Safety
+    ///
+    /// Precondition: `stack` holds the segment's arguments, in order, and nothing else.
+    unsafe fn run_on_stack(&self, stack: &mut RawStack) -> Result<()> {
+        unsafe { self.segment.call0_stack_indexed(stack) }.map_err(|(index, error)| {
+            error.context(OpFailure {
+                index,
+                arguments: self.argument_names.join(", "),
+            })
+        })
+    }
+
+    /// Checks that `A` is the type of argument slot `index`, naming the slot in the error if it
+    /// was named with [`new_with_names`](Self::new_with_names).
+    pub(crate) fn check_argument<A: 'static>(&self, index: usize) -> Result<()> {
+        if self.argument_ids[index] == TypeId::of::<A>() {
+            return Ok(());
+        }
+        let expected = self.argument_names[index].as_ref();
+        match self.argument_labels.get(index) {
+            Some(label) => Err(anyhow::anyhow!(
+                "argument '{label}' expected {expected}, got {}",
+                std::any::type_name::<A>()
+            )),
+            None => Err(anyhow::anyhow!(
+                "argument type mismatch: expected {}, got {expected}",
+                std::any::type_name::<A>()
+            )),
+        }
     }
 
     /// Reinterprets the tuple on top of the stack as a concrete `L`
@@ -1121,6 +2362,7 @@ impl DynSegment {
         debug_assert_eq!(info.size, size_of::<L>());
         debug_assert_eq!(info.align, align_of::<L>());
 
+        self.retire_arguments(self.stack_ids.len() - 1);
         let info = self.stack_ids.last_mut().expect("checked above");
         info.type_id = TypeId::of::<L>();
         info.type_name = Cow::Borrowed(std::any::type_name::<L>());
@@ -1138,6 +2380,7 @@ impl DynSegment {
     ///   `L`, assembled via sequential `.push()` calls (not
     ///   `into_c_stack_list()` on a same-order plain tuple).
     pub fn push_tuple<L: List + ToTypeIdList + 'static>(&mut self) {
+        self.retire_arguments(self.stack_ids.len().saturating_sub(1));
         let info = self
             .stack_ids
             .last_mut()
@@ -1162,6 +2405,7 @@ impl DynSegment {
                     size: elem_info.size,
                     align: elem_info.align,
                     dropper: elem_info.raw_dropper,
+                    boxer: elem_info.raw_boxer,
                     associated: elem_info.associated.clone(),
                 };
                 offset += elem_info.size;
@@ -1172,6 +2416,7 @@ impl DynSegment {
         info.type_id = TypeId::of::<DynTuple>();
         info.type_name = Cow::Borrowed(std::any::type_name::<DynTuple>());
         info.raw_dropper = drop_tuple;
+        info.raw_boxer = box_tuple;
         info.associated = associated;
     }
 }
@@ -1197,6 +2442,672 @@ mod tests {
         }
     }
 
+    #[test]
+    fn arg_ref_reads_argument_twice() -> Result<()> {
+        // a * b + a
+        let mut segment = DynSegment::new::<(i32, i32)>();
+        assert!(segment.arg_ref::<i64>(0).is_err());
+        assert!(segment.arg_ref::<i32>(2).is_err());
+        segment.arg_ref::<i32>(0)?;
+        segment.op2(|b: i32, a: i32| a * b)?;
+        segment.op2(|a: i32, ab: i32| ab + a)?;
+        assert_eq!(
+            segment.arg_ref::<i32>(0).unwrap_err().to_string(),
+            "argument 0 has already been consumed"
+        );
+        assert_eq!(segment.call2::<i32, i32, i32>((6, 7))?, 48);
+
+        let mut segment = DynSegment::new::<(String,)>();
+        segment.arg_ref::<String>(0)?;
+        segment.op2(|a: String, b: String| a + &b)?;
+        assert_eq!(segment.call1::<String, String>("ab".into())?, "abab");
+        Ok(())
+    }
+
+    #[test]
+    fn named_arguments_in_mismatch_errors() -> Result<()> {
+        let mut segment = DynSegment::new_with_names::<(u32, u32)>(&["count", "step"])?;
+        segment.op2(|count: u32, step: u32| count * step)?;
+        assert_eq!(
+            segment
+                .call2::<u32, i32, u32>((3, 4))
+                .unwrap_err()
+                .to_string(),
+            "argument 'step' expected u32, got i32"
+        );
+
+        let mut segment = DynSegment::new_with_names::<(u32, u32)>(&["count", "step"])?;
+        segment.op2(|count: u32, step: u32| count * step)?;
+        assert_eq!(segment.call2::<u32, u32, u32>((3, 4))?, 12);
+
+        assert_eq!(
+            DynSegment::new_with_names::<(u32, u32)>(&["count"])
+                .err()
+                .map(|e| e.to_string()),
+            Some("expected 2 argument name(s), got 1".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_keeps_both_results() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 3i32);
+        let mut other = segment.new_sequence_fragment();
+        other.op0(|| 4i32);
+        segment.sequence(other)?;
+        segment.op2(|a: i32, b: i32| a + b)?;
+        assert_eq!(segment.call0::<i32>()?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_rejects_stale_fragment() {
+        let mut segment = DynSegment::new::<()>();
+        let mut other = segment.new_sequence_fragment();
+        other.op0(|| 4u64);
+        segment.op0(|| 3u8);
+        assert_eq!(
+            segment.sequence(other).unwrap_err().to_string(),
+            "sequenced segment was not created at the current stack top"
+        );
+    }
+
+    #[test]
+    fn push_shared_moves_each_result_to_its_own_slot() -> Result<()> {
+        let mut shared = DynSegment::new::<()>();
+        shared.op0(|| String::from("a"));
+        shared.op0(|| 2u64);
+        shared.make_tuple(2, 0);
+        let shared = SharedSegment::new(shared)?;
+
+        // The u8 beneath misaligns the first result, so each copy needs its own padding.
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 7u8);
+        segment.push_shared(&shared);
+        segment.tuple_index(1);
+        segment.op2(|a: u8, b: u64| format!("{a}{b}"))?;
+        segment.push_shared(&shared);
+        segment.tuple_index(0);
+        segment.op2(|a: String, b: String| a + &b)?;
+        assert_eq!(segment.call0::<String>()?, "72a");
+        Ok(())
+    }
+
+    #[test]
+    fn push_shared_counts_shared_ops_against_the_step_budget() -> Result<()> {
+        let mut shared = DynSegment::new::<()>();
+        shared.op0(|| 20i32);
+        shared.op1(|x: i32| x + 1)?;
+        let shared = SharedSegment::new(shared)?;
+
+        let mut segment = DynSegment::new::<()>();
+        segment.push_shared(&shared);
+        // One step for the op running the shared segment and one for each of its two ops.
+        assert_eq!(unsafe { segment.segment.call0_limited::<i32>(3) }?, 21);
+        let error = unsafe { segment.segment.call0_limited::<i32>(2) }.unwrap_err();
+        assert_eq!(error.to_string(), "step budget exceeded");
+        Ok(())
+    }
+
+    #[test]
+    fn push_shared_drops_values_beneath_on_error() {
+        let mut shared = DynSegment::new::<()>();
+        shared.op0(|| 1u32);
+        shared
+            .op1r(|_: u32| -> Result<u32> { anyhow::bail!("failed") })
+            .unwrap();
+        let shared = SharedSegment::new(shared).unwrap();
+
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| String::from("beneath"));
+        segment.push_shared(&shared);
+        segment.op2(|_: String, b: u32| b).unwrap();
+        assert_eq!(
+            segment.call0::<u32>().unwrap_err().root_cause().to_string(),
+            "failed"
+        );
+    }
+
+    #[test]
+    fn shared_segment_requires_one_result() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1u8);
+        segment.op0(|| 2u8);
+        assert_eq!(
+            SharedSegment::new(segment).err().unwrap().to_string(),
+            "shared segment must have exactly 1 result, but has 2"
+        );
+    }
+
+    #[test]
+    fn stack_offsets_count_each_argument_once() -> Result<()> {
+        let segment = DynSegment::new::<(u32, u64)>();
+        assert_eq!(segment.current_stack_offset(), 16);
+        assert_eq!(segment.max_stack_offset(), 16);
+
+        // The element copied into the predicate sits right above the arguments and the list.
+        let mut segment = DynSegment::new::<(u32,)>();
+        segment.op0(|| vec![1u32, 2, 3]);
+        let (mut predicate, element) = segment.new_element_fragment::<u32>();
+        unsafe { predicate.push_element(element) };
+        predicate.op1(|x: u32| x > 2)?;
+        segment.quantify::<u32>(predicate, Quantifier::Exists)?;
+        segment.op2(|limit: u32, found: bool| found && limit > 0)?;
+        assert!(segment.call1::<u32, bool>(1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn quantify_folds_predicate_and_short_circuits() -> Result<()> {
+        let build = |list: Vec<String>, quantifier| -> Result<DynSegment> {
+            let mut segment = DynSegment::new::<()>();
+            segment.op0(|| 1u8);
+            segment.op0(move || list.clone());
+            let (mut predicate, element) = segment.new_element_fragment::<String>();
+            unsafe { predicate.push_element(element) };
+            predicate.op1r(|s: String| {
+                s.parse::<i32>()
+                    .map(|n| n > 0)
+                    .map_err(|_| anyhow::anyhow!("not a number: {s}"))
+            })?;
+            segment.quantify::<String>(predicate, quantifier)?;
+            segment.op2(|_: u8, b: bool| b)?;
+            Ok(segment)
+        };
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(build(strings(&["1", "2"]), Quantifier::All)?.call0::<bool>()?);
+        assert!(!build(strings(&["1", "-2", "x"]), Quantifier::All)?.call0::<bool>()?);
+        assert!(build(strings(&["-1", "2", "x"]), Quantifier::Exists)?.call0::<bool>()?);
+        assert!(!build(strings(&["-1"]), Quantifier::Exists)?.call0::<bool>()?);
+        assert!(build(Vec::new(), Quantifier::All)?.call0::<bool>()?);
+        assert!(!build(Vec::new(), Quantifier::Exists)?.call0::<bool>()?);
+
+        let err = build(strings(&["1", "x"]), Quantifier::All)?
+            .call0::<bool>()
+            .unwrap_err();
+        assert_eq!(err.root_cause().to_string(), "not a number: x");
+        Ok(())
+    }
+
+    #[test]
+    fn quantify_element_is_readable_after_arguments_and_in_branches() -> Result<()> {
+        // exists(x, if x < 9 { false } else { x == 9 }), in a segment taking an argument
+        let build = |list: Vec<i16>| -> Result<DynSegment> {
+            let mut segment = DynSegment::new::<(i64,)>();
+            segment.op0(move || list.clone());
+            let (mut predicate, element) = segment.new_element_fragment::<i16>();
+            unsafe { predicate.push_element(element) };
+            predicate.op1(|x: i16| x < 9)?;
+            let mut then_fragment = predicate.new_fragment();
+            then_fragment.op0(|| false);
+            let mut else_fragment = predicate.new_fragment();
+            unsafe { else_fragment.push_element(element) };
+            else_fragment.op1(|x: i16| x == 9)?;
+            predicate.join2(then_fragment, else_fragment)?;
+            segment.quantify::<i16>(predicate, Quantifier::Exists)?;
+            segment.op2(|_: i64, b: bool| b)?;
+            Ok(segment)
+        };
+        assert!(build(vec![5, 0, 9])?.call1::<i64, bool>(1)?);
+        assert!(!build(vec![5, 0, 10])?.call1::<i64, bool>(1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn quantify_rejects_predicate_without_bool_result() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| vec![1i32]);
+        let (mut predicate, element) = segment.new_element_fragment::<i32>();
+        unsafe { predicate.push_element(element) };
+        assert_eq!(
+            segment
+                .quantify::<i32>(predicate, Quantifier::All)
+                .unwrap_err()
+                .to_string(),
+            "predicate must produce a `bool`, but produces `i32`"
+        );
+    }
+
+    #[test]
+    fn stack_info_debug_shows_type_name_and_padding() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1u8);
+        segment.op0(|| 2u32);
+        let formatted = format!("{:?}", segment.peek_stack_infos(1)[0]);
+        assert_eq!(
+            formatted,
+            r#"StackInfo { type_name: "u32", padding: true, size: 4, align: 4 }"#
+        );
+    }
+
+    #[test]
+    fn stack_info_debug_lists_tuple_element_names() {
+        let mut segment = DynSegment::new::<()>();
+        let ambient_start = segment.current_stack_offset();
+        segment.op0(|| 1u8);
+        segment.op0(|| 2i64);
+        segment.make_tuple(2, ambient_start);
+        let formatted = format!("{:?}", segment.peek_stack_infos(1)[0]);
+        assert!(
+            formatted.contains(r#"associated: ["u8", "i64"]"#),
+            "{formatted}"
+        );
+        assert!(formatted.contains("padding: false"), "{formatted}");
+    }
+
+    #[test]
+    fn is_complete_requires_exactly_one_result() {
+        let mut segment = DynSegment::new::<()>();
+        assert!(!segment.is_complete());
+        segment.op0(|| 1u8);
+        assert!(segment.is_complete());
+        segment.op0(|| 2u8);
+        assert!(!segment.is_complete());
+        segment.op2(|a: u8, b: u8| a + b).unwrap();
+        assert!(segment.is_complete());
+        assert_eq!(segment.call0::<u8>().unwrap(), 3);
+    }
+
+    #[test]
+    fn is_complete_accepts_two_arguments() -> Result<()> {
+        let mut segment = DynSegment::new::<(u8, u8)>();
+        assert!(!segment.is_complete());
+        segment.op2(|a: u8, b: u8| a + b)?;
+        assert!(segment.is_complete());
+        assert_eq!(segment.call2::<u8, u8, u8>((1, 2))?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn is_complete_rejects_more_arguments_than_any_call_supports() {
+        let mut segment = DynSegment::new::<(u8, u8, u8)>();
+        segment.op2(|a: u8, b: u8| a + b).unwrap();
+        segment.op2(|a: u8, b: u8| a + b).unwrap();
+        assert!(!segment.is_complete());
+    }
+
+    #[test]
+    fn call0_any_boxes_single_result() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 40i32);
+        segment.op0(|| 2i32);
+        assert!(segment.call0_any().is_err());
+        segment.op2(|a: i32, b: i32| a + b)?;
+        let result = segment.call0_any()?;
+        assert_eq!(result.downcast::<i32>().ok().map(|r| *r), Some(42));
+        Ok(())
+    }
+
+    #[test]
+    fn drain_results_returns_every_stack_value_in_order() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 7u8);
+        segment.op0(|| String::from("left"));
+        segment.op0(|| 3.5f64);
+        let results = segment.drain_results()?;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].downcast_ref::<u8>(), Some(&7));
+        assert_eq!(
+            results[1].downcast_ref::<String>().map(String::as_str),
+            Some("left")
+        );
+        assert_eq!(results[2].downcast_ref::<f64>(), Some(&3.5));
+        assert!(segment.peek_output_type_id().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn drain_results_boxes_tuples_as_element_vectors() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| true);
+        let ambient_start = segment.current_stack_offset();
+        segment.op0(|| 1u8);
+        segment.op0(|| 2u32);
+        segment.make_tuple(2, ambient_start);
+        let results = segment.drain_results()?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].downcast_ref::<bool>(), Some(&true));
+        let elements = results[1]
+            .downcast_ref::<Vec<Box<dyn Any>>>()
+            .expect("tuple drains as a vector of elements");
+        assert_eq!(elements[0].downcast_ref::<u8>(), Some(&1));
+        assert_eq!(elements[1].downcast_ref::<u32>(), Some(&2));
+        Ok(())
+    }
+
+    #[test]
+    fn coalesce_keeps_selected_value_or_runs_fallback() -> Result<()> {
+        for (value, expected) in [(Some(3i32), 3), (None, 5)] {
+            let mut segment = DynSegment::new::<()>();
+            segment.op0(|| String::from("beneath"));
+            segment.op0(move || value);
+            let mut fallback = segment.new_fragment();
+            fallback.op0(|| 5i32);
+            segment.coalesce(fallback, |o: Option<i32>| o)?;
+            segment.op2(|s: String, n: i32| format!("{s} {n}"))?;
+            assert_eq!(segment.call0::<String>()?, format!("beneath {expected}"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn coalesce_rejects_fallback_of_another_type() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 0i32);
+        let mut fallback = segment.new_fragment();
+        fallback.op0(|| 5i64);
+        let err = segment
+            .coalesce(fallback, |n: i32| (n != 0).then_some(n))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "fallback must produce `i32`, but produces `i64`"
+        );
+    }
+
+    #[test]
+    fn drain_results_keeps_type_stack_on_op_error() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1u8);
+        segment.op0(|| 2i32);
+        segment.op1r(|_: i32| -> Result<i32> { Err(anyhow::anyhow!("boom")) })?;
+        assert!(segment.drain_results().is_err());
+        assert_eq!(segment.peek_output_type_id(), Some(TypeId::of::<i32>()));
+        segment.op2(|a: u8, b: i32| i32::from(a) + b)?;
+        assert!(segment.is_complete());
+        Ok(())
+    }
+
+    #[test]
+    fn drain_results_moves_values_without_dropping_them() -> Result<()> {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let counter = drops.clone();
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(move || DropCounter(counter.clone()));
+        segment.op0(|| 1i32);
+        let results = segment.drain_results()?;
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop(results);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn join2_coerced_widens_narrower_branch() -> Result<()> {
+        for (condition, expected) in [(true, 40i64), (false, 5_000_000_000i64)] {
+            let mut segment = DynSegment::new::<()>();
+            segment.op0(move || condition);
+            let mut then_fragment = segment.new_fragment();
+            then_fragment.op0(|| 40i32);
+            let mut else_fragment = segment.new_fragment();
+            else_fragment.op0(|| 5_000_000_000i64);
+            segment.join2_coerced(then_fragment, else_fragment)?;
+            assert_eq!(segment.call0::<i64>()?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn join2_coerced_widens_both_branches_to_common_type() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| false);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(|| 1u32);
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| -1i32);
+        segment.join2_coerced(then_fragment, else_fragment)?;
+        assert_eq!(segment.call0::<i64>()?, -1);
+        Ok(())
+    }
+
+    #[test]
+    fn join2_coerced_rejects_non_integer_mismatch() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| true);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(|| 1i32);
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| 1.0f64);
+        let err = segment
+            .join2_coerced(then_fragment, else_fragment)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "fragment result types must match");
+    }
+
+    #[test]
+    fn with_ops_rolls_back_failed_build() -> Result<()> {
+        let drop_count = Arc::new(AtomicUsize::new(0));
+        let tracker = DropCounter(drop_count.clone());
+        let mut segment = DynSegment::new::<(u32,)>();
+        segment.op0(|| 10u32);
+        let ops = segment.op_count();
+        let err = segment
+            .with_ops(|segment| {
+                segment.op2(|a: u32, b: u32| a + b)?;
+                segment.op0(move || tracker.clone());
+                segment.op1(|x: u32| u64::from(x))
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "stack type ids do not match");
+        assert_eq!(drop_count.load(Ordering::SeqCst), 1);
+        assert_eq!(segment.op_count(), ops);
+        segment.with_ops(|segment| segment.op2(|a: u32, b: u32| a * b))?;
+        assert_eq!(segment.call1::<u32, u32>(4)?, 40);
+        Ok(())
+    }
+
+    #[test]
+    fn call1_str_accepts_non_static_str() -> Result<()> {
+        let mut segment = DynSegment::new::<(String,)>();
+        segment.op1(|s: String| s.to_uppercase())?;
+        let owner = format!("{}-{}", "local", 42);
+        let borrowed: &str = &owner[..5];
+        assert_eq!(segment.call1_str::<String>(borrowed)?, "LOCAL");
+
+        let mut segment = DynSegment::new::<(&str,)>();
+        segment.op1(|s: &str| s.len())?;
+        assert!(segment.call1_str::<usize>(borrowed).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn can_apply_checks_top_types_without_consuming() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        assert!(!segment.can_apply::<(u32, (u32, ()))>());
+        segment.op0(|| 1u32);
+        assert!(!segment.can_apply::<(u32, (u32, ()))>());
+        assert!(segment.can_apply::<(u32, ())>());
+        segment.op0(|| 2u32);
+        assert!(segment.can_apply::<(u32, (u32, ()))>());
+        assert!(!segment.can_apply::<(u32, (u64, ()))>());
+        assert!(segment.can_apply::<()>());
+
+        segment.op2(|a: u32, b: u32| a + b)?;
+        assert_eq!(segment.call0::<u32>()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn call_error_names_failing_op_and_arguments() -> Result<()> {
+        let mut segment = DynSegment::new::<(u32,)>();
+        segment.op1(|x: u32| x + 1)?;
+        segment.op1r(|x: u32| -> Result<u32> {
+            ensure!(x < 5, "too large");
+            Ok(x)
+        })?;
+
+        let failing_op = segment.op_count() - 1;
+        let err = segment.call1::<u32, u32>(7).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("op {failing_op} failed in segment with arguments (u32)")
+        );
+        assert_eq!(err.root_cause().to_string(), "too large");
+        assert_eq!(
+            err.downcast_ref::<OpFailure>().map(OpFailure::index),
+            Some(failing_op)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn zero_sized_values_are_tracked_on_type_stack() -> Result<()> {
+        struct Marker;
+
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1u8);
+        segment.op0(|| Marker);
+        segment.op0(|| [0u64; 0]);
+        segment.op0(|| 2u32);
+        assert_eq!(segment.stack_ids.len(), 4);
+        assert_eq!(segment.stack_ids[1].size, 0);
+        segment.op2(|_: [u64; 0], x: u32| x)?;
+        segment.op2(|_: Marker, x: u32| x)?;
+        segment.op2(|a: u8, b: u32| u32::from(a) + b)?;
+        assert_eq!(segment.call0::<u32>()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn is_fallible_tracks_fallible_ops() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1i32);
+        segment.op0(|| false);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(|| 2i32);
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| 3i32);
+        segment.join2(then_fragment, else_fragment)?;
+        segment.op2(|a: i32, b: i32| a + b)?;
+        assert!(!segment.is_fallible());
+
+        let mark = segment.mark();
+        segment.op0r(|| Ok(4i32));
+        assert!(segment.is_fallible());
+        segment.rewind(mark);
+        assert!(!segment.is_fallible());
+
+        segment.op0(|| true);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0r(|| Ok(5i32));
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| 6i32);
+        segment.join2(then_fragment, else_fragment)?;
+        assert!(segment.is_fallible());
+        segment.op2(|a: i32, b: i32| a + b)?;
+        assert_eq!(segment.call0::<i32>()?, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn dump_ops_follows_rewind_and_failed_with_ops() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 2u8);
+        let mark = segment.mark();
+        segment.op1(|n: u8| u32::from(n))?;
+        segment.rewind(mark);
+        assert_eq!(segment.dump_ops().len(), 1);
+        let result = segment.with_ops(|segment| {
+            segment.op1(|n: u8| n.to_string())?;
+            segment.op1(|n: u8| n) // the top is now a `String`: fails
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            segment.dump_ops(),
+            [OpInfo {
+                arity: 0,
+                result_type: "u8".into()
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn join2_merges_fragment_labels() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.push_label("condition");
+        segment.op0(|| true);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.push_label("then");
+        then_fragment.op0(|| 1i32);
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.push_label("else");
+        else_fragment.op0(|| 2i32);
+        segment.join2(then_fragment, else_fragment)?;
+        segment.push_label("negate");
+        segment.op1(|n: i32| -n)?;
+        assert_eq!(segment.op_labels(), ["condition", "then", "else", "negate"]);
+        assert_eq!(segment.segment.op_records().len(), segment.op_count());
+        assert_eq!(segment.call0::<i32>()?, -1);
+        Ok(())
+    }
+
+    #[test]
+    fn rewind_restores_consumed_values_and_arguments() -> Result<()> {
+        let mut segment = DynSegment::new::<(i32,)>();
+        segment.op0(|| 2i32);
+        let mark = segment.mark();
+        segment.op2(|a: i32, b: i32| a * b)?;
+        assert!(segment.arg_ref::<i32>(0).is_err());
+        segment.rewind(mark);
+        segment.arg_ref::<i32>(0)?;
+        segment.op2(|a: i32, b: i32| a + b)?;
+        segment.op2(|a: i32, b: i32| a * b)?;
+        assert_eq!(segment.call1::<i32, i32>(5)?, 35);
+        Ok(())
+    }
+
+    #[test]
+    fn rewind_drops_removed_ops() -> Result<()> {
+        let drop_count = Arc::new(AtomicUsize::new(0));
+        let tracker = DropCounter(drop_count.clone());
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 2.5f64);
+        let mark = segment.mark();
+        segment.op0(move || tracker.clone());
+        segment.op0(|| 1u8);
+        segment.rewind(mark);
+        assert_eq!(drop_count.load(Ordering::SeqCst), 1);
+        assert_eq!(segment.op_count(), 1);
+        segment.op0(|| 4.0f64);
+        segment.op2(|a: f64, b: f64| a * b)?;
+        assert_eq!(segment.call0::<f64>()?, 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn dump_ops_records_arity_and_result_type() -> Result<()> {
+        let mut segment = DynSegment::new::<(u32,)>();
+        segment.op0(|| 2u32);
+        segment.op2(|a: u32, b: u32| f64::from(a * b))?;
+        segment.op0r(|| Ok(true));
+        segment
+            .op3(|a: f64, b: bool, c: u8| if b { a } else { f64::from(c) })
+            .unwrap_err();
+        segment.op1r(|flag: bool| Ok(u8::from(flag)))?;
+        segment.op3(|a: f64, _: u8, _: bool| a as i64).unwrap_err();
+        let mark = segment.mark();
+        segment.just(String::new());
+        segment.rewind(mark);
+        let ops: Vec<(usize, String)> = segment
+            .dump_ops()
+            .into_iter()
+            .map(|op| (op.arity, op.result_type.into_owned()))
+            .collect();
+        assert_eq!(
+            ops,
+            [
+                (0, "u32".to_string()),
+                (2, "f64".to_string()),
+                (0, "bool".to_string()),
+                (1, "u8".to_string()),
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn drop_on_error() -> Result<(), anyhow::Error> {
         let mut segment = DynSegment::new::<()>();
@@ -1210,12 +3121,38 @@ mod tests {
 
         assert_eq!(drop_count.load(Ordering::SeqCst), 0); // Nothing dropped yet
         let result = segment.call0::<u32>();
-        assert!(matches!(result, Err(e) if e.to_string() == "error"));
+        assert!(matches!(result, Err(e) if e.root_cause().to_string() == "error"));
         assert_eq!(drop_count.load(Ordering::SeqCst), 1); // The DropCounter from op0 was dropped
 
         Ok(())
     }
 
+    #[test]
+    fn join2_unwinds_stack_when_branch_errors() -> Result<(), anyhow::Error> {
+        let outer_drops = Arc::new(AtomicUsize::new(0));
+        let outer = DropCounter(outer_drops.clone());
+        let branch_drops = Arc::new(AtomicUsize::new(0));
+        let branch = DropCounter(branch_drops.clone());
+
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(move || outer.clone());
+        segment.op0(|| true);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(move || branch.clone());
+        then_fragment.op0r(|| -> Result<u32> { Err(anyhow::anyhow!("error")) });
+        then_fragment.op2(|_: DropCounter, n: u32| n)?;
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| 0u32);
+        segment.join2(then_fragment, else_fragment)?;
+        segment.op2(|_: DropCounter, n: u32| n)?;
+
+        let result = segment.call0::<u32>();
+        assert!(matches!(result, Err(e) if e.root_cause().to_string() == "error"));
+        assert_eq!(branch_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(outer_drops.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
     #[test]
     fn op1r_success() -> Result<(), anyhow::Error> {
         let mut segment = DynSegment::new::<()>();
@@ -1238,7 +3175,7 @@ mod tests {
         segment.op2(|_: DropCounter, x: u32| x)?; // consume to single u32 for call0
         let result = segment.call0::<u32>();
         assert!(result.is_err(), "expected Err, got {:?}", result);
-        assert_eq!(result.unwrap_err().to_string(), "op1r error");
+        assert_eq!(result.unwrap_err().root_cause().to_string(), "op1r error");
         // DropCounter (under the u32) was unwound when op1r failed.
         assert_eq!(drop_count.load(Ordering::SeqCst), 1);
         Ok(())
@@ -1270,7 +3207,7 @@ mod tests {
         segment.op2(|_: DropCounter, x: u32| x)?; // consume to single u32 for call0
         let result = segment.call0::<u32>();
         assert!(result.is_err(), "expected Err, got {:?}", result);
-        assert_eq!(result.unwrap_err().to_string(), "op2r error");
+        assert_eq!(result.unwrap_err().root_cause().to_string(), "op2r error");
         // DropCounter (under the two u32s) was unwound when op2r failed.
         assert_eq!(drop_count.load(Ordering::SeqCst), 1);
         Ok(())
@@ -1343,6 +3280,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn max_stack_offset_tracks_high_water_mark() -> Result<(), anyhow::Error> {
+        let mut segment = DynSegment::new::<()>();
+        assert_eq!(segment.max_stack_offset(), 0);
+        segment.op0(|| 1u8);
+        segment.op0(|| 2u32);
+        segment.op0(|| 3u64);
+        assert_eq!(segment.max_stack_offset(), 16);
+        segment.op3(|a: u8, b: u32, c: u64| u64::from(a) + u64::from(b) + c)?;
+        assert_eq!(segment.current_stack_offset(), 8);
+        assert_eq!(segment.max_stack_offset(), 16);
+        assert_eq!(segment.call0::<u64>()?, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn max_stack_offset_includes_joined_fragments() -> Result<(), anyhow::Error> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| true);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(|| 1u64);
+        then_fragment.op0(|| 2u64);
+        then_fragment.op2(|a: u64, b: u64| a + b)?;
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| 0u64);
+        segment.join2(then_fragment, else_fragment)?;
+        assert_eq!(segment.max_stack_offset(), 16);
+        assert_eq!(segment.call0::<u64>()?, 3);
+        Ok(())
+    }
+
     #[test]
     fn push_arg_single_input() -> Result<(), anyhow::Error> {
         let mut seg = DynSegment::new::<()>();
@@ -1431,7 +3399,7 @@ mod tests {
         let x: i32 = 5;
         let result = seg.call_dyn::<i32>(&[&x as &dyn Any]);
         assert!(result.is_err(), "expected Err when op fails");
-        let msg = result.unwrap_err().to_string();
+        let msg = format!("{:#}", result.unwrap_err());
         assert!(
             msg.contains("op failed"),
             "error message should propagate op error: {msg}"
@@ -1553,7 +3521,7 @@ mod tests {
         ];
         let result = unsafe { seg.call_dyn_tuple(&[], &extractors) };
         assert!(result.is_err(), "expected Err when op fails");
-        let msg = result.unwrap_err().to_string();
+        let msg = format!("{:#}", result.unwrap_err());
         assert!(
             msg.contains("op failed"),
             "error message should propagate op error: {msg}"
@@ -1581,6 +3549,7 @@ mod tests {
             size: 4,
             align: 4,
             dropper: |ptr, _associated| unsafe { std::ptr::drop_in_place(ptr.cast::<u32>()) },
+            boxer: |ptr, _associated| unsafe { Box::new(std::ptr::read(ptr.cast::<u32>())) },
             associated: Vec::new(),
         };
         assert_eq!(a.offset, 4);
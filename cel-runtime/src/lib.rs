@@ -32,6 +32,9 @@
 //! assert_eq!(segment.call((1u32, "2")).unwrap(), "3");
 //! ```
 
+/// Serializable bytecode programs, a plain-data alternative to closure-based segments.
+#[cfg(feature = "bytecode")]
+pub mod bytecode;
 /// Compile-time stack list implementation for type-safe stack operations.
 pub mod c_stack_list;
 /// Dynamic segment implementation with runtime type checking.
@@ -40,6 +43,14 @@ pub mod dyn_segment;
 pub mod list_traits;
 /// Memory management and alignment utilities for the runtime.
 pub mod memory;
+/// CEL's `null` value and the optional types it unifies into.
+pub mod nullable;
+/// Lossless integer promotions for reconciling mismatched integer types.
+pub mod numeric_promotion;
+/// Reusable op factories, such as wrapping arithmetic.
+pub mod ops;
+/// Compiled, repeatedly callable form of a dynamic segment.
+pub mod program;
 /// Raw segment implementation without type safety.
 pub mod raw_segment;
 /// Raw sequence implementation for operation sequences.
@@ -48,8 +59,12 @@ pub mod raw_sequence;
 pub mod raw_stack;
 /// Raw vector implementation for dynamic memory allocation.
 pub mod raw_vec;
+/// Reverse-Polish frontend compiling token lists into dynamic segments.
+pub mod rpn;
 /// Type-safe segment implementation with compile-time validation.
 pub mod segment;
+/// Truthiness of the values the `?:` operator tests.
+pub mod truthiness;
 /// Tuple list implementation for type-safe tuple operations.
 pub mod tuple_list;
 
@@ -57,9 +72,14 @@ pub use c_stack_list::*;
 pub use dyn_segment::*;
 pub use list_traits::*;
 pub use memory::*;
+pub use nullable::*;
+pub use numeric_promotion::*;
+pub use program::*;
 pub use raw_segment::*;
 pub use raw_sequence::*;
 pub use raw_stack::*;
 pub use raw_vec::*;
+pub use rpn::*;
 pub use segment::*;
+pub use truthiness::*;
 //pub use tuple_list::*;
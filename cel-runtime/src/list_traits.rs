@@ -1,6 +1,10 @@
 //! A collection of traits for homegenous lists (cons cells), similar to tuples.
 
-use std::any::TypeId;
+use std::alloc::Layout;
+use std::any::{Any, TypeId};
+use std::ops::{RangeFrom, RangeTo, Sub};
+
+use typenum::{B1, Bit, Sub1, U0, UInt, Unsigned};
 
 /// A trait representing a homogeneous list (cons cell) with a head and tail.
 ///
@@ -35,6 +39,49 @@ pub trait List {
         Self::LENGTH
     }
 
+    /// Returns the element at runtime index `i` (0 is the head) as `&dyn Any`, or `None` if `i`
+    /// is past the end of the list.
+    ///
+    /// - Complexity: O(i).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cel_runtime::*;
+    ///
+    /// let list = (1, "x").into_c_stack_list();
+    /// assert_eq!(list.get_dyn(1).and_then(|v| v.downcast_ref::<&str>()), Some(&"x"));
+    /// assert!(list.get_dyn(2).is_none());
+    /// ```
+    fn get_dyn(&self, i: usize) -> Option<&dyn Any> {
+        if self.is_empty() {
+            None
+        } else if i == 0 {
+            Some(self.head())
+        } else {
+            self.tail().get_dyn(i - 1)
+        }
+    }
+
+    /// Returns a borrowed view of the list that indexes elements back to front, without moving
+    /// or copying them.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use cel_runtime::*;
+    ///
+    /// let list = (1, "x").into_c_stack_list();
+    /// assert_eq!(list.rev_view().get::<&str>(0), Some(&"x"));
+    /// assert_eq!(list.rev_view().get::<i32>(1), Some(&1));
+    /// ```
+    fn rev_view(&self) -> RevView<'_, Self>
+    where
+        Self: Sized,
+    {
+        RevView(self)
+    }
+
     /// The type of the list after pushing a new value.
     type Push<U: 'static>: List;
     /// Pushes a new value onto the front of the list, returning a new list.
@@ -76,6 +123,42 @@ pub trait List {
 /// Convenience alias for reversing a `List` onto its empty counterpart.
 pub type ReverseList<T> = <T as List>::ReverseOnto<<T as List>::Empty>;
 
+/// A borrowed view of a [`List`] indexing its elements back to front: index 0 is the last
+/// element. Returned by [`List::rev_view`].
+pub struct RevView<'a, L>(&'a L);
+
+impl<'a, L: List> RevView<'a, L> {
+    /// Returns the length of the list.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        L::LENGTH
+    }
+
+    /// Returns true if the list is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        L::LENGTH == 0
+    }
+
+    /// Returns the element `i` places from the end of the list (0 is the last element) as
+    /// `&dyn Any`, or `None` if `i` is past the front of the list.
+    ///
+    /// - Complexity: O(n) in the length of the list.
+    #[must_use]
+    pub fn get_dyn(&self, i: usize) -> Option<&'a dyn Any> {
+        self.0.get_dyn(L::LENGTH.checked_sub(i.checked_add(1)?)?)
+    }
+
+    /// Returns the element `i` places from the end of the list, or `None` if `i` is past the
+    /// front of the list or the element is not a `T`.
+    ///
+    /// - Complexity: O(n) in the length of the list.
+    #[must_use]
+    pub fn get<T: 'static>(&self, i: usize) -> Option<&'a T> {
+        self.get_dyn(i)?.downcast_ref()
+    }
+}
+
 // Iterate a list (not recurse) to implement equal against an iterator.
 /// Property generator for iterating over type-level lists.
 pub trait ListTypeProperty {
@@ -92,6 +175,13 @@ impl ListTypeProperty for TypeId {
     }
 }
 
+impl ListTypeProperty for Layout {
+    type Output = Self;
+    fn property<R: List>() -> Self::Output {
+        Layout::new::<R::Head>()
+    }
+}
+
 /// Iterator over type-level `List` values producing properties via `P`.
 pub struct ListTypeIterator<T: List, P: ListTypeProperty> {
     pub(crate) advance: fn(&mut Self) -> Option<P::Output>,
@@ -133,6 +223,9 @@ impl<T: ListTypeIteratorAdvance<P> + 'static, P: ListTypeProperty> Iterator
 /// Iterator that yields the [`TypeId`] of each head in the `List`.
 pub type TypeIdIterator<T> = ListTypeIterator<T, TypeId>;
 
+/// Iterator that yields the [`Layout`] of each head in the `List`.
+pub type LayoutIterator<T> = ListTypeIterator<T, Layout>;
+
 /// Marker type representing the head of an empty list.
 pub struct Undefined;
 
@@ -192,10 +285,81 @@ pub trait ListIndex<Idx: ?Sized> {
     fn index(&self, index: Idx) -> &Self::Output;
 }
 
+/// Splits a list at typenum index `N` into the sublists before and from `N`, built from
+/// [`ListIndex`] with [`RangeTo`] and [`RangeFrom`].
+///
+/// # Examples
+///
+/// ```rust
+/// use cel_runtime::*;
+/// use typenum::U1;
+///
+/// let list = (1, 2.5, "x").into_c_stack_list();
+/// let (prefix, suffix) = list.split_at(U1::new());
+/// assert_eq!(*prefix, (1,).into_c_stack_list());
+/// assert_eq!(*suffix, (2.5, "x").into_c_stack_list());
+/// ```
+pub trait ListSplitAt<N> {
+    /// The elements before index `N`.
+    type Prefix;
+    /// The elements from index `N` on.
+    type Suffix;
+    /// Returns references to the elements before `n` and from `n` on.
+    fn split_at(&self, n: N) -> (&Self::Prefix, &Self::Suffix);
+}
+
+impl<L, N: Copy> ListSplitAt<N> for L
+where
+    L: ListIndex<RangeTo<N>> + ListIndex<RangeFrom<N>>,
+{
+    type Prefix = <L as ListIndex<RangeTo<N>>>::Output;
+    type Suffix = <L as ListIndex<RangeFrom<N>>>::Output;
+    fn split_at(&self, n: N) -> (&Self::Prefix, &Self::Suffix) {
+        (ListIndex::index(self, ..n), ListIndex::index(self, n..))
+    }
+}
+
 /// Type alias for getting element type at index `N`, following [`std::ops::Index`] convention
 /// Element type at index `N` of list `L`.
 pub type Item<L, N> = <L as ListIndex<N>>::Output;
 
+/// Resolves the element type at typenum index `N` of a `List`, without an index expression.
+///
+/// Unlike [`ListIndex`], this is implemented for every `List`, so it can be named in `where`
+/// clauses of generic code over a segment's stack type.
+///
+/// - Postcondition: an index past the end of the list resolves to [`Undefined`].
+///
+/// # Examples
+///
+/// ```rust
+/// use cel_runtime::*;
+/// use typenum::U1;
+///
+/// fn second<L: List + ListElement<U1, Element = f64>>(_: &L) {}
+/// second(&(1i32, 2.5f64).into_c_stack_list());
+/// ```
+pub trait ListElement<N: Unsigned> {
+    /// The element type at index `N`.
+    type Element: 'static;
+}
+
+impl<L: List> ListElement<U0> for L {
+    type Element = L::Head;
+}
+
+impl<L: List, U: Unsigned, B: Bit> ListElement<UInt<U, B>> for L
+where
+    UInt<U, B>: Sub<B1>,
+    Sub1<UInt<U, B>>: Unsigned,
+    L::Tail: ListElement<Sub1<UInt<U, B>>>,
+{
+    type Element = <L::Tail as ListElement<Sub1<UInt<U, B>>>>::Element;
+}
+
+/// Element type at typenum index `N` of list `L`; see [`ListElement`].
+pub type TypeAt<L, N> = <L as ListElement<N>>::Element;
+
 /// Convert to a type-level `List` without consuming `self`.
 pub trait ToList {
     /// The resulting `List` type using `T` as the empty list type family.
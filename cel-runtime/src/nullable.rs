@@ -0,0 +1,109 @@
+//! CEL's `null` value and the optional types it unifies into, used to reconcile a conditional
+//! whose branches produce a value and `null`.
+//!
+//! A branch producing a `T` is wrapped into `Some`, and a branch producing [`CelNull`] into
+//! `None::<T>`, so both produce an `Option<T>`. A branch that already produces an `Option<T>` is
+//! left as is.
+
+use crate::dyn_segment::DynSegment;
+use anyhow::{Result, anyhow};
+use std::any::TypeId;
+
+/// The type of CEL's `null` literal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CelNull;
+
+/// One type `T` that unifies with [`CelNull`] into `Option<T>`.
+struct Nullable {
+    value: fn() -> TypeId,
+    option: fn() -> TypeId,
+    wrap_some: fn(&mut DynSegment) -> Result<()>,
+    wrap_none: fn(&mut DynSegment) -> Result<()>,
+}
+
+macro_rules! nullables {
+    ($($t:ty),+ $(,)?) => {
+        &[$(Nullable {
+            value: TypeId::of::<$t>,
+            option: TypeId::of::<Option<$t>>,
+            wrap_some: |segment| segment.op1(Some::<$t>),
+            wrap_none: |segment| segment.op1(|_: CelNull| None::<$t>),
+        },)+]
+    };
+}
+
+/// Every type a literal or built-in operator produces.
+const NULLABLES: &[Nullable] = nullables!(
+    bool, char, String, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+);
+
+/// Appends ops to `value` and `null`, the two branches of a conditional, so both produce
+/// `Option<T>`: `value` produces a `T` or an `Option<T>`, and `null` produces a [`CelNull`].
+///
+/// # Errors
+///
+/// Returns an error if either fragment produces no value, if `null` doesn't produce a `CelNull`,
+/// or if `value` produces a type with no optional form in the table.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{CelNull, DynSegment, unify_null};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| true);
+/// let mut then_fragment = segment.new_fragment();
+/// then_fragment.op0(|| 5i32);
+/// let mut else_fragment = segment.new_fragment();
+/// else_fragment.op0(|| CelNull);
+/// unify_null(&mut then_fragment, &mut else_fragment).unwrap();
+/// segment.join2(then_fragment, else_fragment).unwrap();
+/// assert_eq!(segment.call0::<Option<i32>>().unwrap(), Some(5));
+/// ```
+pub fn unify_null(value: &mut DynSegment, null: &mut DynSegment) -> Result<()> {
+    let ([value_info], [null_info]) = (value.peek_stack_infos(1), null.peek_stack_infos(1)) else {
+        return Err(anyhow!("each branch must produce a value"));
+    };
+    if null_info.type_id != TypeId::of::<CelNull>() {
+        return Err(anyhow!("expected `null`, got {}", null_info.type_name));
+    }
+    let id = value_info.type_id;
+    if let Some(nullable) = NULLABLES.iter().find(|n| (n.option)() == id) {
+        return (nullable.wrap_none)(null);
+    }
+    let nullable = NULLABLES
+        .iter()
+        .find(|n| (n.value)() == id)
+        .ok_or_else(|| anyhow!("no optional type for {}", value_info.type_name))?;
+    (nullable.wrap_some)(value)?;
+    (nullable.wrap_none)(null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unify_null_keeps_an_optional_branch() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| false);
+        let mut then_fragment = segment.new_fragment();
+        then_fragment.op0(|| Some(String::from("x")));
+        let mut else_fragment = segment.new_fragment();
+        else_fragment.op0(|| CelNull);
+        unify_null(&mut then_fragment, &mut else_fragment)?;
+        segment.join2(then_fragment, else_fragment)?;
+        assert_eq!(segment.call0::<Option<String>>()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn unify_null_rejects_unlisted_types() {
+        let mut value = DynSegment::new::<()>();
+        value.op0(|| vec![1u8]);
+        let mut null = DynSegment::new::<()>();
+        null.op0(|| CelNull);
+        let err = unify_null(&mut value, &mut null).unwrap_err();
+        assert_eq!(err.to_string(), "no optional type for alloc::vec::Vec<u8>");
+    }
+}
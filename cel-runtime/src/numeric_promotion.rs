@@ -0,0 +1,272 @@
+//! Lossless integer promotions used to reconcile mismatched integer values, e.g. the two branch
+//! results of a conditional, and integer-to-`f64` promotions for mixed arithmetic like `3 + 2.5`.
+//!
+//! The tables list every pair of built-in types where the narrower type converts into the
+//! wider one via [`From`], so a promotion never changes a value.
+
+use crate::dyn_segment::DynSegment;
+use anyhow::{Result, anyhow};
+use std::any::{TypeId, type_name};
+
+/// One lossless widening conversion from `from` to `to`.
+struct Widening {
+    from: fn() -> TypeId,
+    to: fn() -> TypeId,
+    to_size: usize,
+    apply: fn(&mut DynSegment) -> Result<()>,
+}
+
+/// Appends an op converting the `T` on top of `segment` into an `R`.
+fn widen<T: 'static, R: From<T> + 'static>(segment: &mut DynSegment) -> Result<()> {
+    segment.op1(R::from)
+}
+
+macro_rules! widenings {
+    ($($from:ty => [$($to:ty),+]),+ $(,)?) => {
+        &[$($(Widening {
+            from: TypeId::of::<$from>,
+            to: TypeId::of::<$to>,
+            to_size: size_of::<$to>(),
+            apply: widen::<$from, $to>,
+        },)+)+]
+    };
+}
+
+/// Every lossless widening between the built-in integer types up to 64 bits.
+const WIDENINGS: &[Widening] = widenings! {
+    i8 => [i16, i32, i64],
+    i16 => [i32, i64],
+    i32 => [i64],
+    u8 => [u16, u32, u64, i16, i32, i64],
+    u16 => [u32, u64, i32, i64],
+    u32 => [u64, i64],
+};
+
+/// Returns the table entry widening `from` into `to`, if there is one.
+fn find_widening(from: TypeId, to: TypeId) -> Option<&'static Widening> {
+    WIDENINGS
+        .iter()
+        .find(|w| (w.from)() == from && (w.to)() == to)
+}
+
+/// Returns the narrowest integer type both `a` and `b` convert into losslessly, or `None` if
+/// either isn't a built-in integer type or no such type exists (e.g. `u64` and `i8`).
+///
+/// - Postcondition: the result is `a` or `b` whenever one already widens into the other.
+/// - Complexity: O(n²) in the size of the promotion table.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::common_integer_type;
+/// use std::any::TypeId;
+///
+/// let common = common_integer_type(TypeId::of::<i32>(), TypeId::of::<u32>());
+/// assert_eq!(common, Some(TypeId::of::<i64>()));
+/// ```
+#[must_use]
+pub fn common_integer_type(a: TypeId, b: TypeId) -> Option<TypeId> {
+    if a == b {
+        return WIDENINGS
+            .iter()
+            .any(|w| (w.from)() == a || (w.to)() == a)
+            .then_some(a);
+    }
+    if find_widening(a, b).is_some() {
+        return Some(b);
+    }
+    if find_widening(b, a).is_some() {
+        return Some(a);
+    }
+    WIDENINGS
+        .iter()
+        .filter(|w| (w.from)() == a && find_widening(b, (w.to)()).is_some())
+        .min_by_key(|w| w.to_size)
+        .map(|w| (w.to)())
+}
+
+/// Appends an op to `segment` converting the integer on top of its stack into the integer type
+/// `to`. Does nothing if the top value already has type `to`.
+///
+/// # Errors
+///
+/// Returns an error if `segment` has no value on top, or if the top value doesn't convert into
+/// `to` losslessly.
+pub fn widen_integer(segment: &mut DynSegment, to: TypeId) -> Result<()> {
+    let from = segment
+        .peek_output_type_id()
+        .ok_or_else(|| anyhow!("no value to widen into {}", type_name_of(to)))?;
+    if from == to {
+        return Ok(());
+    }
+    let widening = find_widening(from, to).ok_or_else(|| {
+        anyhow!(
+            "cannot widen {} into {}",
+            segment.peek_stack_infos(1)[0].type_name,
+            type_name_of(to)
+        )
+    })?;
+    (widening.apply)(segment)
+}
+
+/// One lossless conversion of an integer type into `f64`.
+struct FloatPromotion {
+    from: fn() -> TypeId,
+    /// Converts the integer on top of the stack.
+    top: fn(&mut DynSegment) -> Result<()>,
+    /// Converts the integer beneath an `f64` on top of the stack.
+    beneath: fn(&mut DynSegment) -> Result<()>,
+}
+
+macro_rules! float_promotions {
+    ($($from:ty),+ $(,)?) => {
+        &[$(FloatPromotion {
+            from: TypeId::of::<$from>,
+            top: widen::<$from, f64>,
+            beneath: |segment| segment.op1_beneath::<$from, f64, f64, _>(f64::from),
+        },)+]
+    };
+}
+
+/// Every built-in integer type `f64` represents exactly.
+const FLOAT_PROMOTIONS: &[FloatPromotion] = float_promotions!(i8, i16, i32, u8, u16, u32);
+
+/// Returns whether the integer type `id` converts into `f64` losslessly.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::promotes_to_f64;
+/// use std::any::TypeId;
+///
+/// assert!(promotes_to_f64(TypeId::of::<i32>()));
+/// assert!(!promotes_to_f64(TypeId::of::<i64>()));
+/// ```
+#[must_use]
+pub fn promotes_to_f64(id: TypeId) -> bool {
+    FLOAT_PROMOTIONS.iter().any(|p| (p.from)() == id)
+}
+
+/// If the top two values of `segment` are an `f64` and an integer that converts into `f64`
+/// losslessly, in either order, appends an op converting the integer into an `f64` so that a
+/// binary `f64` operator applies. Returns whether an op was appended.
+///
+/// # Errors
+///
+/// Returns an error only if appending the conversion fails, which doesn't happen for a stack
+/// matching the checked types.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, promote_operands_to_f64};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| 3i32);
+/// segment.op0(|| 2.5f64);
+/// assert!(promote_operands_to_f64(&mut segment).unwrap());
+/// segment.op2(|a: f64, b: f64| a * b).unwrap();
+/// assert_eq!(segment.call0::<f64>().unwrap(), 7.5);
+/// ```
+pub fn promote_operands_to_f64(segment: &mut DynSegment) -> Result<bool> {
+    let [lhs, rhs] = match segment.peek_stack_infos(2) {
+        [lhs, rhs] => [lhs.type_id, rhs.type_id],
+        _ => return Ok(false),
+    };
+    let f64_id = TypeId::of::<f64>();
+    let find = |id: TypeId| FLOAT_PROMOTIONS.iter().find(|p| (p.from)() == id);
+    match (find(lhs), find(rhs)) {
+        (Some(promotion), None) if rhs == f64_id => (promotion.beneath)(segment)?,
+        (None, Some(promotion)) if lhs == f64_id => (promotion.top)(segment)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Returns the name of the integer type `id` from the promotion table, for error messages.
+fn type_name_of(id: TypeId) -> &'static str {
+    macro_rules! names {
+        ($($t:ty),+) => {
+            $(if id == TypeId::of::<$t>() {
+                return type_name::<$t>();
+            })+
+        };
+    }
+    names!(i8, i16, i32, i64, u8, u16, u32, u64);
+    "unknown type"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn common_integer_type_prefers_the_wider_operand() {
+        let id = TypeId::of::<i64>();
+        assert_eq!(common_integer_type(TypeId::of::<i32>(), id), Some(id));
+        assert_eq!(common_integer_type(id, TypeId::of::<u8>()), Some(id));
+    }
+
+    #[test]
+    fn common_integer_type_picks_narrowest_shared_type() {
+        assert_eq!(
+            common_integer_type(TypeId::of::<i8>(), TypeId::of::<u8>()),
+            Some(TypeId::of::<i16>())
+        );
+        assert_eq!(
+            common_integer_type(TypeId::of::<u32>(), TypeId::of::<i32>()),
+            Some(TypeId::of::<i64>())
+        );
+    }
+
+    #[test]
+    fn common_integer_type_rejects_unrelated_types() {
+        assert_eq!(
+            common_integer_type(TypeId::of::<u64>(), TypeId::of::<i8>()),
+            None
+        );
+        assert_eq!(
+            common_integer_type(TypeId::of::<f64>(), TypeId::of::<i32>()),
+            None
+        );
+        assert_eq!(
+            common_integer_type(TypeId::of::<f64>(), TypeId::of::<f64>()),
+            None
+        );
+    }
+
+    #[test]
+    fn widen_integer_appends_conversion() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| -7i8);
+        widen_integer(&mut segment, TypeId::of::<i64>())?;
+        assert_eq!(segment.call0::<i64>()?, -7);
+        Ok(())
+    }
+
+    #[test]
+    fn promote_operands_to_f64_converts_either_operand() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1.5f64);
+        segment.op0(|| 2u8);
+        assert!(promote_operands_to_f64(&mut segment)?);
+        segment.op2(|a: f64, b: f64| a - b)?;
+        assert_eq!(segment.call0::<f64>()?, -0.5);
+
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 1i64);
+        segment.op0(|| 2.5f64);
+        assert!(!promote_operands_to_f64(&mut segment)?);
+        segment.op0(|| 1i64);
+        assert!(!promote_operands_to_f64(&mut segment)?);
+        Ok(())
+    }
+
+    #[test]
+    fn widen_integer_rejects_narrowing() {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| 7i64);
+        let err = widen_integer(&mut segment, TypeId::of::<i32>()).unwrap_err();
+        assert_eq!(err.to_string(), "cannot widen i64 into i32");
+    }
+}
@@ -0,0 +1,315 @@
+//! Op factories for use with the `op#` methods of [`DynSegment`](crate::DynSegment) and
+//! [`Segment`](crate::Segment).
+//!
+//! Each factory returns a plain function pointer, so the same op can be pushed onto any number of
+//! segments without capturing state. The exceptions are operator sections, such as
+//! [`add_const`](crate::ops::add_const), which capture their constant operand and return a
+//! cloneable closure instead.
+
+use anyhow::{Result, anyhow};
+use std::any::type_name;
+use std::fmt::Display;
+use std::ops::{Add, Mul, Sub};
+
+/// Integer types with two's-complement wrapping arithmetic.
+pub trait WrappingArithmetic: Copy + 'static {
+    /// Returns `self + rhs`, wrapping around at the boundary of the type.
+    fn wrapping_add(self, rhs: Self) -> Self;
+    /// Returns `self - rhs`, wrapping around at the boundary of the type.
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    /// Returns `self * rhs`, wrapping around at the boundary of the type.
+    fn wrapping_mul(self, rhs: Self) -> Self;
+}
+
+/// Integer types with overflow-checked arithmetic.
+pub trait CheckedArithmetic: Copy + 'static {
+    /// Returns `self + rhs`, or `None` on overflow.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    /// Returns `self - rhs`, or `None` on overflow.
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    /// Returns `self * rhs`, or `None` on overflow.
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! wrapping_arithmetic {
+    ($($t:ty),*) => {
+        $(impl WrappingArithmetic for $t {
+            fn wrapping_add(self, rhs: Self) -> Self {
+                <$t>::wrapping_add(self, rhs)
+            }
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                <$t>::wrapping_sub(self, rhs)
+            }
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                <$t>::wrapping_mul(self, rhs)
+            }
+        })*
+    };
+}
+
+wrapping_arithmetic!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize
+);
+
+macro_rules! checked_arithmetic {
+    ($($t:ty),*) => {
+        $(impl CheckedArithmetic for $t {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        })*
+    };
+}
+
+checked_arithmetic!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize
+);
+
+/// Returns a binary op computing `a + b` with wrapping on overflow.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| u8::MAX);
+/// segment.op0(|| 1u8);
+/// segment.op2(ops::wrapping_add::<u8>()).unwrap();
+/// assert_eq!(segment.call0::<u8>().unwrap(), 0);
+/// ```
+#[must_use]
+pub fn wrapping_add<T: WrappingArithmetic>() -> fn(T, T) -> T {
+    T::wrapping_add
+}
+
+/// Returns a binary op computing `a - b` with wrapping on overflow.
+#[must_use]
+pub fn wrapping_sub<T: WrappingArithmetic>() -> fn(T, T) -> T {
+    T::wrapping_sub
+}
+
+/// Returns a binary op computing `a * b` with wrapping on overflow.
+#[must_use]
+pub fn wrapping_mul<T: WrappingArithmetic>() -> fn(T, T) -> T {
+    T::wrapping_mul
+}
+
+/// Returns a binary op computing `a + b`, or an error on overflow.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| u8::MAX);
+/// segment.op0(|| 1u8);
+/// segment.op2r(ops::checked_add::<u8>()).unwrap();
+/// assert!(segment.call0::<u8>().is_err());
+/// ```
+#[must_use]
+pub fn checked_add<T: CheckedArithmetic>() -> fn(T, T) -> Result<T> {
+    |a, b| {
+        a.checked_add(b)
+            .ok_or_else(|| anyhow!("arithmetic overflow"))
+    }
+}
+
+/// Returns a binary op computing `a - b`, or an error on overflow.
+#[must_use]
+pub fn checked_sub<T: CheckedArithmetic>() -> fn(T, T) -> Result<T> {
+    |a, b| {
+        a.checked_sub(b)
+            .ok_or_else(|| anyhow!("arithmetic overflow"))
+    }
+}
+
+/// Returns a binary op computing `a * b`, or an error on overflow.
+#[must_use]
+pub fn checked_mul<T: CheckedArithmetic>() -> fn(T, T) -> Result<T> {
+    |a, b| {
+        a.checked_mul(b)
+            .ok_or_else(|| anyhow!("arithmetic overflow"))
+    }
+}
+
+/// Returns a binary op computing `a + b` that panics on overflow, in release builds as well as
+/// debug builds.
+///
+/// # Examples
+///
+/// ```should_panic
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| u8::MAX);
+/// segment.op0(|| 1u8);
+/// segment.op2(ops::panicking_add::<u8>()).unwrap();
+/// let _ = segment.call0::<u8>(); // panics with "arithmetic overflow"
+/// ```
+#[must_use]
+pub fn panicking_add<T: CheckedArithmetic>() -> fn(T, T) -> T {
+    |a, b| a.checked_add(b).expect("arithmetic overflow")
+}
+
+/// Returns a binary op computing `a - b` that panics on overflow, in release builds as well as
+/// debug builds.
+#[must_use]
+pub fn panicking_sub<T: CheckedArithmetic>() -> fn(T, T) -> T {
+    |a, b| a.checked_sub(b).expect("arithmetic overflow")
+}
+
+/// Returns a binary op computing `a * b` that panics on overflow, in release builds as well as
+/// debug builds.
+#[must_use]
+pub fn panicking_mul<T: CheckedArithmetic>() -> fn(T, T) -> T {
+    |a, b| a.checked_mul(b).expect("arithmetic overflow")
+}
+
+/// Returns a unary op converting an `F` to a `T` with `TryFrom` semantics: a value `T` can't
+/// represent is an error rather than being truncated, unlike an `as` cast in Rust. Lossless
+/// conversions (e.g. `i32` to `f64`) never fail.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<(i64,)>();
+/// segment.op1r(ops::cast::<i64, i32>()).unwrap();
+/// assert_eq!(segment.call1::<i64, i32>(-5).unwrap(), -5);
+/// ```
+#[must_use]
+pub fn cast<F, T>() -> fn(F) -> Result<T>
+where
+    T: TryFrom<F>,
+    T::Error: Display,
+{
+    |value| {
+        T::try_from(value).map_err(|e| {
+            anyhow!(
+                "cast from `{}` to `{}` failed: {e}",
+                type_name::<F>(),
+                type_name::<T>()
+            )
+        })
+    }
+}
+
+/// Returns a unary op flattening a list of lists into one list, in order.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| vec![vec![1, 2], vec![], vec![3]]);
+/// segment.op1(ops::flatten::<i32>()).unwrap();
+/// assert_eq!(segment.call0::<Vec<i32>>().unwrap(), [1, 2, 3]);
+/// ```
+#[must_use]
+pub fn flatten<T>() -> fn(Vec<Vec<T>>) -> Vec<T> {
+    |lists| lists.into_iter().flatten().collect()
+}
+
+/// Returns a binary op concatenating list `b` onto the end of list `a`.
+#[must_use]
+pub fn concat<T>() -> fn(Vec<T>, Vec<T>) -> Vec<T> {
+    |mut a, b| {
+        a.extend(b);
+        a
+    }
+}
+
+/// Returns a unary op computing `x + c`, the section `(+ c)` of the binary `+`, for building
+/// point-free ops without pushing `c` as a separate value. Overflow behaves as `+` does for `T`.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, ops};
+///
+/// let mut segment = DynSegment::new::<(i32,)>();
+/// segment.op1(ops::add_const(5)).unwrap();
+/// assert_eq!(segment.call1::<i32, i32>(37).unwrap(), 42);
+/// ```
+pub fn add_const<T>(c: T) -> impl Fn(T) -> T + Clone + 'static
+where
+    T: Add<Output = T> + Copy + 'static,
+{
+    move |x| x + c
+}
+
+/// Returns a unary op computing `x - c`, the section `(- c)` of the binary `-`.
+pub fn sub_const<T>(c: T) -> impl Fn(T) -> T + Clone + 'static
+where
+    T: Sub<Output = T> + Copy + 'static,
+{
+    move |x| x - c
+}
+
+/// Returns a unary op computing `x * c`, the section `(* c)` of the binary `*`.
+pub fn mul_const<T>(c: T) -> impl Fn(T) -> T + Clone + 'static
+where
+    T: Mul<Output = T> + Copy + 'static,
+{
+    move |x| x * c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapping_ops_wrap_at_type_boundary() {
+        assert_eq!(wrapping_add::<u8>()(u8::MAX, 1), 0);
+        assert_eq!(wrapping_sub::<u8>()(0, 1), u8::MAX);
+        assert_eq!(wrapping_mul::<u8>()(128, 2), 0);
+        assert_eq!(wrapping_add::<i32>()(i32::MAX, 1), i32::MIN);
+        assert_eq!(wrapping_sub::<i64>()(i64::MIN, 1), i64::MAX);
+        assert_eq!(wrapping_mul::<i8>()(-128, -1), -128);
+    }
+
+    #[test]
+    fn cast_checks_range() {
+        assert_eq!(cast::<i64, i32>()(-7).unwrap(), -7);
+        assert_eq!(
+            cast::<i64, i32>()(i64::from(i32::MAX) + 1)
+                .unwrap_err()
+                .to_string(),
+            "cast from `i64` to `i32` failed: out of range integral type conversion attempted"
+        );
+        assert_eq!(cast::<i32, f64>()(-3).unwrap(), -3.0);
+    }
+
+    #[test]
+    fn sections_apply_constant_operand() -> Result<()> {
+        let add_five = add_const(5u32);
+        let mut segment = crate::DynSegment::new::<(u32,)>();
+        segment.op1(add_five.clone())?;
+        segment.op1(add_five)?;
+        assert_eq!(segment.call1::<u32, u32>(1)?, 11);
+
+        assert_eq!(sub_const(0.5f64)(2.0), 1.5);
+        assert_eq!(mul_const(-3i64)(7), -21);
+        Ok(())
+    }
+
+    #[test]
+    fn list_ops_join_lists_in_order() {
+        assert_eq!(
+            flatten::<char>()(vec![vec!['a'], vec![], vec!['b', 'c']]),
+            ['a', 'b', 'c']
+        );
+        assert_eq!(concat::<u8>()(vec![1, 2], vec![3]), [1, 2, 3]);
+        assert_eq!(concat::<u8>()(vec![], vec![]), []);
+    }
+}
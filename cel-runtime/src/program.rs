@@ -0,0 +1,322 @@
+//! A compiled, immutable form of a [`DynSegment`] that can be called repeatedly.
+//!
+//! A [`DynSegment`] is a builder: its `call*` methods consume the tracked type stack, so it can
+//! only be executed once. [`DynSegment::into_program`] freezes the ops together with the argument
+//! and result types into a [`Program`], which only executes. [`DynSegment::prepare`] instead
+//! borrows the segment and returns a [`PreparedCall`] that checks the signature once and reuses
+//! its stack across calls.
+
+use crate::dyn_segment::DynSegment;
+use crate::raw_segment::RawSegment;
+use crate::raw_stack::RawStack;
+use anyhow::{Result, ensure};
+use std::any::{TypeId, type_name};
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+/// A compiled segment that can be called any number of times and cheaply cloned; clones share
+/// the ops.
+///
+/// Argument and result types are checked on every call.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::DynSegment;
+///
+/// let mut segment = DynSegment::new::<(i32,)>();
+/// segment.op1(|x: i32| x * 2).unwrap();
+/// let program = segment.into_program().unwrap();
+/// assert_eq!(program.call1::<i32, i32>(21).unwrap(), 42);
+/// assert_eq!(program.clone().call1::<i32, i32>(5).unwrap(), 10);
+/// ```
+#[derive(Clone)]
+pub struct Program {
+    segment: Rc<RawSegment>,
+    argument_ids: Vec<TypeId>,
+    /// Type names for each argument slot, for error reporting (parallel to `argument_ids`).
+    argument_names: Vec<Cow<'static, str>>,
+    result_id: TypeId,
+    result_name: Cow<'static, str>,
+    max_stack_offset: usize,
+}
+
+impl Program {
+    pub(crate) fn new(
+        segment: RawSegment,
+        argument_ids: Vec<TypeId>,
+        argument_names: Vec<Cow<'static, str>>,
+        (result_id, result_name): (TypeId, Cow<'static, str>),
+        max_stack_offset: usize,
+    ) -> Self {
+        Program {
+            segment: Rc::new(segment),
+            argument_ids,
+            argument_names,
+            result_id,
+            result_name,
+            max_stack_offset,
+        }
+    }
+
+    /// Checks that the program takes arguments of exactly the types `ids`, named `names`, and
+    /// returns an `R`.
+    fn check_signature<R: 'static>(&self, ids: &[TypeId], names: &[&str]) -> Result<()> {
+        ensure!(
+            self.argument_ids.len() == ids.len(),
+            "expected {} argument(s), but program requires {} argument(s)",
+            ids.len(),
+            self.argument_ids.len()
+        );
+        for ((id, name), (expected_id, expected_name)) in ids
+            .iter()
+            .zip(names)
+            .zip(self.argument_ids.iter().zip(&self.argument_names))
+        {
+            ensure!(
+                id == expected_id,
+                "argument type mismatch: expected {expected_name}, got {name}"
+            );
+        }
+        ensure!(
+            self.result_id == TypeId::of::<R>(),
+            "result type mismatch: expected {}, got {}",
+            self.result_name,
+            type_name::<R>()
+        );
+        Ok(())
+    }
+
+    /// Executes the program, which must take no arguments, and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program takes arguments, if its result is not an `R`, or if any
+    /// op returns an error.
+    pub fn call0<R: 'static>(&self) -> Result<R> {
+        self.check_signature::<R>(&[], &[])?;
+        unsafe { self.segment.call0_presized(self.max_stack_offset) }
+    }
+
+    /// Executes the program with one argument and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program doesn't take exactly one `A`, if its result is not an
+    /// `R`, or if any op returns an error.
+    pub fn call1<A: 'static, R: 'static>(&self, arg: A) -> Result<R> {
+        self.check_signature::<R>(&[TypeId::of::<A>()], &[type_name::<A>()])?;
+        unsafe { self.segment.call1(arg) }
+    }
+
+    /// Executes the program with two arguments and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the program doesn't take exactly an `A` and a `B`, if its result is
+    /// not an `R`, or if any op returns an error.
+    pub fn call2<A: 'static, B: 'static, R: 'static>(&self, args: (A, B)) -> Result<R> {
+        self.check_signature::<R>(
+            &[TypeId::of::<A>(), TypeId::of::<B>()],
+            &[type_name::<A>(), type_name::<B>()],
+        )?;
+        unsafe { self.segment.call2(args) }
+    }
+}
+
+/// A handle for calling a borrowed [`DynSegment`] repeatedly with an `A`, returning an `R`.
+///
+/// The signature is checked once by [`DynSegment::prepare`]; each [`call`](Self::call) only runs
+/// the ops, on a stack that is allocated once and reused.
+pub struct PreparedCall<'a, A, R> {
+    segment: &'a RawSegment,
+    stack: RawStack,
+    _signature: PhantomData<fn(A) -> R>,
+}
+
+impl<A: 'static, R: 'static> PreparedCall<'_, A, R> {
+    /// Executes the segment with `arg` and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any op returns an error.
+    ///
+    /// - Complexity: O(n) in the number of ops; no allocation once the stack has grown to the
+    ///   segment's high-water mark.
+    pub fn call(&mut self, arg: A) -> Result<R> {
+        // Failing ops unwind the values below them, but an earlier call that returned an error
+        // without doing so, or that panicked, may have left values behind; start over rather
+        // than run ops on stale bytes. The values left behind are leaked.
+        if self.stack.len() != 0 {
+            self.stack = RawStack::with_base_alignment(self.segment.base_alignment());
+        }
+        self.stack.push(arg);
+        // Safety: `prepare` checked that the segment takes exactly one `A` and leaves exactly
+        // one `R`, and the stack is empty at this point.
+        unsafe {
+            self.segment.call0_stack(&mut self.stack)?;
+            Ok(self.stack.pop(false))
+        }
+    }
+}
+
+impl DynSegment {
+    /// Checks once that the segment takes exactly one `A` and leaves exactly one `R`, and returns
+    /// a [`PreparedCall`] for calling it repeatedly. Unlike [`call1`](Self::call1), this does
+    /// not consume the segment's type stack.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment doesn't take exactly one `A` or doesn't leave exactly one
+    /// `R` on the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::DynSegment;
+    ///
+    /// let mut segment = DynSegment::new::<(i32,)>();
+    /// segment.op1(|x: i32| x + 1).unwrap();
+    /// let mut prepared = segment.prepare::<i32, i32>().unwrap();
+    /// assert_eq!(prepared.call(1).unwrap(), 2);
+    /// assert_eq!(prepared.call(41).unwrap(), 42);
+    /// ```
+    pub fn prepare<A: 'static, R: 'static>(&self) -> Result<PreparedCall<'_, A, R>> {
+        ensure!(
+            self.argument_ids.len() == 1,
+            "expected 1 argument, but segment requires {} argument(s)",
+            self.argument_ids.len()
+        );
+        self.check_argument::<A>(0)?;
+        ensure!(
+            self.stack_ids.len() == 1,
+            "segment must leave exactly 1 value on the stack, but leaves {}",
+            self.stack_ids.len()
+        );
+        ensure!(
+            self.stack_ids[0].type_id == TypeId::of::<R>(),
+            "result type mismatch: expected {}, got {}",
+            self.stack_ids[0].type_name,
+            type_name::<R>()
+        );
+        Ok(PreparedCall {
+            segment: &self.segment,
+            stack: RawStack::with_base_alignment_and_capacity(
+                self.segment.base_alignment(),
+                self.max_stack_offset,
+            ),
+            _signature: PhantomData,
+        })
+    }
+
+    /// Freezes the segment into a [`Program`] that can be called repeatedly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the segment doesn't leave exactly one value on the stack.
+    pub fn into_program(self) -> Result<Program> {
+        ensure!(
+            self.stack_ids.len() == 1,
+            "program must leave exactly 1 value on the stack, but leaves {}",
+            self.stack_ids.len()
+        );
+        let max_stack_offset = self.max_stack_offset;
+        let result = &self.stack_ids[0];
+        let result = (result.type_id, result.type_name.clone());
+        Ok(Program::new(
+            self.segment,
+            self.argument_ids,
+            self.argument_names,
+            result,
+            max_stack_offset,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepared_call_runs_repeatedly() -> Result<()> {
+        let mut segment = DynSegment::new::<(u64,)>();
+        segment.op0(|| 3u64);
+        segment.op2r(|x: u64, y: u64| {
+            x.checked_mul(y)
+                .ok_or_else(|| anyhow::anyhow!("multiply overflow"))
+        })?;
+        let mut prepared = segment.prepare::<u64, u64>()?;
+        for i in 0..1000 {
+            assert_eq!(prepared.call(i)?, i * 3);
+        }
+        assert!(prepared.call(u64::MAX).is_err());
+        assert_eq!(prepared.call(7)?, 21);
+        assert!(segment.prepare::<u32, u64>().is_err());
+        assert!(segment.prepare::<u64, u32>().is_err());
+        assert_eq!(segment.call1::<u64, u64>(2)?, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn prepared_call_starts_over_after_a_panicking_op() -> Result<()> {
+        let fail = std::rc::Rc::new(std::cell::Cell::new(true));
+        let failing = fail.clone();
+        let mut segment = DynSegment::new::<(u64,)>();
+        segment.op0(|| 3u64);
+        segment.op1(move |x: u64| {
+            assert!(!failing.get(), "op panicked");
+            x
+        })?;
+        segment.op2(|x: u64, y: u64| x * y)?;
+        let mut prepared = segment.prepare::<u64, u64>()?;
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| prepared.call(5)));
+        assert!(panic.is_err());
+        // The argument is still on the stack, beneath the popped operand.
+        assert_ne!(prepared.stack.len(), 0);
+
+        fail.set(false);
+        assert_eq!(prepared.call(7)?, 21);
+        assert_eq!(prepared.stack.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn program_is_callable_repeatedly() -> Result<()> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| String::from("ab"));
+        segment.op1(|s: String| s.repeat(2))?;
+        let program = segment.into_program()?;
+        assert_eq!(program.call0::<String>()?, "abab");
+        assert_eq!(program.call0::<String>()?, "abab");
+        assert_eq!(program.clone().call0::<String>()?, "abab");
+        Ok(())
+    }
+
+    #[test]
+    fn program_checks_signature() -> Result<()> {
+        let mut segment = DynSegment::new::<(u32, u32)>();
+        segment.op2(|a: u32, b: u32| a + b)?;
+        let program = segment.into_program()?;
+        assert_eq!(program.call2::<u32, u32, u32>((2, 3))?, 5);
+        assert_eq!(
+            program.call1::<u32, u32>(2).unwrap_err().to_string(),
+            "expected 1 argument(s), but program requires 2 argument(s)"
+        );
+        assert_eq!(
+            program
+                .call2::<u32, i32, u32>((2, 3))
+                .unwrap_err()
+                .to_string(),
+            "argument type mismatch: expected u32, got i32"
+        );
+        assert_eq!(
+            program
+                .call2::<u32, u32, u64>((2, 3))
+                .unwrap_err()
+                .to_string(),
+            "result type mismatch: expected u32, got u64"
+        );
+        Ok(())
+    }
+}
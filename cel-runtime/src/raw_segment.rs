@@ -1,18 +1,185 @@
+use crate::dyn_segment::OpInfo;
+use crate::memory::align_index;
 use crate::raw_sequence::RawSequence;
 use crate::raw_stack::RawStack;
-use anyhow::Result;
+use anyhow::{Result, anyhow, ensure};
+use std::any::{Any, TypeId, type_name};
+use std::cell::Cell;
 use std::cmp::max;
+use std::rc::Rc;
 
-type Operation = fn(&RawSequence, usize, &mut RawStack) -> Result<usize>;
+thread_local! {
+    // Safety: points at the environment of the innermost executing segment that has one, valid
+    // only while that segment executes. Restored by `EnvGuard::drop` even on panic.
+    static ENV: Cell<Option<*const dyn Any>> = const { Cell::new(None) };
+}
+
+/// Restores the previous `ENV` when dropped, so segments with environments may nest.
+struct EnvGuard(Option<*const dyn Any>);
+
+impl EnvGuard {
+    /// Makes `env` the active environment until the guard is dropped.
+    fn enter(env: &dyn Any) -> Self {
+        EnvGuard(ENV.with(|e| e.replace(Some(env as *const dyn Any))))
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        ENV.with(|e| e.set(self.0));
+    }
+}
+
+/// Calls `f` with the active environment.
+///
+/// # Errors
+/// Returns an error if no environment is active or if it is not of type `E`.
+fn with_env<E: 'static, R>(f: impl FnOnce(&E) -> R) -> Result<R> {
+    let env = ENV
+        .with(Cell::get)
+        .ok_or_else(|| anyhow!("segment has no environment"))?;
+    // Safety: `ENV` is only set by an `EnvGuard` living for the duration of the call that
+    // borrows the environment.
+    let env = unsafe { &*env }
+        .downcast_ref::<E>()
+        .ok_or_else(|| anyhow!("segment environment is not of type {}", type_name::<E>()))?;
+    Ok(f(env))
+}
+
+/// The step budget and deadline a call enforces on every op it runs. The limits are carried by
+/// the [`RawStack`] the call runs on, so they also apply to sub-segments an op runs on that stack
+/// (e.g. the branches of a conditional), but not to calls that make their own stack. Clones share
+/// the remaining budget.
+#[derive(Clone, Default)]
+pub(crate) struct Limits {
+    /// Remaining op invocations, or `None` when execution is unbounded.
+    steps: Option<Rc<Cell<usize>>>,
+    /// Returns `false` once execution should stop, or `None` without a deadline.
+    should_continue: Option<Rc<dyn Fn() -> bool>>,
+}
+
+impl Limits {
+    /// Returns whether there is a step budget or a deadline to check.
+    fn is_active(&self) -> bool {
+        self.steps.is_some() || self.should_continue.is_some()
+    }
+
+    /// Consumes one step from the budget, if any, and checks the deadline, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the budget is already exhausted or the deadline predicate returns
+    /// `false`.
+    fn consume_step(&self) -> Result<()> {
+        if let Some(steps) = &self.steps {
+            ensure!(steps.get() > 0, LimitExceeded::Steps);
+            steps.set(steps.get() - 1);
+        }
+        if let Some(should_continue) = &self.should_continue {
+            ensure!(should_continue(), LimitExceeded::Deadline);
+        }
+        Ok(())
+    }
+}
+
+/// The error of a call stopped by its [`Limits`].
+#[derive(Clone, Copy, Debug)]
+enum LimitExceeded {
+    /// The step budget ran out.
+    Steps,
+    /// The deadline predicate returned `false`.
+    Deadline,
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LimitExceeded::Steps => "step budget exceeded",
+            LimitExceeded::Deadline => "deadline exceeded",
+        })
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+type Operation = fn(&RawSegment, usize, &mut RawStack) -> Result<usize>;
+
+/// Drops the value stored at an offset in a segment's storage.
+type StorageDropper = fn(&mut RawSequence, usize);
+
+/// One entry of a segment's constant table.
+struct Constant {
+    offset: usize,
+    type_id: TypeId,
+    dropper: unsafe fn(&mut RawSequence, usize) -> usize,
+}
 
 /// A segment represents a sequence of operations that can be executed.
 ///
 /// Each operation is stored along with its data in the segment's storage,
 /// and can manipulate values on a stack during execution.
+/// Constants shared by several operations live once in a separate constant table and are
+/// referenced by index.
 pub struct RawSegment {
     ops: Vec<Operation>,
     storage: RawSequence,
-    dropper: Vec<fn(&mut RawSequence, usize) -> usize>,
+    /// The storage offset and dropper of each value in `storage`.
+    dropper: Vec<(usize, StorageDropper)>,
+    /// The largest alignment of any value pushed to `storage`.
+    storage_alignment: usize,
+    base_alignment: usize,
+    env: Option<Box<dyn Any>>,
+    constants: RawSequence,
+    constant_table: Vec<Constant>,
+    /// The metadata of each op, parallel to `ops`.
+    records: Vec<OpRecord>,
+    /// The label recorded with [`push_label`](Self::push_label) for the op pushed next.
+    pending_label: Option<&'static str>,
+}
+
+/// Metadata recorded for one op of a [`RawSegment`]. Records are kept parallel to the op list,
+/// so rewinding the segment removes the records of the removed ops.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpRecord {
+    /// The label recorded with [`RawSegment::push_label`] for the op, if any.
+    pub label: Option<&'static str>,
+    /// The arity and result type of an op pushed through an `op#` method of
+    /// [`DynSegment`](crate::DynSegment).
+    pub info: Option<OpInfo>,
+    /// The source range the op was compiled from, if the compiler recorded one with
+    /// [`RawSegment::attribute_ops`].
+    pub span: Option<OpSpan>,
+    /// The records of the ops of the fragments the op runs, such as the branches of a
+    /// conditional, in order.
+    pub nested: Vec<OpRecord>,
+}
+
+/// A source range an op was compiled from, as `(line, column)` positions with 1-based lines and
+/// 0-based columns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpSpan {
+    /// Start position (inclusive).
+    pub start: (usize, usize),
+    /// End position (exclusive).
+    pub end: (usize, usize),
+}
+
+impl OpRecord {
+    /// Appends the labels of `records`, and of the records nested in them, to `labels` in order.
+    fn collect_labels(records: &[OpRecord], labels: &mut Vec<&'static str>) {
+        for record in records {
+            labels.extend(record.label);
+            Self::collect_labels(&record.nested, labels);
+        }
+    }
+}
+
+/// A point in a [`RawSegment`]'s op list that [`RawSegment::rewind`] returns to.
+#[derive(Clone, Copy, Debug)]
+pub struct RawSegmentMark {
+    ops: usize,
+    storage: usize,
+    droppers: usize,
+    pending_label: Option<&'static str>,
     base_alignment: usize,
 }
 
@@ -30,10 +197,207 @@ impl RawSegment {
             ops: Vec::new(),
             storage: RawSequence::new(),
             dropper: Vec::new(),
+            storage_alignment: 1,
             base_alignment: 0,
+            env: None,
+            constants: RawSequence::new(),
+            constant_table: Vec::new(),
+            records: Vec::new(),
+            pending_label: None,
+        }
+    }
+
+    /// Records `label` as a human-readable name for the op pushed next, for stable textual dumps
+    /// of a segment (e.g. golden tests). Labels do not affect execution.
+    pub fn push_label(&mut self, label: &'static str) {
+        self.pending_label = Some(label);
+    }
+
+    /// Returns the labels recorded with [`push_label`](Self::push_label), in op order, including
+    /// the labels of the fragments nested in joined ops.
+    #[must_use]
+    pub fn op_labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        OpRecord::collect_labels(&self.records, &mut labels);
+        labels
+    }
+
+    /// Returns the metadata of each op, indexed like the op list.
+    #[must_use]
+    pub fn op_records(&self) -> &[OpRecord] {
+        &self.records
+    }
+
+    /// Attributes the ops pushed since the last call, which have no span yet, to `span`.
+    ///
+    /// - Complexity: O(k) in the number of ops attributed.
+    pub fn attribute_ops(&mut self, span: OpSpan) {
+        for record in self
+            .records
+            .iter_mut()
+            .rev()
+            .take_while(|record| record.span.is_none())
+        {
+            record.span = Some(span);
+        }
+    }
+
+    /// Returns the metadata of the op pushed last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no op has been pushed.
+    pub(crate) fn last_op_record_mut(&mut self) -> &mut OpRecord {
+        self.records.last_mut().expect("an op has been pushed")
+    }
+
+    /// Pushes `op` along with its record, which takes the pending label.
+    fn push_operation(&mut self, op: Operation) {
+        self.ops.push(op);
+        self.records.push(OpRecord {
+            label: self.pending_label.take(),
+            ..OpRecord::default()
+        });
+    }
+
+    /// Returns the number of ops pushed so far.
+    #[must_use]
+    pub fn op_count(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Returns a mark for the current end of the op list, for [`rewind`](Self::rewind).
+    #[must_use]
+    pub fn mark(&self) -> RawSegmentMark {
+        RawSegmentMark {
+            ops: self.ops.len(),
+            storage: self.storage.len(),
+            droppers: self.dropper.len(),
+            pending_label: self.pending_label,
+            base_alignment: self.base_alignment,
         }
     }
 
+    /// Removes every op pushed since `mark`, dropping the values they stored. Constants stay in
+    /// the constant table.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mark` is past the end of the op list, e.g. because it came from another
+    /// segment or the segment was already rewound further.
+    ///
+    /// - Complexity: O(k) in the number of removed ops.
+    pub fn rewind(&mut self, mark: RawSegmentMark) {
+        assert!(
+            mark.ops <= self.ops.len()
+                && mark.storage <= self.storage.len()
+                && mark.droppers <= self.dropper.len(),
+            "rewind to a mark past the end of the segment"
+        );
+        let removed = self.dropper.split_off(mark.droppers);
+        assert!(
+            removed.iter().all(|&(offset, _)| offset >= mark.storage),
+            "rewind to a mark from another segment"
+        );
+        self.ops.truncate(mark.ops);
+        self.records.truncate(mark.ops);
+        self.pending_label = mark.pending_label;
+        self.base_alignment = mark.base_alignment;
+        drop_each(removed.len(), |i| {
+            let (offset, dropper) = removed[i];
+            dropper(&mut self.storage, offset);
+        });
+        // Safety: every value past `mark.storage` was stored by a removed op and dropped above.
+        unsafe { self.storage.truncate(mark.storage) };
+    }
+
+    /// Stores `value` in the segment's constant table and returns its index, for use with
+    /// [`push_indirect`](Self::push_indirect).
+    ///
+    /// - Complexity: amortized O(1).
+    pub fn push_constant<T: 'static>(&mut self, value: T) -> usize {
+        let offset = self.constants.push(value);
+        self.constant_table.push(Constant {
+            offset,
+            type_id: TypeId::of::<T>(),
+            dropper: RawSequence::drop_in_place::<T>,
+        });
+        self.constant_table.len() - 1
+    }
+
+    /// Pushes an operation that pushes a clone of the constant at `index` onto the stack. The
+    /// constant is stored once in the constant table, not per op.
+    ///
+    /// # Errors
+    /// Returns an error if there is no constant at `index` or if it is not of type `T`.
+    pub fn push_indirect<T: Clone + 'static>(&mut self, index: usize) -> Result<()> {
+        let constant = self
+            .constant_table
+            .get(index)
+            .ok_or_else(|| anyhow!("no constant at index {index}"))?;
+        ensure!(
+            constant.type_id == TypeId::of::<T>(),
+            "constant {index} is not of type {}",
+            type_name::<T>()
+        );
+        self.push_storage(constant.offset);
+        self.push_operation(|segment, p, stack| {
+            let (offset, r) = unsafe { segment.storage.next::<usize>(p) };
+            let (value, _) = unsafe { segment.constants.next::<T>(*offset) };
+            stack.push(value.clone());
+            Ok(r)
+        });
+        self.base_alignment = max(self.base_alignment, align_of::<T>());
+        Ok(())
+    }
+
+    /// Stores `env` as the environment read by ops pushed with
+    /// [`push_op0_env`](Self::push_op0_env), replacing any previous environment.
+    ///
+    /// While this segment executes, its environment shadows that of any enclosing segment,
+    /// including for sub-segments run from its ops that have no environment of their own.
+    pub fn set_env<E: 'static>(&mut self, env: E) {
+        self.env = Some(Box::new(env));
+    }
+
+    /// Inserts a closure that manipulates the stack directly, as [`raw0_`](Self::raw0_) pushes,
+    /// in front of every op of the segment.
+    ///
+    /// The stored values of the other ops move up in storage by a multiple of their largest
+    /// alignment, so they are read at their new offsets unchanged. Marks taken before the call
+    /// no longer apply.
+    ///
+    /// - Complexity: O(n) in the number of ops and the size of their storage.
+    pub(crate) fn prepend_raw0_<F>(&mut self, op: F)
+    where
+        F: Fn(&mut RawStack) -> Result<()> + 'static,
+    {
+        /// The stored closure, and the offset the original first op's storage moved to.
+        struct Front<F> {
+            op: F,
+            next: usize,
+        }
+
+        let shift = align_index(self.storage_alignment, size_of::<Front<F>>());
+        self.storage.push_front(Front { op, next: shift }, shift);
+        self.storage_alignment = max(self.storage_alignment, align_of::<Front<F>>());
+        for (offset, _) in &mut self.dropper {
+            *offset += shift;
+        }
+        self.dropper.insert(
+            0,
+            (0, |storage, p| unsafe {
+                storage.drop_in_place::<Front<F>>(p);
+            }),
+        );
+        self.ops.insert(0, |segment, p, stack| {
+            let (front, _) = unsafe { segment.storage.next::<Front<F>>(p) };
+            (front.op)(stack)?;
+            Ok(front.next)
+        });
+        self.records.insert(0, OpRecord::default());
+    }
+
     /// Returns the maximum alignment required by any value pushed onto the stack while executing this segment.
     pub(crate) fn base_alignment(&self) -> usize {
         self.base_alignment
@@ -50,9 +414,11 @@ impl RawSegment {
     where
         T: 'static,
     {
-        self.storage.push(value);
-        self.dropper
-            .push(|storage, p| unsafe { storage.drop_in_place::<T>(p) });
+        let offset = self.storage.push(value);
+        self.storage_alignment = max(self.storage_alignment, align_of::<T>());
+        self.dropper.push((offset, |storage, p| unsafe {
+            storage.drop_in_place::<T>(p);
+        }));
     }
 
     /// Pushes a closure that manipulates the stack directly and returns `()`.
@@ -61,8 +427,8 @@ impl RawSegment {
         F: Fn(&mut RawStack) -> Result<()> + 'static,
     {
         self.push_storage(op);
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
             f(stack)?;
             Ok(r)
         });
@@ -75,8 +441,8 @@ impl RawSegment {
         R: 'static,
     {
         self.push_storage(op);
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
             let result = f(stack)?;
             stack.push(result);
             Ok(r)
@@ -84,6 +450,32 @@ impl RawSegment {
         self.base_alignment = max(self.base_alignment, align_of::<R>());
     }
 
+    /// Pushes a nullary operation that borrows the environment of type `E` and returns a value of
+    /// type `R`. The environment is stored once, via [`set_env`](Self::set_env), and is not
+    /// captured by `op`.
+    ///
+    /// Executing the op fails if no environment is active or if it is not of type `E`.
+    pub fn push_op0_env<E, R, F>(&mut self, op: F)
+    where
+        F: Fn(&E) -> R + 'static,
+        E: 'static,
+        R: 'static,
+    {
+        self.raw0(move |_stack| with_env(|env: &E| op(env)));
+    }
+
+    /// Pushes an operation that clones the `T` at byte `offset` from the start of the stack and
+    /// pushes the clone.
+    ///
+    /// # Safety
+    ///
+    /// When the op runs, a live `T` must be stored at `offset`.
+    pub unsafe fn push_clone_at<T: Clone + 'static>(&mut self, offset: usize) {
+        self.raw0(move |stack| {
+            Ok(unsafe { stack.read_at(offset, |ptr| (*ptr.cast::<T>()).clone()) })
+        });
+    }
+
     /// Pushes a unary operation that takes one argument of type T and returns a value of type R.
     pub fn push_op0<R, F>(&mut self, op: F)
     where
@@ -91,8 +483,8 @@ impl RawSegment {
         R: 'static,
     {
         self.push_storage(op);
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
             stack.push(f());
             Ok(r)
         });
@@ -106,9 +498,9 @@ impl RawSegment {
         T: 'static,
         R: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             stack.push(f(x));
             Ok(r)
         });
@@ -121,9 +513,9 @@ impl RawSegment {
         T: 'static,
         R: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             let result = f(stack, x)?;
             stack.push(result);
             Ok(r)
@@ -168,9 +560,9 @@ impl RawSegment {
         F: Fn(T) + 'static,
         T: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             f(x); // drop the result
             Ok(r)
         });
@@ -190,6 +582,23 @@ impl RawSegment {
         }
     }
 
+    /// Pushes the op-dispatch closure for discarding a value with compile-time padding.
+    fn push_drop_<const PADDING0: bool, T: 'static>(&mut self) {
+        self.push_operation(|_segment, p, stack| {
+            unsafe { stack.drop::<T>(PADDING0) };
+            Ok(p)
+        });
+    }
+
+    /// Push an operation that pops the `T` on top of the stack and drops it, without a closure.
+    pub fn push_drop<T: 'static>(&mut self, padding0: bool) {
+        if padding0 {
+            self.push_drop_::<true, T>();
+        } else {
+            self.push_drop_::<false, T>();
+        }
+    }
+
     /// Pushes the op-dispatch closure for a binary infallible operation with compile-time padding.
     fn push_op2_<const PADDING0: bool, const PADDING1: bool, T, U, R, F>(&mut self)
     where
@@ -198,10 +607,10 @@ impl RawSegment {
         U: 'static,
         R: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let y: U = unsafe { stack.pop(PADDING1) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let y: U = unsafe { stack.pop_static::<PADDING1, U>() };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             stack.push(f(x, y));
             Ok(r)
         });
@@ -234,10 +643,10 @@ impl RawSegment {
         U: 'static,
         R: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let y: U = unsafe { stack.pop(PADDING1) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let y: U = unsafe { stack.pop_static::<PADDING1, U>() };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             let result = f(stack, x, y)?;
             stack.push(result);
             Ok(r)
@@ -274,11 +683,11 @@ impl RawSegment {
         V: 'static,
         R: 'static,
     {
-        self.ops.push(|storage, p, stack| {
-            let (f, r) = unsafe { storage.next::<F>(p) };
-            let z: V = unsafe { stack.pop(PADDING2) };
-            let y: U = unsafe { stack.pop(PADDING1) };
-            let x: T = unsafe { stack.pop(PADDING0) };
+        self.push_operation(|segment, p, stack| {
+            let (f, r) = unsafe { segment.storage.next::<F>(p) };
+            let z: V = unsafe { stack.pop_static::<PADDING2, V>() };
+            let y: U = unsafe { stack.pop_static::<PADDING1, U>() };
+            let x: T = unsafe { stack.pop_static::<PADDING0, T>() };
             stack.push(f(x, y, z));
             Ok(r)
         });
@@ -318,13 +727,71 @@ impl RawSegment {
     /// Precondition: The stack must be in the correct state (base alignment, index, and values)
     /// for the operations in the segment.
     pub unsafe fn call0_stack(&self, stack: &mut RawStack) -> Result<()> {
+        unsafe { self.call0_stack_indexed(stack) }.map_err(|(_, error)| error)
+    }
+
+    /// Executes all operations in the segment on the supplied stack, as
+    /// [`call0_stack`](Self::call0_stack), but pairs an error with the index of the op that
+    /// returned it.
+    ///
+    /// # Safety
+    ///
+    /// Precondition: as for [`call0_stack`](Self::call0_stack).
+    pub(crate) unsafe fn call0_stack_indexed(
+        &self,
+        stack: &mut RawStack,
+    ) -> std::result::Result<(), (usize, anyhow::Error)> {
+        let _env_guard = self.env.as_deref().map(EnvGuard::enter);
         let mut p = 0;
-        for op in &self.ops {
-            p = op(&self.storage, p, stack)?;
+        // Checked once per call, so unlimited execution pays nothing per op. Ops don't change the
+        // limits of the stack they run on.
+        if stack.limits().is_active() {
+            let limits = stack.limits().clone();
+            for (index, op) in self.ops.iter().enumerate() {
+                limits.consume_step().map_err(|error| (index, error))?;
+                p = op(self, p, stack).map_err(|error| (index, error))?;
+            }
+        } else {
+            for (index, op) in self.ops.iter().enumerate() {
+                p = op(self, p, stack).map_err(|error| (index, error))?;
+            }
         }
         Ok(())
     }
 
+    /// Executes all operations in the segment on a caller-provided `stack`, leaving whatever the
+    /// segment produces on the stack instead of popping a final result. Running several segments
+    /// in turn on one stack composes them.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error. The stack is left
+    /// in an unspecified state on error.
+    ///
+    /// # Safety
+    /// The stack must be in the correct state (base alignment, index, and values) for the
+    /// operations in the segment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{RawSegment, RawStack};
+    ///
+    /// let mut produce = RawSegment::new();
+    /// produce.push_op0(|| 20);
+    /// let mut consume = RawSegment::new();
+    /// consume.push_op1(|x: i32| x + 1, false);
+    ///
+    /// let mut stack = RawStack::with_base_alignment(align_of::<i32>());
+    /// unsafe {
+    ///     produce.run_on(&mut stack).unwrap();
+    ///     consume.run_on(&mut stack).unwrap();
+    ///     assert_eq!(stack.pop::<i32>(false), 21);
+    /// }
+    /// ```
+    pub unsafe fn run_on(&self, stack: &mut RawStack) -> Result<()> {
+        unsafe { self.call0_stack(stack) }
+    }
+
     /// Executes all operations in the segment and returns the final result.
     ///
     /// # Errors
@@ -344,6 +811,152 @@ impl RawSegment {
         Ok(unsafe { stack.pop(false) })
     }
 
+    /// Executes all operations in the segment twice, reusing one stack buffer, and returns both
+    /// results, e.g. to check that the segment is deterministic.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation of either run returns an error.
+    ///
+    /// # Safety
+    /// This function is unsafe if the result type does not match the type returned by the
+    /// operations in the segment or if the operations expect any initial values on the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawSegment;
+    ///
+    /// let mut segment = RawSegment::new();
+    /// segment.push_op0(|| 6);
+    /// segment.push_op1(|x: i32| x * 7, false);
+    /// let (first, second) = unsafe { segment.call0_twice::<i32>() }.unwrap();
+    /// assert_eq!(first, second);
+    /// ```
+    pub unsafe fn call0_twice<T>(&self) -> Result<(T, T)>
+    where
+        T: 'static,
+    {
+        let mut stack = RawStack::with_base_alignment(self.base_alignment);
+        let mut run = || -> Result<T> {
+            unsafe {
+                self.call0_stack(&mut stack)?;
+                Ok(stack.pop(false))
+            }
+        };
+        let first = run()?;
+        Ok((first, run()?))
+    }
+
+    /// Executes all operations in the segment on a stack allocated up front with room for
+    /// `max_bytes`, and returns the final result.
+    ///
+    /// - Postcondition: the stack does not reallocate if no op pushes it past `max_bytes`.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error.
+    ///
+    /// # Safety
+    /// This function is unsafe if the result type does not match the type returned by the
+    /// operations in the segment or if the operations expect any initial values on the stack.
+    pub unsafe fn call0_presized<T>(&self, max_bytes: usize) -> Result<T>
+    where
+        T: 'static,
+    {
+        let mut stack = RawStack::with_base_alignment_and_capacity(self.base_alignment, max_bytes);
+        unsafe {
+            self.call0_stack(&mut stack)?;
+        }
+        Ok(unsafe { stack.pop(false) })
+    }
+
+    /// Executes all operations in the segment, invoking at most `max_steps` ops, and returns the
+    /// final result.
+    ///
+    /// Every op invocation counts against the budget, including ops of sub-segments run from
+    /// within an op on its stack via [`RawSegment::run_on`] (e.g. the branches of a conditional).
+    /// A limited call made from within an op runs on its own stack under its own limits only.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error, or if more than
+    /// `max_steps` ops would be invoked.
+    ///
+    /// # Safety
+    /// This function is unsafe if the result type does not match the type returned by the
+    /// operations in the segment or if the operations expect any initial values on the stack.
+    ///
+    /// - Complexity: O(n) in the number of op invocations, at most `max_steps`.
+    pub unsafe fn call0_limited<T>(&self, max_steps: usize) -> Result<T>
+    where
+        T: 'static,
+    {
+        unsafe {
+            self.call0_with_limits(Limits {
+                steps: Some(Rc::new(Cell::new(max_steps))),
+                should_continue: None,
+            })
+        }
+    }
+
+    /// Executes all operations in the segment, checking `should_continue` before each op, and
+    /// returns the final result. This gives cooperative cancellation, e.g. comparing against a
+    /// wall-clock deadline or polling a cancellation flag.
+    ///
+    /// As with [`call0_limited`](Self::call0_limited), ops of sub-segments run from within an op
+    /// on its stack via [`RawSegment::run_on`] are checked too.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error, or if
+    /// `should_continue` returns `false`.
+    ///
+    /// # Safety
+    /// This function is unsafe if the result type does not match the type returned by the
+    /// operations in the segment or if the operations expect any initial values on the stack.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawSegment;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut segment = RawSegment::new();
+    /// segment.push_op0(|| 42);
+    /// let deadline = Instant::now() + Duration::from_secs(60);
+    /// let result =
+    ///     unsafe { segment.call0_with_deadline::<i32>(move || Instant::now() < deadline) };
+    /// assert_eq!(result.unwrap(), 42);
+    /// ```
+    pub unsafe fn call0_with_deadline<T>(
+        &self,
+        should_continue: impl Fn() -> bool + 'static,
+    ) -> Result<T>
+    where
+        T: 'static,
+    {
+        unsafe {
+            self.call0_with_limits(Limits {
+                steps: None,
+                should_continue: Some(Rc::new(should_continue)),
+            })
+        }
+    }
+
+    /// Executes all operations in the segment on a stack carrying `limits`, and returns the final
+    /// result.
+    ///
+    /// # Safety
+    /// As for [`call0`](Self::call0).
+    unsafe fn call0_with_limits<T>(&self, limits: Limits) -> Result<T>
+    where
+        T: 'static,
+    {
+        let mut stack = RawStack::with_base_alignment(self.base_alignment);
+        stack.set_limits(limits);
+        unsafe {
+            self.call0_stack(&mut stack)?;
+        }
+        Ok(unsafe { stack.pop(false) })
+    }
+
     /// Executes all operations in the segment with one argument of type A and returns the final
     /// result.
     ///
@@ -359,9 +972,8 @@ impl RawSegment {
     {
         let mut stack = RawStack::with_base_alignment(self.base_alignment);
         stack.push(arg);
-        let mut p = 0;
-        for op in &self.ops {
-            p = op(&self.storage, p, &mut stack)?;
+        unsafe {
+            self.call0_stack(&mut stack)?;
         }
         Ok(unsafe { stack.pop(false) })
     }
@@ -382,20 +994,103 @@ impl RawSegment {
         let mut stack = RawStack::with_base_alignment(self.base_alignment);
         stack.push(arg.0);
         stack.push(arg.1);
-        let mut p = 0;
-        for op in &self.ops {
-            p = op(&self.storage, p, &mut stack)?;
+        unsafe {
+            self.call0_stack(&mut stack)?;
         }
         Ok(unsafe { stack.pop(false) })
     }
+
+    /// Executes all operations in the segment with three arguments of types A, B, and C, pushed
+    /// in that order, and returns the final result.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error.
+    ///
+    /// # Safety
+    /// This function is unsafe if the arguments and result types do not match the types expected or
+    /// returned by the operations in the segment.
+    pub unsafe fn call3<A, B, C, T>(&self, arg: (A, B, C)) -> Result<T>
+    where
+        T: 'static,
+    {
+        let mut stack = RawStack::with_base_alignment(self.base_alignment);
+        stack.push(arg.0);
+        stack.push(arg.1);
+        stack.push(arg.2);
+        unsafe {
+            self.call0_stack(&mut stack)?;
+        }
+        Ok(unsafe { stack.pop(false) })
+    }
+
+    /// Executes all operations in the segment with four arguments of types A, B, C, and D, pushed
+    /// in that order, and returns the final result.
+    ///
+    /// # Errors
+    /// Halts execution and returns an error if any operation returns an error.
+    ///
+    /// # Safety
+    /// This function is unsafe if the arguments and result types do not match the types expected or
+    /// returned by the operations in the segment.
+    pub unsafe fn call4<A, B, C, D, T>(&self, arg: (A, B, C, D)) -> Result<T>
+    where
+        T: 'static,
+    {
+        let mut stack = RawStack::with_base_alignment(self.base_alignment);
+        stack.push(arg.0);
+        stack.push(arg.1);
+        stack.push(arg.2);
+        stack.push(arg.3);
+        unsafe {
+            self.call0_stack(&mut stack)?;
+        }
+        Ok(unsafe { stack.pop(false) })
+    }
+}
+
+/// Calls `drop_one` with each index in `0..count`. If a call panics, the remaining indices are
+/// still visited while unwinding, so one panicking destructor doesn't leak the rest; a second
+/// panic during that unwinding aborts, as for any panicking destructor.
+fn drop_each(count: usize, drop_one: impl FnMut(usize)) {
+    struct Guard<F: FnMut(usize)> {
+        next: usize,
+        count: usize,
+        drop_one: F,
+    }
+
+    impl<F: FnMut(usize)> Drop for Guard<F> {
+        fn drop(&mut self) {
+            while self.next < self.count {
+                let i = self.next;
+                self.next += 1;
+                (self.drop_one)(i);
+            }
+        }
+    }
+
+    let mut guard = Guard {
+        next: 0,
+        count,
+        drop_one,
+    };
+    while guard.next < guard.count {
+        let i = guard.next;
+        guard.next += 1;
+        (guard.drop_one)(i);
+    }
 }
 
 impl Drop for RawSegment {
     fn drop(&mut self) {
-        let mut p = 0;
-        for e in &self.dropper {
-            p = e(&mut self.storage, p);
-        }
+        let stored = self.dropper.len();
+        drop_each(stored + self.constant_table.len(), |i| {
+            if let Some(&(offset, dropper)) = self.dropper.get(i) {
+                dropper(&mut self.storage, offset);
+            } else {
+                let constant = &self.constant_table[i - stored];
+                unsafe { (constant.dropper)(&mut self.constants, constant.offset) };
+            }
+        });
     }
 }
 
@@ -403,6 +1098,172 @@ impl Drop for RawSegment {
 mod tests {
     use super::*;
 
+    #[test]
+    fn call3_and_call4_push_arguments_in_order() {
+        let mut segment = RawSegment::new();
+        segment.push_op2(|b: i32, c: i32| b - c, false, false);
+        segment.push_op2(|a: i32, bc: i32| a * 10 + bc, false, false);
+        assert_eq!(
+            unsafe { segment.call3::<i32, i32, i32, i32>((1, 5, 2)) }.unwrap(),
+            13
+        );
+
+        let mut segment = RawSegment::new();
+        segment.push_op2(|c: i64, d: i64| c - d, false, false);
+        segment.push_op2(|b: i64, cd: i64| b * 10 + cd, false, false);
+        segment.push_op2(|a: i64, bcd: i64| a * 100 + bcd, false, false);
+        assert_eq!(
+            unsafe { segment.call4::<i64, i64, i64, i64, i64>((1, 2, 5, 1)) }.unwrap(),
+            124
+        );
+    }
+
+    #[test]
+    fn rewind_truncates_ops_storage_and_alignment() {
+        let value = std::rc::Rc::new(());
+        let tracked = value.clone();
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 7u32);
+        segment.push_op0(|| 3u32);
+        let mark = segment.mark();
+        segment.push_op1(|x: u32| f64::from(x), false);
+        segment.push_op0(move || tracked.clone());
+        assert_eq!(segment.op_count(), 4);
+        assert_eq!(segment.base_alignment(), align_of::<f64>());
+
+        segment.rewind(mark);
+        assert_eq!(segment.op_count(), 2);
+        assert_eq!(segment.base_alignment(), align_of::<u32>());
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+        segment.push_op2(|a: u32, b: u32| a * b, false, false);
+        unsafe {
+            assert_eq!(segment.call0::<u32>().unwrap(), 21);
+        }
+    }
+
+    #[test]
+    fn op_records_stay_parallel_to_ops_through_rewind() {
+        let mut segment = RawSegment::new();
+        segment.push_label("seven");
+        segment.push_op0(|| 7u32);
+        segment.push_op0(|| 3u32);
+        let mark = segment.mark();
+        segment.push_label("product");
+        segment.push_op2(|a: u32, b: u32| a * b, false, false);
+        assert_eq!(segment.op_records().len(), segment.op_count());
+        assert_eq!(segment.op_records()[1].label, None);
+        assert_eq!(segment.op_labels(), ["seven", "product"]);
+
+        segment.rewind(mark);
+        assert_eq!(segment.op_records().len(), 2);
+        assert_eq!(segment.op_labels(), ["seven"]);
+    }
+
+    #[test]
+    fn push_drop_discards_top_value() {
+        let value = std::rc::Rc::new(());
+        let tracked = value.clone();
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 42u64);
+        segment.push_op0(move || tracked.clone());
+        segment.push_drop::<std::rc::Rc<()>>(false);
+        unsafe {
+            assert_eq!(segment.call0::<u64>().unwrap(), 42);
+        }
+        assert_eq!(std::rc::Rc::strong_count(&value), 2); // `value` and the op0 capture
+        drop(segment);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn push_indirect_pushes_constants_by_index() {
+        let mut segment = RawSegment::new();
+        let greeting = segment.push_constant(String::from("hello"));
+        let count = segment.push_constant(3usize);
+        segment.push_indirect::<String>(greeting).unwrap();
+        segment.push_indirect::<usize>(count).unwrap();
+        segment.push_op2(|s: String, n: usize| s.repeat(n), false, false);
+        segment.push_indirect::<usize>(count).unwrap();
+        segment.push_op2(|s: String, n: usize| s.len() + n, false, false);
+        unsafe {
+            assert_eq!(segment.call0::<usize>().unwrap(), 18);
+            assert_eq!(segment.call0::<usize>().unwrap(), 18);
+        }
+    }
+
+    #[test]
+    fn push_indirect_rejects_bad_index_and_type() {
+        let mut segment = RawSegment::new();
+        let index = segment.push_constant(1u8);
+        assert_eq!(
+            segment
+                .push_indirect::<u8>(index + 1)
+                .unwrap_err()
+                .to_string(),
+            "no constant at index 1"
+        );
+        assert_eq!(
+            segment.push_indirect::<u16>(index).unwrap_err().to_string(),
+            "constant 0 is not of type u16"
+        );
+    }
+
+    #[test]
+    fn panicking_destructor_does_not_leak_remaining_values() {
+        struct PanicOnDrop;
+        impl Drop for PanicOnDrop {
+            fn drop(&mut self) {
+                panic!("destructor panicked");
+            }
+        }
+
+        let value = std::rc::Rc::new(());
+        let mut segment = RawSegment::new();
+        let before = value.clone();
+        segment.push_op0(move || std::rc::Rc::strong_count(&before));
+        let panicker = PanicOnDrop;
+        segment.push_op0(move || {
+            let _ = &panicker;
+        });
+        let after = value.clone();
+        segment.push_op0(move || std::rc::Rc::strong_count(&after));
+        segment.push_constant(value.clone());
+        assert_eq!(std::rc::Rc::strong_count(&value), 4);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(segment)));
+        assert!(result.is_err());
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn constants_are_dropped_with_segment() {
+        let value = std::rc::Rc::new(());
+        let mut segment = RawSegment::new();
+        segment.push_constant(value.clone());
+        segment.push_constant(value.clone());
+        assert_eq!(std::rc::Rc::strong_count(&value), 3);
+        drop(segment);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn op0_env_reads_segment_environment() {
+        let mut segment = RawSegment::new();
+        segment.set_env(String::from("env"));
+        segment.push_op0_env(|env: &String| env.len());
+        unsafe {
+            assert_eq!(segment.call0::<usize>().unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn op0_env_without_environment_is_error() {
+        let mut segment = RawSegment::new();
+        segment.push_op0_env(|env: &u32| *env);
+        let err = unsafe { segment.call0::<u32>() }.unwrap_err();
+        assert_eq!(err.to_string(), "segment has no environment");
+    }
+
     #[test]
     fn nullary_operation() {
         let mut segment = RawSegment::new();
@@ -456,4 +1317,147 @@ mod tests {
             assert_eq!(segment.call0::<i32>().unwrap(), 25);
         }
     }
+
+    #[test]
+    fn call0_presized_matches_call0() {
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 10u8);
+        segment.push_op0(|| 5u64);
+        segment.push_op2(|x: u8, y: u64| u64::from(x) + y, false, true);
+        unsafe {
+            assert_eq!(segment.call0_presized::<u64>(16).unwrap(), 15);
+            assert_eq!(segment.call0_presized::<u64>(0).unwrap(), 15);
+        }
+    }
+
+    #[test]
+    fn call0_limited_within_budget() {
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 10);
+        segment.push_op1(|x: i32| x * 2, false);
+        unsafe {
+            assert_eq!(segment.call0_limited::<i32>(2).unwrap(), 20);
+        }
+    }
+
+    #[test]
+    fn run_on_composes_segments_on_one_stack() {
+        let mut first = RawSegment::new();
+        first.push_op0(|| 10);
+        first.push_op0(|| 5);
+        let mut second = RawSegment::new();
+        second.push_op2(|x: i32, y: i32| x - y, false, false);
+        second.push_op1(|x: i32| x * 3, false);
+
+        let mut stack = RawStack::with_base_alignment(first.base_alignment);
+        unsafe {
+            first.run_on(&mut stack).unwrap();
+            second.run_on(&mut stack).unwrap();
+            assert_eq!(stack.pop::<i32>(false), 15);
+        }
+    }
+
+    #[test]
+    fn call0_limited_counts_sub_segment_ops() {
+        // A `repeat`-style op that runs its body five times; 1 + 5 * 2 ops in total.
+        let mut body = RawSegment::new();
+        body.push_op1(|x: i32| x + 1, false);
+        body.push_op1(|x: i32| x * 2, false);
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 0);
+        segment.raw0_(move |stack| {
+            for _ in 0..5 {
+                unsafe { body.call0_stack(stack)? };
+            }
+            Ok(())
+        });
+        unsafe {
+            assert_eq!(segment.call0_limited::<i32>(12).unwrap(), 62);
+            let err = segment
+                .call0_limited::<i32>(11)
+                .expect_err("budget of 11 must be exceeded");
+            assert!(err.to_string().contains("step budget exceeded"));
+            // The budget does not outlive the limited call.
+            assert_eq!(segment.call0::<i32>().unwrap(), 62);
+        }
+    }
+
+    #[test]
+    fn limited_call_from_an_op_has_its_own_limits() {
+        let mut inner = RawSegment::new();
+        inner.push_op0(|| 1);
+        inner.push_op1(|x: i32| x + 1, false);
+        let inner = std::rc::Rc::new(inner);
+        let mut outer = RawSegment::new();
+        let nested = inner.clone();
+        outer.raw0(move |_stack| unsafe { nested.call0_limited::<i32>(2) });
+        unsafe {
+            // The inner ops run on their own stack, so only the outer op counts here.
+            assert_eq!(outer.call0_limited::<i32>(1).unwrap(), 2);
+        }
+
+        let mut outer = RawSegment::new();
+        outer.raw0(move |_stack| unsafe { inner.call0_limited::<i32>(1) });
+        let err = unsafe { outer.call0_limited::<i32>(10) }
+            .expect_err("the inner budget stops the inner segment");
+        assert!(format!("{err:#}").contains("step budget exceeded"));
+    }
+
+    #[test]
+    fn call0_twice_returns_both_runs() -> Result<()> {
+        let mut pure = RawSegment::new();
+        pure.push_op0(|| String::from("cel"));
+        pure.push_op1(|s: String| s.to_uppercase(), false);
+        let (first, second) = unsafe { pure.call0_twice::<String>()? };
+        assert_eq!(first, "CEL");
+        assert_eq!(first, second);
+
+        let counter = std::rc::Rc::new(Cell::new(0));
+        let mut impure = RawSegment::new();
+        let captured = counter.clone();
+        impure.push_op0(move || {
+            captured.set(captured.get() + 1);
+            captured.get()
+        });
+        let (first, second) = unsafe { impure.call0_twice::<i32>()? };
+        assert_ne!(first, second);
+        assert_eq!(counter.get(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn call0_with_deadline_aborts_once_predicate_fails() {
+        let mut body = RawSegment::new();
+        body.push_op1(|x: i32| x + 1, false);
+        let mut segment = RawSegment::new();
+        segment.push_op0(|| 0);
+        segment.raw0_(move |stack| {
+            for _ in 0..5 {
+                unsafe { body.call0_stack(stack)? };
+            }
+            Ok(())
+        });
+        let checks = std::rc::Rc::new(Cell::new(0));
+        let check_until = |limit| {
+            checks.set(0);
+            let checks = checks.clone();
+            move || {
+                checks.set(checks.get() + 1);
+                checks.get() <= limit
+            }
+        };
+        unsafe {
+            assert_eq!(
+                segment.call0_with_deadline::<i32>(check_until(7)).unwrap(),
+                5
+            );
+            let err = segment
+                .call0_with_deadline::<i32>(check_until(4))
+                .expect_err("predicate flips before the last op");
+            assert!(err.to_string().contains("deadline exceeded"));
+            assert_eq!(checks.get(), 5);
+            // The predicate does not outlive the call.
+            assert_eq!(segment.call0::<i32>().unwrap(), 5);
+        }
+    }
 }
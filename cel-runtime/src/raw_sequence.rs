@@ -7,7 +7,7 @@ use std::mem;
 /// The `RawSequence` provides a memory-efficient way to store heterogeneous values
 /// while maintaining proper alignment requirements for each type. It uses an
 /// internal buffer that aligns values according to their type's requirements,
-/// up to a maximum alignment of 4096 bytes.
+/// up to a maximum alignment of 4096 bytes by default (see [`RawSequence::with_alignment`]).
 pub struct RawSequence {
     buffer: RawVec,
 }
@@ -23,20 +23,55 @@ impl RawSequence {
     /// The sequence is initialized with a 4096-byte aligned buffer.
     #[must_use]
     pub fn new() -> Self {
+        Self::with_alignment(4096)
+    }
+
+    /// Creates a new empty `RawSequence` whose buffer is aligned to `max_align`, the largest
+    /// alignment of any type it can store.
+    ///
+    /// # Panics
+    /// Panics if `max_align` is not a power of two.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawSequence;
+    ///
+    /// let sequence = RawSequence::with_alignment(8192);
+    /// assert_eq!(sequence.max_alignment(), 8192);
+    /// ```
+    #[must_use]
+    pub fn with_alignment(max_align: usize) -> Self {
+        assert!(
+            max_align.is_power_of_two(),
+            "alignment {max_align} is not a power of two"
+        );
         RawSequence {
-            buffer: RawVec::with_base_alignment(4096),
+            buffer: RawVec::with_base_alignment(max_align),
         }
     }
 
-    /// Pushes a value onto the sequence.
+    /// Returns the largest alignment of any type the sequence can store.
+    #[must_use]
+    pub fn max_alignment(&self) -> usize {
+        self.buffer.base_alignment()
+    }
+
+    /// Pushes a value onto the sequence and returns the offset it was stored at.
     ///
     /// The value is stored at an address that satisfies its alignment requirements.
     /// Automatically grows the internal buffer if needed.
     ///
     /// # Panics
-    /// Panics if the type's alignment requirement exceeds 4096 bytes.
-    pub fn push<T>(&mut self, value: T) {
-        assert!(mem::align_of::<T>() <= 4096);
+    /// Panics if the type's alignment requirement exceeds [`max_alignment`](Self::max_alignment).
+    pub fn push<T>(&mut self, value: T) -> usize {
+        assert!(
+            mem::align_of::<T>() <= self.max_alignment(),
+            "alignment {} of `{}` exceeds the sequence's maximum alignment {}",
+            mem::align_of::<T>(),
+            std::any::type_name::<T>(),
+            self.max_alignment()
+        );
         let len = self.buffer.len();
         let aligned: usize = align_index(mem::align_of::<T>(), len);
         let new_len = aligned + mem::size_of::<T>();
@@ -46,6 +81,35 @@ impl RawSequence {
             self.buffer.set_len(new_len);
             std::ptr::write(self.buffer.as_mut_ptr().add(aligned).cast::<T>(), value);
         }
+        aligned
+    }
+
+    /// Moves every stored value up by `shift` bytes and stores `value` at offset 0, in front of
+    /// them.
+    ///
+    /// - Precondition: `shift` is at least `size_of::<T>()` and a multiple of the alignment of
+    ///   every stored value, so each keeps its alignment at its new offset.
+    /// - Complexity: O(n) in the length of the sequence.
+    ///
+    /// # Panics
+    /// Panics if the type's alignment requirement exceeds [`max_alignment`](Self::max_alignment).
+    pub fn push_front<T>(&mut self, value: T, shift: usize) {
+        assert!(
+            mem::align_of::<T>() <= self.max_alignment(),
+            "alignment {} of `{}` exceeds the sequence's maximum alignment {}",
+            mem::align_of::<T>(),
+            std::any::type_name::<T>(),
+            self.max_alignment()
+        );
+        debug_assert!(shift >= mem::size_of::<T>());
+        let len = self.buffer.len();
+        self.buffer.reserve(shift);
+        unsafe {
+            let ptr = self.buffer.as_mut_ptr();
+            std::ptr::copy(ptr, ptr.add(shift), len);
+            self.buffer.set_len(len + shift);
+            std::ptr::write(ptr.cast::<T>(), value);
+        }
     }
 
     /// Drops a value in-place at the specified position.
@@ -77,6 +141,15 @@ impl RawSequence {
         unsafe { (&*ptr, aligned + mem::size_of::<T>()) }
     }
 
+    /// Shortens the sequence to `len` bytes without dropping the values past it.
+    ///
+    /// # Safety
+    /// Every value stored past `len` must already have been dropped or moved out.
+    pub unsafe fn truncate(&mut self, len: usize) {
+        assert!(len <= self.buffer.len());
+        unsafe { self.buffer.set_len(len) };
+    }
+
     /// Returns the number of bytes in the RawSequence, also referred to as its ‘length’.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -123,4 +196,43 @@ mod tests {
         let p = unsafe { seq.drop_in_place::<f64>(p) };
         let _ = unsafe { seq.drop_in_place::<&str>(p) };
     }
+
+    #[test]
+    fn with_alignment_stores_over_aligned_values() {
+        #[repr(align(8192))]
+        struct Page(u8);
+
+        let mut seq = RawSequence::with_alignment(16384);
+        assert_eq!(seq.max_alignment(), 16384);
+        seq.push(1u8);
+        let p = seq.push(Page(7));
+        assert_eq!(p, 8192);
+        let (page, _) = unsafe { seq.next::<Page>(1) };
+        assert_eq!(page.0, 7);
+        assert!((page as *const Page as usize).is_multiple_of(8192));
+    }
+
+    #[test]
+    fn push_front_shifts_stored_values() {
+        let mut seq = RawSequence::new();
+        seq.push(1u8);
+        seq.push(2.5f64);
+        seq.push_front(7u16, 8);
+
+        let (value, p) = unsafe { seq.next::<u16>(0) };
+        assert_eq!((*value, p), (7, 2));
+        let (value, p) = unsafe { seq.next::<u8>(8) };
+        assert_eq!(*value, 1);
+        let (value, _) = unsafe { seq.next::<f64>(p) };
+        assert_eq!(*value, 2.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the sequence's maximum alignment 4096")]
+    fn push_over_aligned_value_panics() {
+        #[repr(align(8192))]
+        struct Page;
+
+        RawSequence::new().push(Page);
+    }
 }
@@ -1,13 +1,49 @@
+use crate::list_traits::{LayoutIterator, ListTypeIteratorAdvance};
 use crate::memory::align_index;
+use crate::raw_segment::Limits;
 use crate::raw_vec::RawVec;
+use std::alloc::Layout;
+use std::collections::TryReserveError;
+use std::fmt;
 use std::mem::MaybeUninit;
 use std::mem::size_of;
 
 /// A simple raw stack that stores values as raw bytes. Each value is naturally aligned given the
 /// base alignment of the stack, which is the maximum alignment of any value stored in the stack.
-#[derive(Debug)]
+///
+/// With the `debug-type-tags` feature, the stack also records the type of each pushed value, and
+/// popping a value as a different type panics instead of being undefined behavior.
 pub struct RawStack {
     buffer: RawVec,
+    /// The step budget and deadline that every op run on the stack is checked against.
+    limits: Limits,
+    /// One tag per value on the stack, bottom first, which pops check against the popped type.
+    #[cfg(feature = "debug-type-tags")]
+    tags: Vec<TypeTag>,
+}
+
+/// The byte range of a value on a [`RawStack`] and the name of its type, or `None` for bytes
+/// pushed without a type (by [`push_raw`](RawStack::push_raw) or
+/// [`repack`](RawStack::repack)). Names rather than `TypeId`s are recorded because values
+/// needn't be `'static`.
+#[cfg(feature = "debug-type-tags")]
+#[derive(Clone, Copy, Debug)]
+struct TypeTag {
+    start: usize,
+    end: usize,
+    type_name: Option<&'static str>,
+}
+
+/// Shows the stack's length, capacity, and base alignment. The contents are not shown: values may
+/// have uninitialized interior padding, and reading it to print would be undefined behavior.
+impl fmt::Debug for RawStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawStack")
+            .field("len", &self.buffer.len())
+            .field("capacity", &self.buffer.capacity())
+            .field("base_alignment", &self.buffer.base_alignment())
+            .finish()
+    }
 }
 
 impl RawStack {
@@ -23,9 +59,40 @@ impl RawStack {
     pub fn with_base_alignment(base_alignment: usize) -> Self {
         RawStack {
             buffer: RawVec::with_base_alignment(base_alignment),
+            limits: Limits::default(),
+            #[cfg(feature = "debug-type-tags")]
+            tags: Vec::new(),
         }
     }
 
+    /// Creates a new `RawStack` with base alignment and room for `capacity` bytes.
+    ///
+    /// - Postcondition: pushes totaling at most `capacity` bytes (including padding) do not
+    ///   reallocate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::raw_stack::RawStack;
+    /// let stack = RawStack::with_base_alignment_and_capacity(align_of::<u32>(), 16);
+    /// ```
+    #[must_use]
+    pub fn with_base_alignment_and_capacity(base_alignment: usize, capacity: usize) -> Self {
+        let mut stack = RawStack::with_base_alignment(base_alignment);
+        stack.buffer.reserve_exact(capacity);
+        stack
+    }
+
+    /// Returns the limits that ops run on the stack are checked against.
+    pub(crate) fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Replaces the limits that ops run on the stack are checked against.
+    pub(crate) fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
     /// Returns the number of bytes currently on the stack.
     #[must_use]
     #[allow(clippy::len_without_is_empty)]
@@ -38,6 +105,12 @@ impl RawStack {
     /// The value is stored as raw bytes in the internal buffer. The pushed value must be
     /// later popped using the correct type.
     ///
+    /// A zero-sized value stores no bytes, but is still aligned like any other value, inserting
+    /// padding if its alignment requires it, so the stack's layout matches a [`CStackList`]
+    /// of the same types.
+    ///
+    /// [`CStackList`]: crate::CStackList
+    ///
     /// # Type Parameters
     ///
     /// * `T`: The type of the value to push.
@@ -59,6 +132,86 @@ impl RawStack {
         let new_len = aligned_index + size_of::<T>();
 
         self.buffer.reserve(new_len - len);
+        unsafe { self.write_reserved(len, aligned_index, value) }
+    }
+
+    /// Pushes each of `values` onto the stack in order, as [`push`](Self::push) does, reserving
+    /// room for all of them up front. Returns whether padding was inserted before the first
+    /// value; the rest are never padded, since the size of `T` is a multiple of its alignment.
+    ///
+    /// Nothing records how many values were pushed; the caller pops them back as `T`, the first
+    /// with the returned padding flag and the rest with `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::raw_stack::RawStack;
+    /// let mut stack = RawStack::with_base_alignment(align_of::<u32>());
+    /// assert!(!stack.push_all(vec![1u32, 2, 3]));
+    /// assert_eq!(unsafe { stack.pop::<u32>(false) }, 3);
+    /// ```
+    ///
+    /// # Complexity
+    ///
+    /// O(n) in the number of values.
+    pub fn push_all<T>(&mut self, values: Vec<T>) -> bool {
+        let len = self.buffer.len();
+        let aligned_index = align_index(align_of::<T>(), len);
+        let new_len = aligned_index + size_of::<T>() * values.len();
+
+        self.buffer.reserve(new_len - len);
+        let mut values = values.into_iter();
+        let Some(first) = values.next() else {
+            return false;
+        };
+        let padded = self.push(first);
+        for value in values {
+            self.push(value);
+        }
+        padded
+    }
+
+    /// Pushes a value of type `T` onto the stack, as [`push`](Self::push) does, but returns an
+    /// error instead of panicking or aborting if the stack can't grow to hold it. On error, the
+    /// stack is unchanged and `value` is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stack's capacity would exceed `isize::MAX` bytes or the
+    /// allocation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::raw_stack::RawStack;
+    /// let mut stack = RawStack::with_base_alignment(align_of::<u32>());
+    /// assert_eq!(stack.try_push(42u32), Ok(false));
+    /// assert_eq!(unsafe { stack.pop::<u32>(false) }, 42);
+    /// ```
+    pub fn try_push<T>(&mut self, value: T) -> Result<bool, TryReserveError> {
+        let len = self.buffer.len();
+        let aligned_index = align_index(align_of::<T>(), len);
+        let new_len = aligned_index + size_of::<T>();
+
+        self.buffer.try_reserve(new_len - len)?;
+        Ok(unsafe { self.write_reserved(len, aligned_index, value) })
+    }
+
+    /// Writes `value` at `aligned_index`, marking the padding from `len`, and returns whether
+    /// padding was inserted.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be the current length, `aligned_index` the first index at or after it aligned
+    /// for `T`, and the capacity must be at least `aligned_index + size_of::<T>()`.
+    unsafe fn write_reserved<T>(&mut self, len: usize, aligned_index: usize, value: T) -> bool {
+        let new_len = aligned_index + size_of::<T>();
+        #[cfg(feature = "debug-type-tags")]
+        self.tags.push(TypeTag {
+            start: aligned_index,
+            end: new_len,
+            type_name: Some(std::any::type_name::<T>()),
+        });
         unsafe {
             self.buffer.set_len(new_len);
             if aligned_index - len > 0 {
@@ -67,14 +220,47 @@ impl RawStack {
                 self.buffer[len + 1..aligned_index].fill(MaybeUninit::new(0));
             }
 
-            std::ptr::write(
-                self.buffer.as_mut_ptr().add(aligned_index).cast::<T>(),
-                value,
-            );
+            if size_of::<T>() == 0 {
+                // A zero-sized value has no bytes to store, and an unallocated buffer's pointer
+                // may not be aligned for it; `pop` recreates it from a dangling pointer.
+                std::mem::forget(value);
+            } else {
+                std::ptr::write(
+                    self.buffer.as_mut_ptr().add(aligned_index).cast::<T>(),
+                    value,
+                );
+            }
         }
         aligned_index - len > 0
     }
 
+    /// Reserves capacity for pushing one value of each element type of `L`, in list order (head
+    /// first), including the padding each push would insert.
+    ///
+    /// - Postcondition: pushing those values, in that order, does not reallocate the stack.
+    ///
+    /// - Complexity: O(n) in the length of `L`, plus O(m) in the current length if the stack
+    ///   reallocates.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::*;
+    ///
+    /// let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+    /// stack.reserve_for::<<(u8, f64) as IntoList>::Output<CNil<()>>>();
+    /// ```
+    pub fn reserve_for<L>(&mut self)
+    where
+        L: ListTypeIteratorAdvance<Layout> + 'static,
+    {
+        let len = self.buffer.len();
+        let end = LayoutIterator::<L>::new().fold(len, |index, layout| {
+            align_index(layout.align(), index) + layout.size()
+        });
+        self.buffer.reserve(end - len);
+    }
+
     /// Pushes `size` raw bytes from `src`, aligned to `align`, using the same
     /// padding/marker-byte bookkeeping as [`push`](Self::push).
     ///
@@ -101,6 +287,12 @@ impl RawStack {
         let new_len = aligned_index + size;
 
         self.buffer.reserve(new_len - len);
+        #[cfg(feature = "debug-type-tags")]
+        self.tags.push(TypeTag {
+            start: aligned_index,
+            end: new_len,
+            type_name: None,
+        });
         unsafe {
             self.buffer.set_len(new_len);
             if aligned_index - len > 0 {
@@ -159,6 +351,33 @@ impl RawStack {
     /// # Safety
     /// No live (undropped) value may exist at or above `new_len`.
     pub unsafe fn truncate_to(&mut self, new_len: usize, padding: bool) {
+        #[cfg(feature = "debug-type-tags")]
+        self.retire_tags(new_len);
+        unsafe { self.strip_to(new_len, padding) };
+    }
+
+    /// Removes the tags of the values above `new_len`, or, if there are none, of the zero-sized
+    /// value on top at `new_len`, which a truncation to the current length discards.
+    #[cfg(feature = "debug-type-tags")]
+    fn retire_tags(&mut self, new_len: usize) {
+        let kept = self.tags.partition_point(|tag| tag.end <= new_len);
+        if kept < self.tags.len() {
+            self.tags.truncate(kept);
+        } else if self
+            .tags
+            .last()
+            .is_some_and(|tag| tag.start == new_len && tag.end == new_len)
+        {
+            self.tags.pop();
+        }
+    }
+
+    /// Truncates the bytes of the stack as [`truncate_to`](Self::truncate_to) does, without
+    /// touching the type tags.
+    ///
+    /// # Safety
+    /// As for [`truncate_to`](Self::truncate_to).
+    unsafe fn strip_to(&mut self, new_len: usize, padding: bool) {
         debug_assert!(new_len <= self.buffer.len());
         let padding_count = if padding {
             self.buffer[..new_len]
@@ -194,6 +413,26 @@ impl RawStack {
         }
     }
 
+    /// Drops every value described by `entries`, listed bottom to top, in reverse (LIFO) order.
+    /// Each entry is the `(size, padding, run_drop)` that [`drop_sized`](Self::drop_sized) takes
+    /// for one value.
+    ///
+    /// - Complexity: O(n) in the number of entries.
+    ///
+    /// # Safety
+    /// The top values of the stack must be live, valid values matching `entries` in order; each
+    /// `run_drop` must correctly run its value's destructor given a pointer to its start.
+    pub unsafe fn drop_all<I, F>(&mut self, entries: I)
+    where
+        I: IntoIterator<Item = (usize, bool, F)>,
+        I::IntoIter: DoubleEndedIterator,
+        F: FnOnce(*mut u8),
+    {
+        for (size, padding, run_drop) in entries.into_iter().rev() {
+            unsafe { self.drop_sized(size, padding, run_drop) };
+        }
+    }
+
     /// Repacks `sizes.len()` already-pushed values (currently at the absolute
     /// byte offsets in `src_offsets`) into one contiguous, self-contained
     /// region of `total_size` bytes starting at `dest_base`, placing element
@@ -231,6 +470,16 @@ impl RawStack {
         debug_assert_eq!(dest_offsets.len(), sizes.len());
 
         let target_len = dest_base + total_size;
+        #[cfg(feature = "debug-type-tags")]
+        {
+            let elements = self.tags.len().saturating_sub(sizes.len());
+            self.tags.truncate(elements);
+            self.tags.push(TypeTag {
+                start: dest_base,
+                end: target_len,
+                type_name: None,
+            });
+        }
         let current_len = self.buffer.len();
         let grown_len = current_len.max(target_len);
         unsafe {
@@ -279,23 +528,98 @@ impl RawStack {
     /// let value: u32 = unsafe { stack.pop(padding) };
     /// ```
     pub unsafe fn pop<T>(&mut self, padding: bool) -> T {
+        let result = unsafe { self.pop_unchecked_no_padding() };
+        if padding {
+            unsafe { self.strip_to(self.buffer.len(), true) };
+        }
+        result
+    }
+
+    /// Pops a value of type `T` that was pushed without padding, skipping the scan for padding
+    /// bytes that [`pop`](Self::pop) does when its `padding` is `true`.
+    ///
+    /// # Safety
+    ///
+    /// The type `T` must be the same type as the value on the top of the stack, and its
+    /// [`push`](Self::push) must have returned `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::raw_stack::RawStack;
+    /// let mut stack = RawStack::with_base_alignment(align_of::<u32>());
+    /// assert!(!stack.push(100u32));
+    /// let value: u32 = unsafe { stack.pop_unchecked_no_padding() };
+    /// assert_eq!(value, 100);
+    /// ```
+    #[inline]
+    pub unsafe fn pop_unchecked_no_padding<T>(&mut self) -> T {
+        debug_assert!(
+            self.buffer.len() >= size_of::<T>(),
+            "pop of {} ({} bytes) from a stack holding {} bytes",
+            std::any::type_name::<T>(),
+            size_of::<T>(),
+            self.buffer.len()
+        );
         let p: usize = self.buffer.len() - size_of::<T>();
-        let result = unsafe { std::ptr::read(self.buffer.as_ptr().add(p).cast::<T>()) };
-        // count the number of trailing 0s in the buffer before the result
-        let padding_count = if padding {
-            self.buffer[..p]
-                .iter()
-                .rev()
-                .take_while(|&x| unsafe { x.assume_init() == 0 })
-                .count()
-                + 1
+        #[cfg(feature = "debug-type-tags")]
+        self.check_tag::<T>(p);
+        let result = if size_of::<T>() == 0 {
+            // Safety: a dangling pointer is valid and aligned for reads of a zero-sized `T`.
+            unsafe { std::ptr::NonNull::<T>::dangling().as_ptr().read() }
         } else {
-            0
+            unsafe { std::ptr::read(self.buffer.as_ptr().add(p).cast::<T>()) }
         };
-        self.buffer.truncate(p - padding_count);
+        self.buffer.truncate(p);
         result
     }
 
+    /// Removes the tag of the value on top of the stack, panicking if it isn't a `T` starting at
+    /// `start`.
+    #[cfg(feature = "debug-type-tags")]
+    fn check_tag<T>(&mut self, start: usize) {
+        let popped = std::any::type_name::<T>();
+        // The head of an empty list is popped as a result without ever having been pushed.
+        if popped == std::any::type_name::<crate::list_traits::Undefined>() {
+            return;
+        }
+        let tag = self
+            .tags
+            .pop()
+            .unwrap_or_else(|| panic!("pop of `{popped}` from a stack with no tagged values"));
+        match tag.type_name {
+            Some(pushed) => assert!(
+                pushed == popped && tag.start == start,
+                "pop of `{popped}` at offset {start}, but the top of the stack holds `{pushed}` \
+                 at offset {}",
+                tag.start
+            ),
+            None => assert!(
+                tag.start == start && tag.end == self.buffer.len(),
+                "pop of `{popped}` ({} bytes), but the top of the stack holds an untyped {}-byte \
+                 value",
+                size_of::<T>(),
+                tag.end - tag.start
+            ),
+        }
+    }
+
+    /// Pops a value of type `T` whose padding is known at compile time, taking the
+    /// [`pop_unchecked_no_padding`](Self::pop_unchecked_no_padding) fast path when there is
+    /// none. Used by the ops [`RawSegment`](crate::RawSegment) generates for each padding.
+    ///
+    /// # Safety
+    ///
+    /// As for [`pop`](Self::pop) with `padding` set to `PADDING`.
+    #[inline]
+    pub(crate) unsafe fn pop_static<const PADDING: bool, T>(&mut self) -> T {
+        if PADDING {
+            unsafe { self.pop(true) }
+        } else {
+            unsafe { self.pop_unchecked_no_padding() }
+        }
+    }
+
     /// Pops a value of type `T` from the stack and drops it.
     ///
     /// # Safety
@@ -309,6 +633,90 @@ impl RawStack {
     pub unsafe fn drop<T>(&mut self, padding: bool) {
         unsafe { self.pop::<T>(padding) };
     }
+
+    /// Pops a tuple `L` of values in one call. The last element of `L` must be on top of the
+    /// stack, and `paddings[i]` must be the value [`push`](Self::push) returned for element `i`.
+    ///
+    /// # Safety
+    ///
+    /// The values on the top of the stack must be the elements of `L`, in order, pushed with
+    /// `paddings`. Incorrect usage can lead to undefined behavior.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `paddings` doesn't have one entry per element of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::raw_stack::RawStack;
+    /// let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+    /// let paddings = [stack.push(1.5f64), stack.push(7u32)];
+    /// let (x, n): (f64, u32) = unsafe { stack.pop_list(&paddings) };
+    /// assert_eq!((x, n), (1.5, 7));
+    /// ```
+    pub unsafe fn pop_list<L: PopList>(&mut self, paddings: &[bool]) -> L {
+        assert_eq!(
+            paddings.len(),
+            L::LENGTH,
+            "pop_list of {} values given {} paddings",
+            L::LENGTH,
+            paddings.len()
+        );
+        unsafe { L::pop_from(self, paddings) }
+    }
+}
+
+/// A tuple of values that [`RawStack::pop_list`] pops together, last element first.
+pub trait PopList: Sized {
+    /// The number of values in the tuple.
+    const LENGTH: usize;
+
+    /// Pops the elements of `Self` from `stack`, using `paddings[i]` for element `i`.
+    ///
+    /// # Safety
+    ///
+    /// See [`RawStack::pop_list`]; `paddings` has exactly `LENGTH` entries.
+    unsafe fn pop_from(stack: &mut RawStack, paddings: &[bool]) -> Self;
+}
+
+impl PopList for () {
+    const LENGTH: usize = 0;
+
+    unsafe fn pop_from(_stack: &mut RawStack, _paddings: &[bool]) -> Self {}
+}
+
+impl<A> PopList for (A,) {
+    const LENGTH: usize = 1;
+
+    unsafe fn pop_from(stack: &mut RawStack, paddings: &[bool]) -> Self {
+        unsafe { (stack.pop(paddings[0]),) }
+    }
+}
+
+impl<A, B> PopList for (A, B) {
+    const LENGTH: usize = 2;
+
+    unsafe fn pop_from(stack: &mut RawStack, paddings: &[bool]) -> Self {
+        unsafe {
+            let b = stack.pop(paddings[1]);
+            let a = stack.pop(paddings[0]);
+            (a, b)
+        }
+    }
+}
+
+impl<A, B, C> PopList for (A, B, C) {
+    const LENGTH: usize = 3;
+
+    unsafe fn pop_from(stack: &mut RawStack, paddings: &[bool]) -> Self {
+        unsafe {
+            let c = stack.pop(paddings[2]);
+            let b = stack.pop(paddings[1]);
+            let a = stack.pop(paddings[0]);
+            (a, b, c)
+        }
+    }
 }
 
 /* Test module */
@@ -317,6 +725,145 @@ mod tests {
     use super::*;
     use std::cmp::max;
 
+    #[test]
+    fn pop_list_pops_mixed_alignments() {
+        let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+        let paddings = [stack.push(2.25f64), stack.push(9u32)];
+        let (x, n): (f64, u32) = unsafe { stack.pop_list(&paddings) };
+        assert_eq!((x, n), (2.25, 9));
+        assert_eq!(stack.len(), 0);
+
+        let paddings = [stack.push(3u32), stack.push(-0.5f64)];
+        assert!(paddings[1]);
+        let (n, x): (u32, f64) = unsafe { stack.pop_list(&paddings) };
+        assert_eq!((n, x), (3, -0.5));
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn zero_sized_values_interleave_with_real_values() {
+        #[derive(Debug, PartialEq)]
+        struct Marker;
+
+        let mut empty = RawStack::with_base_alignment(align_of::<u64>());
+        assert!(!empty.push([0u64; 0]));
+        assert_eq!(empty.len(), 0);
+        assert_eq!(unsafe { empty.pop::<[u64; 0]>(false) }, []);
+        assert_eq!(empty.len(), 0);
+
+        let mut stack = RawStack::with_base_alignment(align_of::<u64>());
+        let paddings = [
+            stack.push(Marker),
+            stack.push(1u8),
+            stack.push([0u64; 0]),
+            stack.push(Marker),
+            stack.push(7u32),
+        ];
+        assert_eq!(paddings, [false, false, true, false, false]);
+        assert_eq!(stack.len(), 12);
+        unsafe {
+            assert_eq!(stack.pop::<u32>(paddings[4]), 7);
+            assert_eq!(stack.pop::<Marker>(paddings[3]), Marker);
+            assert_eq!(stack.pop::<[u64; 0]>(paddings[2]), []);
+            assert_eq!(stack.len(), 1);
+            assert_eq!(stack.pop::<u8>(paddings[1]), 1);
+            assert_eq!(stack.pop::<Marker>(paddings[0]), Marker);
+        }
+        assert_eq!(stack.len(), 0);
+    }
+
+    #[test]
+    fn pop_unchecked_no_padding_matches_pop_for_same_type_pipeline() {
+        let run = |pop: fn(&mut RawStack) -> u64| {
+            let mut stack = RawStack::with_base_alignment(align_of::<u64>());
+            let mut total = 0;
+            for round in 0..1_000u64 {
+                for i in 0..16 {
+                    assert!(!stack.push(round * 16 + i));
+                }
+                for _ in 0..16 {
+                    total += pop(&mut stack);
+                }
+                assert_eq!(stack.len(), 0);
+            }
+            total
+        };
+        let checked = run(|stack| unsafe { stack.pop(false) });
+        let fast = run(|stack| unsafe { stack.pop_unchecked_no_padding() });
+        assert_eq!(checked, (0..16_000).sum());
+        assert_eq!(fast, checked);
+    }
+
+    #[test]
+    fn try_push_pads_like_push() {
+        let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+        assert_eq!(stack.try_push(7u8), Ok(false));
+        assert_eq!(stack.try_push(2.5f64), Ok(true));
+        assert_eq!(stack.len(), 16);
+        let (n, x): (u8, f64) = unsafe { stack.pop_list(&[false, true]) };
+        assert_eq!((n, x), (7, 2.5));
+    }
+
+    #[test]
+    fn try_push_error_leaves_stack_unchanged_and_drops_value() {
+        // Aligning the buffer to the largest power of two needs more than `isize::MAX` bytes.
+        let mut stack = RawStack::with_base_alignment(1 << (usize::BITS - 1));
+        let value = std::rc::Rc::new(());
+        assert!(stack.try_push(value.clone()).is_err());
+        assert_eq!(stack.len(), 0);
+        assert_eq!(std::rc::Rc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn debug_shows_length_and_base_alignment() {
+        let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+        stack.push(1u8);
+        stack.push(2.0f64);
+        let debug = format!("{stack:?}");
+        assert!(debug.starts_with("RawStack { len: 16, "), "{debug}");
+        assert!(debug.ends_with("base_alignment: 8 }"), "{debug}");
+        unsafe {
+            stack.drop::<f64>(true);
+            stack.drop::<u8>(false);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "pop of u64 (8 bytes) from a stack holding 2 bytes")]
+    fn pop_too_large_type_asserts() {
+        let mut stack = RawStack::with_base_alignment(align_of::<u64>());
+        stack.push(7u16);
+        let _ = unsafe { stack.pop::<u64>(false) };
+    }
+
+    #[test]
+    fn drop_all_drops_in_lifo_order() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Tracked(u8, Rc<RefCell<Vec<u8>>>);
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.1.borrow_mut().push(self.0);
+            }
+        }
+
+        let dropped = Rc::new(RefCell::new(Vec::new()));
+        let mut stack = RawStack::with_base_alignment(align_of::<Tracked>());
+        let entries: Vec<_> = (1..=3)
+            .map(|i| {
+                let padding = stack.push(Tracked(i, dropped.clone()));
+                (size_of::<Tracked>(), padding, |ptr: *mut u8| unsafe {
+                    std::ptr::drop_in_place(ptr.cast::<Tracked>())
+                })
+            })
+            .collect();
+        unsafe { stack.drop_all(entries) };
+        assert_eq!(*dropped.borrow(), vec![3, 2, 1]);
+        assert_eq!(stack.len(), 0);
+    }
+
     #[test]
     fn push_pop_u32() {
         let mut stack = RawStack::with_base_alignment(align_of::<u32>());
@@ -350,6 +897,25 @@ mod tests {
         assert_eq!(value_u, 42);
     }
 
+    #[test]
+    fn reserve_for_avoids_reallocation_during_pushes() {
+        use crate::{CNil, IntoList};
+
+        let mut stack = RawStack::with_base_alignment(align_of::<f64>());
+        let _ = stack.push(1u16);
+        stack.reserve_for::<<(u8, u32, f64) as IntoList>::Output<CNil<()>>>();
+        let capacity = stack.buffer.capacity();
+        let ptr = unsafe { stack.buffer.as_ptr() };
+        let padding_u8 = stack.push(2u8);
+        let padding_u32 = stack.push(3u32);
+        let padding_f64 = stack.push(4.5f64);
+        assert_eq!(stack.buffer.capacity(), capacity);
+        assert_eq!(unsafe { stack.buffer.as_ptr() }, ptr);
+        assert_eq!(unsafe { stack.pop::<f64>(padding_f64) }, 4.5);
+        assert_eq!(unsafe { stack.pop::<u32>(padding_u32) }, 3);
+        assert_eq!(unsafe { stack.pop::<u8>(padding_u8) }, 2);
+    }
+
     #[test]
     fn len_reflects_pushed_bytes() {
         let mut stack = RawStack::with_base_alignment(align_of::<u32>());
@@ -358,6 +924,21 @@ mod tests {
         assert_eq!(stack.len(), size_of::<u32>());
     }
 
+    #[test]
+    fn push_all_pushes_values_in_order() {
+        let mut stack = RawStack::with_base_alignment(align_of::<u64>());
+        let _ = stack.push(1u8);
+        assert!(stack.push_all(vec![10u32, 20, 30]));
+        assert!(!stack.push_all(Vec::<u64>::new()));
+        unsafe {
+            assert_eq!(stack.pop::<u32>(false), 30);
+            assert_eq!(stack.pop::<u32>(false), 20);
+            assert_eq!(stack.pop::<u32>(true), 10);
+            assert_eq!(stack.pop::<u8>(false), 1);
+        }
+        assert_eq!(stack.len(), 0);
+    }
+
     #[test]
     fn push_raw_round_trips_like_push() {
         let mut stack = RawStack::with_base_alignment(align_of::<f64>());
@@ -578,4 +1159,27 @@ mod tests {
             "the u32's bytes must survive the repack uncorrupted"
         );
     }
+
+    #[cfg(feature = "debug-type-tags")]
+    #[test]
+    fn tagged_pop_of_pushed_type_succeeds() {
+        let mut stack = RawStack::with_base_alignment(align_of::<u64>());
+        let paddings = [stack.push(1u8), stack.push(2u64), stack.push(())];
+        unsafe {
+            stack.pop::<()>(paddings[2]);
+            assert_eq!(stack.pop::<u64>(paddings[1]), 2);
+            assert_eq!(stack.pop::<u8>(paddings[0]), 1);
+        }
+    }
+
+    #[cfg(feature = "debug-type-tags")]
+    #[test]
+    #[should_panic(
+        expected = "pop of `i32` at offset 0, but the top of the stack holds `u32` at offset 0"
+    )]
+    fn tagged_pop_of_other_type_panics() {
+        let mut stack = RawStack::with_base_alignment(align_of::<u32>());
+        let padding = stack.push(7u32);
+        let _ = unsafe { stack.pop::<i32>(padding) };
+    }
 }
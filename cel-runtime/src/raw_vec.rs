@@ -1,5 +1,7 @@
 use crate::memory::align_index;
+use std::alloc::{Layout, handle_alloc_error};
 use std::cmp::max;
+use std::collections::TryReserveError;
 use std::mem::MaybeUninit;
 use std::ops::{Index, IndexMut};
 use std::slice::SliceIndex;
@@ -60,11 +62,71 @@ impl RawVec {
         let ptr_as_index = buffer.as_ptr() as usize;
         let start_offset = align_index(base_alignment, ptr_as_index) - ptr_as_index;
         unsafe { buffer.set_len(start_offset) };
-        RawVec {
+        let result = RawVec {
             buffer,
             base_alignment,
             start_offset,
+        };
+        result.debug_check_invariants();
+        result
+    }
+
+    /// Creates a `RawVec` holding the bytes of `data`, adopting its allocation.
+    ///
+    /// If the allocation doesn't start at a multiple of `base_alignment`, the bytes are shifted up
+    /// within it to the first aligned address; they are copied to a new allocation only if the
+    /// existing one has too little spare capacity for that shift.
+    ///
+    /// - Precondition: `base_alignment` is a power of two.
+    /// - Postcondition: `len()` equals `data.len()` and the bytes are unchanged.
+    /// - Complexity: O(1) if the allocation is already aligned, otherwise O(n) in `data.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawVec;
+    ///
+    /// let vec = RawVec::from_bytes(7u32.to_ne_bytes().to_vec(), align_of::<u32>());
+    /// assert_eq!(unsafe { vec.as_ptr().cast::<u32>().read() }, 7);
+    /// ```
+    #[must_use]
+    pub fn from_bytes(data: Vec<u8>, base_alignment: usize) -> Self {
+        if data.capacity() == 0 {
+            return Self::with_base_alignment(base_alignment);
+        }
+        let len = data.len();
+        let mut data = std::mem::ManuallyDrop::new(data);
+        // Safety: `MaybeUninit<u8>` has the same size and alignment as `u8`, and `data` is not
+        // dropped, so the allocation is owned by `buffer` alone.
+        let mut buffer: Vec<MaybeUninit<u8>> =
+            unsafe { Vec::from_raw_parts(data.as_mut_ptr().cast(), len, data.capacity()) };
+        let ptr_as_index = buffer.as_ptr() as usize;
+        let start_offset = align_index(base_alignment, ptr_as_index) - ptr_as_index;
+        if start_offset + len > buffer.capacity() {
+            let mut result = Self::with_base_alignment_and_capacity(base_alignment, len);
+            unsafe {
+                std::ptr::copy_nonoverlapping(buffer.as_ptr(), result.as_mut_ptr(), len);
+                result.set_len(len);
+            }
+            return result;
         }
+        unsafe {
+            let ptr = buffer.as_mut_ptr();
+            std::ptr::copy(ptr, ptr.add(start_offset), len);
+            buffer.set_len(start_offset + len);
+        }
+        let result = RawVec {
+            buffer,
+            base_alignment,
+            start_offset,
+        };
+        result.debug_check_invariants();
+        result
+    }
+
+    /// Returns the alignment of the start of the vector.
+    pub(crate) fn base_alignment(&self) -> usize {
+        self.base_alignment
     }
 
     /// Returns the capacity of the vector in bytes.
@@ -92,19 +154,102 @@ impl RawVec {
     ///
     /// # Panics
     ///
-    /// Panics if the new capacity exceeds `isize::MAX bytes`.
+    /// Panics if the new capacity exceeds `isize::MAX bytes`, and aborts if the allocation fails.
     pub fn reserve(&mut self, addition: usize) {
-        let capacity = self.len() + addition;
+        if self.try_reserve(addition).is_err() {
+            reserve_failed(self.len().saturating_add(addition));
+        }
+    }
+
+    /// Reserves capacity for exactly `additional` more bytes, as [`reserve`](Self::reserve)
+    /// does but without doubling the capacity, for when the final size is known up front.
+    /// Repeated calls that each grow the vector take O(n) time apiece, so prefer
+    /// [`reserve`](Self::reserve) when growing incrementally.
+    ///
+    /// - Postcondition: `capacity()` is at least `len() + additional`; if the vector reallocated,
+    ///   it holds room for that many bytes and no more than the allocator rounds up to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity exceeds `isize::MAX bytes`, and aborts if the allocation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawVec;
+    ///
+    /// let mut vec = RawVec::with_base_alignment(align_of::<u32>());
+    /// vec.reserve_exact(12);
+    /// assert!(vec.capacity() >= 12);
+    /// ```
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let capacity = self.len() + additional;
         if capacity > self.capacity() {
-            let mut new_buffer = Self::with_base_alignment_and_capacity(
-                self.base_alignment,
-                max(capacity, self.capacity() * 2),
+            self.reallocate(capacity);
+        }
+        self.debug_check_invariants();
+    }
+
+    /// Moves the bytes into a new allocation with room for `capacity` bytes.
+    ///
+    /// - Precondition: `capacity >= self.len()`.
+    fn reallocate(&mut self, capacity: usize) {
+        if self.try_reallocate(capacity).is_err() {
+            reserve_failed(capacity.saturating_add(self.base_alignment - 1));
+        }
+    }
+
+    /// Moves the bytes into a new allocation with room for `capacity` bytes, as
+    /// [`reallocate`](Self::reallocate) does, returning an error instead if it can't be made.
+    ///
+    /// - Precondition: `capacity >= self.len()`.
+    /// - Postcondition: on error, the vector is unchanged.
+    fn try_reallocate(&mut self, capacity: usize) -> Result<(), TryReserveError> {
+        let mut buffer: Vec<MaybeUninit<u8>> = Vec::new();
+        buffer.try_reserve_exact(capacity.saturating_add(self.base_alignment - 1))?;
+        let ptr_as_index = buffer.as_ptr() as usize;
+        let start_offset = align_index(self.base_alignment, ptr_as_index) - ptr_as_index;
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                self.as_ptr(),
+                buffer.as_mut_ptr().add(start_offset),
+                self.len(),
             );
-            unsafe {
-                std::ptr::copy_nonoverlapping(self.as_ptr(), new_buffer.as_mut_ptr(), self.len());
-            }
-            *self = new_buffer;
+            buffer.set_len(start_offset + self.len());
         }
+        self.buffer = buffer;
+        self.start_offset = start_offset;
+        Ok(())
+    }
+
+    /// Tries to reserve capacity for at least `additional` more bytes, as
+    /// [`reserve`](Self::reserve) does, returning an error instead of panicking or aborting if
+    /// the capacity overflows or the allocator reports a failure.
+    ///
+    /// - Postcondition: on error, the vector is unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new capacity exceeds `isize::MAX` bytes or the allocation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::RawVec;
+    ///
+    /// let mut vec = RawVec::with_base_alignment(align_of::<u32>());
+    /// vec.try_reserve(16).unwrap();
+    /// assert!(vec.capacity() >= 16);
+    /// assert!(vec.try_reserve(usize::MAX).is_err());
+    /// ```
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        // Saturating to `usize::MAX` still fails below, since it exceeds `isize::MAX`.
+        let capacity = self.len().saturating_add(additional);
+        if capacity > self.capacity() {
+            self.try_reallocate(max(capacity, self.capacity() * 2))?;
+        }
+        self.debug_check_invariants();
+        Ok(())
     }
 
     /// Sets the length of the vector.
@@ -119,6 +264,7 @@ impl RawVec {
     pub unsafe fn set_len(&mut self, len: usize) {
         assert!(len <= self.capacity());
         unsafe { self.buffer.set_len(self.start_offset + len) };
+        self.debug_check_invariants();
     }
 
     /// Returns a raw mutable pointer to the vector's buffer, or a dangling raw pointer valid for
@@ -168,6 +314,39 @@ impl RawVec {
     pub fn truncate(&mut self, len: usize) {
         self.buffer.truncate(self.start_offset + len);
     }
+
+    /// Checks, in debug builds only, that an allocated buffer starts at an address aligned to
+    /// `base_alignment` and that `start_offset` skips fewer than `base_alignment` bytes to get
+    /// there. An unallocated buffer is exempt; its pointer is dangling.
+    #[inline]
+    fn debug_check_invariants(&self) {
+        debug_assert!(
+            self.base_alignment.is_power_of_two(),
+            "base alignment {} is not a power of two",
+            self.base_alignment
+        );
+        debug_assert!(
+            self.start_offset < self.base_alignment,
+            "start offset {} is not less than base alignment {}",
+            self.start_offset,
+            self.base_alignment
+        );
+        debug_assert!(
+            self.buffer.capacity() == 0
+                || unsafe { self.as_ptr() as usize }.is_multiple_of(self.base_alignment),
+            "buffer is not aligned to base alignment {}",
+            self.base_alignment
+        );
+    }
+}
+
+/// Reports a failure to allocate `size` bytes: panics if `size` exceeds `isize::MAX`, and
+/// otherwise aborts through [`handle_alloc_error`].
+fn reserve_failed(size: usize) -> ! {
+    match Layout::array::<u8>(size) {
+        Ok(layout) => handle_alloc_error(layout),
+        Err(_) => panic!("capacity overflow"),
+    }
 }
 
 /* Test module */
@@ -175,6 +354,25 @@ impl RawVec {
 mod tests {
     use super::*;
 
+    #[test]
+    fn try_reserve_reports_overflow_without_changing_vector() {
+        let mut vec = RawVec::with_base_alignment(align_of::<u64>());
+        vec.try_reserve(8).unwrap();
+        unsafe {
+            vec.as_mut_ptr().cast::<u64>().write(7);
+            vec.set_len(8);
+        }
+        let capacity = vec.capacity();
+        assert!(vec.try_reserve(usize::MAX - 4).is_err());
+        assert!(vec.try_reserve(isize::MAX as usize).is_err());
+        assert_eq!(vec.len(), 8);
+        assert_eq!(vec.capacity(), capacity);
+
+        vec.try_reserve(capacity).unwrap();
+        assert_eq!(unsafe { vec.as_ptr() } as usize % align_of::<u64>(), 0);
+        assert_eq!(unsafe { vec.as_ptr().cast::<u64>().read() }, 7);
+    }
+
     #[test]
     fn with_base_alignment() {
         let vec = RawVec::with_base_alignment(align_of::<u32>());
@@ -190,6 +388,24 @@ mod tests {
         assert_eq!(unsafe { vec.as_ptr() as usize } % align_of::<u32>(), 0);
     }
 
+    #[test]
+    fn from_bytes_reads_aligned_values() {
+        let values = [1u64, u64::MAX, 42];
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        let original = data.as_ptr() as usize;
+        let vec = RawVec::from_bytes(data, align_of::<u64>());
+        assert_eq!(vec.len(), 24);
+        let ptr = unsafe { vec.as_ptr() };
+        assert_eq!(ptr as usize % align_of::<u64>(), 0);
+        if original.is_multiple_of(align_of::<u64>()) {
+            assert_eq!(ptr as usize, original);
+        }
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(unsafe { ptr.cast::<u64>().add(i).read() }, *value);
+        }
+        assert!(RawVec::from_bytes(Vec::new(), align_of::<u64>()).is_empty());
+    }
+
     #[test]
     fn reserve() {
         let mut vec = RawVec::with_base_alignment(align_of::<u32>());
@@ -199,6 +415,44 @@ mod tests {
         assert_eq!(unsafe { vec.as_ptr() as usize } % align_of::<u32>(), 0);
     }
 
+    #[test]
+    fn reserve_keeps_length_and_contents_when_reallocating() {
+        let mut vec = RawVec::with_base_alignment(align_of::<u32>());
+        vec.reserve(4);
+        unsafe { vec.set_len(4) };
+        vec[0].write(7);
+        vec[3].write(9);
+        vec.reserve(vec.capacity());
+        assert_eq!(vec.len(), 4);
+        assert_eq!(
+            unsafe { (vec[0].assume_init(), vec[3].assume_init()) },
+            (7, 9)
+        );
+    }
+
+    #[test]
+    fn reserve_exact_does_not_double_capacity() {
+        let mut doubled = RawVec::with_base_alignment(align_of::<u32>());
+        let mut exact = RawVec::with_base_alignment(align_of::<u32>());
+        for vec in [&mut doubled, &mut exact] {
+            vec.reserve_exact(100);
+            unsafe { vec.set_len(100) };
+            vec[99].write(9);
+        }
+        // One byte past the current capacity, so both reallocate.
+        doubled.reserve(doubled.capacity() - 99);
+        exact.reserve_exact(exact.capacity() - 99);
+        assert!(doubled.capacity() >= 200);
+        assert!(
+            (101..200).contains(&exact.capacity()),
+            "{}",
+            exact.capacity()
+        );
+        assert_eq!(exact.len(), 100);
+        assert_eq!(unsafe { exact[99].assume_init() }, 9);
+        assert_eq!(unsafe { exact.as_ptr() as usize } % align_of::<u32>(), 0);
+    }
+
     #[test]
     fn set_len() {
         let mut vec = RawVec::with_base_alignment_and_capacity(align_of::<u32>(), 10);
@@ -207,6 +461,18 @@ mod tests {
         assert_eq!(unsafe { vec.as_ptr() as usize } % align_of::<u32>(), 0);
     }
 
+    #[cfg(debug_assertions)]
+    #[test]
+    fn invariants_hold_after_growth() {
+        let mut vec = RawVec::with_base_alignment(align_of::<u128>());
+        for len in [1, 17, 64, 1000] {
+            vec.reserve(len - vec.len());
+            unsafe { vec.set_len(len) };
+            vec.debug_check_invariants();
+            assert_eq!(unsafe { vec.as_ptr() as usize } % align_of::<u128>(), 0);
+        }
+    }
+
     #[test]
     fn index() {
         let mut vec = RawVec::with_base_alignment_and_capacity(align_of::<u32>(), 10);
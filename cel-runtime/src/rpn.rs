@@ -0,0 +1,122 @@
+//! A frontend that compiles reverse-Polish (postfix) token lists directly into a [`DynSegment`],
+//! for callers that already have RPN from another source and don't need the CEL grammar.
+//!
+//! Arithmetic is on `i64` and fails at execution on overflow or division by zero, like the
+//! CEL parser's default integer arithmetic.
+
+use crate::dyn_segment::DynSegment;
+use crate::ops;
+use anyhow::{Result, anyhow, ensure};
+
+/// One token of a reverse-Polish program for [`DynSegment::from_rpn`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpnToken {
+    /// Pushes an integer.
+    PushInt(i64),
+    /// Pops `b`, then `a`, and pushes `a + b`.
+    Add,
+    /// Pops `b`, then `a`, and pushes `a - b`.
+    Sub,
+    /// Pops `b`, then `a`, and pushes `a * b`.
+    Mul,
+    /// Pops `b`, then `a`, and pushes `a / b`, truncated toward zero.
+    Div,
+}
+
+impl DynSegment {
+    /// Compiles `tokens`, a reverse-Polish program, into a segment taking no arguments and
+    /// producing the one value the program leaves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an operator has fewer than two operands, or if the program doesn't
+    /// leave exactly one value. The returned segment fails when called if an operation
+    /// overflows or divides by zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cel_runtime::{DynSegment, RpnToken::*};
+    ///
+    /// let mut segment = DynSegment::from_rpn(&[PushInt(3), PushInt(4), Add, PushInt(2), Mul])?;
+    /// assert_eq!(segment.call0::<i64>()?, 14);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn from_rpn(tokens: &[RpnToken]) -> Result<DynSegment> {
+        let mut segment = DynSegment::new::<()>();
+        let mut depth = 0usize;
+        for (index, token) in tokens.iter().enumerate() {
+            let op = match *token {
+                RpnToken::PushInt(value) => {
+                    segment.op0(move || value);
+                    depth += 1;
+                    continue;
+                }
+                RpnToken::Add => ops::checked_add::<i64>(),
+                RpnToken::Sub => ops::checked_sub::<i64>(),
+                RpnToken::Mul => ops::checked_mul::<i64>(),
+                RpnToken::Div => |a: i64, b: i64| {
+                    a.checked_div(b).ok_or_else(|| {
+                        anyhow!(if b == 0 {
+                            "division by zero"
+                        } else {
+                            "arithmetic overflow"
+                        })
+                    })
+                },
+            };
+            ensure!(
+                depth >= 2,
+                "`{token:?}` at token {index} needs two operands, but the stack holds {depth}"
+            );
+            segment.op2r(op)?;
+            depth -= 1;
+        }
+        ensure!(
+            depth == 1,
+            "program must leave exactly 1 value, but leaves {depth}"
+        );
+        Ok(segment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpnToken::*;
+    use super::*;
+
+    #[test]
+    fn from_rpn_evaluates_program() -> Result<()> {
+        let mut segment = DynSegment::from_rpn(&[PushInt(3), PushInt(4), Add])?;
+        assert_eq!(segment.call0::<i64>()?, 7);
+
+        let mut segment = DynSegment::from_rpn(&[PushInt(10), PushInt(4), PushInt(1), Sub, Div])?;
+        assert_eq!(segment.call0::<i64>()?, 3);
+
+        let mut segment = DynSegment::from_rpn(&[PushInt(1), PushInt(0), Div])?;
+        assert_eq!(
+            segment.call0::<i64>().unwrap_err().root_cause().to_string(),
+            "division by zero"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn from_rpn_rejects_malformed_program() {
+        assert_eq!(
+            DynSegment::from_rpn(&[PushInt(3), Add])
+                .err()
+                .unwrap()
+                .to_string(),
+            "`Add` at token 1 needs two operands, but the stack holds 1"
+        );
+        assert_eq!(
+            DynSegment::from_rpn(&[PushInt(3), PushInt(4)])
+                .err()
+                .unwrap()
+                .to_string(),
+            "program must leave exactly 1 value, but leaves 2"
+        );
+        assert!(DynSegment::from_rpn(&[]).is_err());
+    }
+}
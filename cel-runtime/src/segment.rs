@@ -25,6 +25,98 @@ impl<H: 'static, T: DropStack + CStackListHeadLimit> DropStack for CStackList<H,
     }
 }
 
+/// Pushes ops onto a [`RawSegment`] that drop the values of a type-level stack.
+pub trait DiscardStack: List {
+    /// Pushes one drop op per value of `Self`, top value first.
+    fn push_discards(segment: &mut RawSegment);
+}
+
+impl DiscardStack for CNil<()> {
+    fn push_discards(_segment: &mut RawSegment) {}
+}
+
+impl<H: 'static, T: DiscardStack + CStackListHeadLimit> DiscardStack for CStackList<H, T> {
+    fn push_discards(segment: &mut RawSegment) {
+        segment.push_drop::<H>(Self::HEAD_PADDED);
+        T::push_discards(segment);
+    }
+}
+
+/// Pushes a segment's arguments onto a [`RawStack`], in argument order, the way the
+/// [`Callable`] impls do.
+pub trait PushArgs {
+    /// Pushes each element of `self` onto `stack`.
+    fn push_args(self, stack: &mut RawStack);
+}
+
+impl PushArgs for () {
+    fn push_args(self, _stack: &mut RawStack) {}
+}
+
+impl<A> PushArgs for (A,) {
+    fn push_args(self, stack: &mut RawStack) {
+        stack.push(self.0);
+    }
+}
+
+impl<A, B> PushArgs for (A, B) {
+    fn push_args(self, stack: &mut RawStack) {
+        stack.push(self.0);
+        stack.push(self.1);
+    }
+}
+
+/// A type-level stack of two or three values that [`Segment::call_tuple`] returns as a tuple,
+/// bottom value first.
+pub trait TupleResult: List {
+    /// The tuple of the stack's values, bottom first.
+    type Output;
+
+    /// Pops the values of `Self` from `stack` into a tuple.
+    ///
+    /// # Safety
+    /// `stack` must hold exactly the values described by `Self`, as pushed by a segment.
+    unsafe fn pop_tuple(stack: &mut RawStack) -> Self::Output;
+}
+
+impl<A: 'static, B: 'static> TupleResult for CStackList<B, CStackList<A, CNil<()>>> {
+    type Output = (A, B);
+
+    unsafe fn pop_tuple(stack: &mut RawStack) -> Self::Output {
+        unsafe { stack.pop_list(&[false, Self::HEAD_PADDED]) }
+    }
+}
+
+impl<A: 'static, B: 'static, C: 'static> TupleResult
+    for CStackList<C, CStackList<B, CStackList<A, CNil<()>>>>
+{
+    type Output = (A, B, C);
+
+    unsafe fn pop_tuple(stack: &mut RawStack) -> Self::Output {
+        unsafe {
+            stack.pop_list(&[
+                false,
+                CStackList::<B, CStackList<A, CNil<()>>>::HEAD_PADDED,
+                Self::HEAD_PADDED,
+            ])
+        }
+    }
+}
+
+/// Implemented only when `Self` and `T` are the same type, so it can be used as a `where` bound
+/// to assert type equality at compile time.
+pub trait SameList<T> {}
+
+impl<T> SameList<T> for T {}
+
+/// Marks a [`Segment`] built only from infallible ops (`op0`, `op0_env`, `op1`, `op2`), which
+/// can be executed with [`Segment::call_infallible`].
+pub struct Pure;
+
+/// Marks a [`Segment`] that may contain fallible ops (`op0r`, `op1r`) and so can only be executed
+/// with [`Callable::call`].
+pub struct Fallible;
+
 /// A type-safe segment that represents a sequence of operations.
 ///
 /// The segment takes input arguments of type `Args` and maintains a type stack `Stack` that tracks
@@ -33,6 +125,8 @@ impl<H: 'static, T: DropStack + CStackListHeadLimit> DropStack for CStackList<H,
 /// # Type Parameters
 /// - `Args`: The input argument types, must implement `IntoList`
 /// - `Stack`: The type stack tracking operation results, defaults to the reverse of `Args`
+/// - `Env`: The environment read by [`op0_env`](Segment::op0_env) ops, defaults to `()`
+/// - `Fallibility`: [`Pure`] until a fallible op is pushed, then [`Fallible`]
 ///
 /// # Examples
 ///
@@ -50,9 +144,19 @@ impl<H: 'static, T: DropStack + CStackListHeadLimit> DropStack for CStackList<H,
 pub struct Segment<
     Args: IntoList + 'static,
     Stack: List = ReverseList<<Args as IntoList>::Output<CNil<()>>>,
+    Env: 'static = (),
+    Fallibility: 'static = Pure,
 > {
     segment: RawSegment,
-    _phantom: std::marker::PhantomData<(Args, Stack)>,
+    _phantom: std::marker::PhantomData<(Args, Stack, Env, Fallibility)>,
+}
+
+/// A segment owns its ops and buffers through heap pointers and never hands out references into
+/// itself, so it can be moved freely, e.g. across `.await` points in an async state machine,
+/// whatever its phantom `Args`, `Stack`, `Env`, or `Fallibility` types are.
+impl<Args: IntoList + 'static, Stack: List, Env: 'static, Fallibility: 'static> Unpin
+    for Segment<Args, Stack, Env, Fallibility>
+{
 }
 
 impl<Args: IntoList + 'static> Default for Segment<Args> {
@@ -62,23 +166,77 @@ impl<Args: IntoList + 'static> Default for Segment<Args> {
 }
 
 impl<Args: IntoList + 'static> Segment<Args> {
-    /// Creates a new empty segment with no operations.
+    /// Creates a new empty segment with no operations and the unit environment.
     #[must_use]
     pub fn new() -> Segment<Args> {
+        Self::with_env(())
+    }
+
+    /// Creates a new empty segment that owns `env`, an environment (e.g. configuration or a
+    /// clock) that ops pushed with [`op0_env`](Segment::op0_env) borrow when executed.
+    ///
+    /// The environment is stored once in the segment rather than captured by each op.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// struct Config {
+    ///     scale: i32,
+    /// }
+    ///
+    /// let segment = Segment::<()>::with_env(Config { scale: 3 })
+    ///     .op0_env(|config| config.scale)
+    ///     .op1(|x| x * 2);
+    /// assert_eq!(segment.call(()).unwrap(), 6);
+    /// ```
+    #[must_use]
+    pub fn with_env<E: 'static>(env: E) -> Segment<Args, ReverseList<Args::Output<CNil<()>>>, E> {
+        let mut segment = RawSegment::new();
+        segment.set_env(env);
         Segment {
-            segment: RawSegment::new(),
+            segment,
             _phantom: std::marker::PhantomData,
         }
     }
 }
 
-impl<Args: IntoList + 'static, Stack: List + 'static> TryFrom<DynSegment> for Segment<Args, Stack>
+impl<Args: IntoList + 'static, Stack: List, Env: 'static, Fallibility: 'static>
+    Segment<Args, Stack, Env, Fallibility>
+where
+    <Args as IntoList>::Output<CNil<()>>: ListTypeIteratorAdvance<TypeId>,
+{
+    /// Returns the `TypeId` of each argument, in argument order, e.g. to validate external
+    /// input before calling the segment.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    /// use std::any::TypeId;
+    ///
+    /// let segment = Segment::<(i32, bool)>::new();
+    /// assert_eq!(
+    ///     segment.argument_type_ids(),
+    ///     [TypeId::of::<i32>(), TypeId::of::<bool>()]
+    /// );
+    /// ```
+    #[must_use]
+    pub fn argument_type_ids(&self) -> Vec<TypeId> {
+        // `Args::Output` lists the first argument at its head; the stack order reverses it.
+        TypeIdIterator::<<Args as IntoList>::Output<CNil<()>>>::new().collect()
+    }
+}
+
+impl<Args: IntoList + 'static, Stack: List + 'static, Fallibility: 'static>
+    Segment<Args, Stack, (), Fallibility>
 where
     ReverseList<<Args as IntoList>::Output<CNil<()>>>: ListTypeIteratorAdvance<TypeId>,
     Stack: ListTypeIteratorAdvance<TypeId>,
 {
-    type Error = anyhow::Error;
-    fn try_from(value: DynSegment) -> Result<Self, Self::Error> {
+    /// Converts `value` after checking its argument and stack types against `Args` and `Stack`.
+    fn from_dyn_segment(value: DynSegment) -> Result<Self> {
         type ArgList<Args> = ReverseList<<Args as IntoList>::Output<CNil<()>>>;
 
         ensure!(
@@ -99,13 +257,49 @@ where
     }
 }
 
-impl<Args: IntoList + 'static, Stack> Segment<Args, Stack>
+impl<Args: IntoList + 'static, Stack: List + 'static> TryFrom<DynSegment> for Segment<Args, Stack>
+where
+    ReverseList<<Args as IntoList>::Output<CNil<()>>>: ListTypeIteratorAdvance<TypeId>,
+    Stack: ListTypeIteratorAdvance<TypeId>,
+{
+    type Error = anyhow::Error;
+    fn try_from(value: DynSegment) -> Result<Self, Self::Error> {
+        ensure!(
+            !value.is_fallible(),
+            "segment has fallible ops; convert it to a `Fallible` segment"
+        );
+        Self::from_dyn_segment(value)
+    }
+}
+
+impl<Args: IntoList + 'static, Stack: List + 'static> TryFrom<DynSegment>
+    for Segment<Args, Stack, (), Fallible>
+where
+    ReverseList<<Args as IntoList>::Output<CNil<()>>>: ListTypeIteratorAdvance<TypeId>,
+    Stack: ListTypeIteratorAdvance<TypeId>,
+{
+    type Error = anyhow::Error;
+    fn try_from(value: DynSegment) -> Result<Self, Self::Error> {
+        Self::from_dyn_segment(value)
+    }
+}
+
+impl<Args: IntoList + 'static, Stack, Env: 'static, Fallibility: 'static>
+    Segment<Args, Stack, Env, Fallibility>
 where
     Stack: DropStack + 'static,
     Stack::Tail: CStackListHeadLimit,
 {
     /// Private method to change the stack type.
-    fn into<NewStack: List + 'static>(self) -> Segment<Args, NewStack> {
+    fn into<NewStack: List + 'static>(self) -> Segment<Args, NewStack, Env, Fallibility> {
+        Segment {
+            segment: self.segment,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Private method to change the stack type and mark the segment [`Fallible`].
+    fn into_fallible<NewStack: List + 'static>(self) -> Segment<Args, NewStack, Env, Fallible> {
         Segment {
             segment: self.segment,
             _phantom: std::marker::PhantomData,
@@ -118,7 +312,7 @@ where
     } */
 
     /// Pushes a nullary operation that takes no arguments and returns a value of type R.
-    pub fn op0<R, F>(mut self, op: F) -> Segment<Args, Stack::Push<R>>
+    pub fn op0<R, F>(mut self, op: F) -> Segment<Args, Stack::Push<R>, Env, Fallibility>
     where
         F: Fn() -> R + 'static,
         R: 'static,
@@ -127,19 +321,30 @@ where
         self.into()
     }
 
+    /// Pushes a nullary operation that borrows the segment's environment and returns a value of
+    /// type R. See [`Segment::with_env`].
+    pub fn op0_env<R, F>(mut self, op: F) -> Segment<Args, Stack::Push<R>, Env, Fallibility>
+    where
+        F: Fn(&Env) -> R + 'static,
+        R: 'static,
+    {
+        self.segment.push_op0_env(op);
+        self.into()
+    }
+
     /// Pushes a fallible nullary operation that returns `Result<R>`.
-    pub fn op0r<R, F>(mut self, op: F) -> Segment<Args, Stack::Push<R>>
+    pub fn op0r<R, F>(mut self, op: F) -> Segment<Args, Stack::Push<R>, Env, Fallible>
     where
         F: Fn() -> Result<R> + 'static,
         R: 'static,
     {
         self.segment
             .raw0(move |stack| op().inspect_err(|_| Stack::drop_stack(stack)));
-        self.into()
+        self.into_fallible()
     }
 
     /// Pushes a unary operation that takes the current stack value and returns a new value.
-    pub fn op1<R, F>(mut self, op: F) -> Segment<Args, CStackList<R, Stack::Tail>>
+    pub fn op1<R, F>(mut self, op: F) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallibility>
     where
         Stack: CStackListHeadPadded,
         F: Fn(Stack::Head) -> R + 'static,
@@ -150,23 +355,25 @@ where
     }
 
     /// Pushes a fallible unary operation that takes the current stack value and returns `Result<R>`.
-    pub fn op1r<R, F>(mut self, op: F) -> Segment<Args, CStackList<R, Stack::Tail>>
+    pub fn op1r<R, F>(mut self, op: F) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallible>
     where
         Stack: CStackListHeadPadded,
+        Stack::Tail: DropStack,
         F: Fn(Stack::Head) -> Result<R> + 'static,
         R: 'static,
     {
+        // The head was popped as the argument; only the values below it remain to unwind.
         self.segment.raw1(
-            move |stack, x| op(x).inspect_err(|_| Stack::drop_stack(stack)),
+            move |stack, x| op(x).inspect_err(|_| Stack::Tail::drop_stack(stack)),
             Stack::HEAD_PADDED,
         );
-        self.into()
+        self.into_fallible()
     }
     /// Pushes a binary operation that takes the top two stack values and returns a new value.
     pub fn op2<R, F>(
         mut self,
         op: F,
-    ) -> Segment<Args, <<Stack::Tail as List>::Tail as List>::Push<R>>
+    ) -> Segment<Args, <<Stack::Tail as List>::Tail as List>::Push<R>, Env, Fallibility>
     where
         Stack: CStackListHeadPadded,
         Stack::Tail: CStackListHeadPadded,
@@ -181,18 +388,336 @@ where
         self.into()
     }
 
+    /// Composes `other`, a single-argument segment taking this segment's result, onto this
+    /// segment: the head of the stack is replaced with `other`'s result.
+    ///
+    /// The argument type is matched at compile time. `other` runs with its own stack and
+    /// environment, and the composed segment is [`Fallible`] since `other` may fail.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let double = Segment::<(i32,)>::new().op1(|x| x * 2);
+    /// let segment = Segment::<()>::new().op0(|| 21).then(double);
+    /// assert_eq!(segment.call(()).unwrap(), 42);
+    /// ```
+    pub fn then<R, S>(self, other: S) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallible>
+    where
+        Stack: CStackListHeadPadded,
+        Stack::Tail: DropStack,
+        S: Callable<(Stack::Head,), Output = Result<R>> + 'static,
+        R: 'static,
+    {
+        self.op1r(move |x| other.call((x,)))
+    }
+
+    /// Maps the segment's result with `f`; the same as a final [`op1`](Self::op1), but only
+    /// available once the stack holds just the result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32,)>::new()
+    ///     .op1(|x| x * 2)
+    ///     .map(|x: i32| format!("<{x}>"));
+    /// assert_eq!(segment.call((21,)).unwrap(), "<42>");
+    /// ```
+    pub fn map<R, F>(self, f: F) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallibility>
+    where
+        Stack: CStackListHeadPadded,
+        Stack::Tail: EmptyList,
+        F: Fn(Stack::Head) -> R + 'static,
+        R: 'static,
+    {
+        self.op1(f)
+    }
+
+    /// Maps the segment's result with the fallible `f`; the same as a final
+    /// [`op1r`](Self::op1r), but only available once the stack holds just the result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32,)>::new()
+    ///     .try_map(|x: i32| u8::try_from(x).map_err(|_| anyhow::anyhow!("`{x}` out of range")));
+    /// assert_eq!(segment.call((42,)).unwrap(), 42u8);
+    /// assert!(segment.call((-1,)).is_err());
+    /// ```
+    pub fn try_map<R, F>(self, f: F) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallible>
+    where
+        Stack: CStackListHeadPadded,
+        Stack::Tail: DropStack + EmptyList,
+        F: Fn(Stack::Head) -> Result<R> + 'static,
+        R: 'static,
+    {
+        self.op1r(f)
+    }
+
+    /// Drops every value left on the stack, top first, and pushes `()` as the result, for a
+    /// segment run only for its side effects.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32,)>::new()
+    ///     .op0(|| "unused")
+    ///     .discard_to_unit();
+    /// assert_eq!(segment.call((42,)).unwrap(), ());
+    /// ```
+    pub fn discard_to_unit(mut self) -> Segment<Args, CStackList<(), CNil<()>>, Env, Fallibility>
+    where
+        Stack: DiscardStack,
+    {
+        Stack::push_discards(&mut self.segment);
+        self.segment.push_op0(|| ());
+        self.into()
+    }
+
+    /// Like [`op0`](Self::op0), but records `label` for [`op_labels`](Self::op_labels).
+    pub fn op0_labeled<R, F>(
+        mut self,
+        label: &'static str,
+        op: F,
+    ) -> Segment<Args, Stack::Push<R>, Env, Fallibility>
+    where
+        F: Fn() -> R + 'static,
+        R: 'static,
+    {
+        self.segment.push_label(label);
+        self.op0(op)
+    }
+
+    /// Like [`op1`](Self::op1), but records `label` for [`op_labels`](Self::op_labels).
+    pub fn op1_labeled<R, F>(
+        mut self,
+        label: &'static str,
+        op: F,
+    ) -> Segment<Args, CStackList<R, Stack::Tail>, Env, Fallibility>
+    where
+        Stack: CStackListHeadPadded,
+        F: Fn(Stack::Head) -> R + 'static,
+        R: 'static,
+    {
+        self.segment.push_label(label);
+        self.op1(op)
+    }
+
+    /// Like [`op2`](Self::op2), but records `label` for [`op_labels`](Self::op_labels).
+    pub fn op2_labeled<R, F>(
+        mut self,
+        label: &'static str,
+        op: F,
+    ) -> Segment<Args, <<Stack::Tail as List>::Tail as List>::Push<R>, Env, Fallibility>
+    where
+        Stack: CStackListHeadPadded,
+        Stack::Tail: CStackListHeadPadded,
+        F: Fn(<Stack::Tail as List>::Head, Stack::Head) -> R + 'static,
+        R: 'static,
+    {
+        self.segment.push_label(label);
+        self.op2(op)
+    }
+
+    /// Returns the labels recorded by the `*_labeled` ops, in push order, for stable textual
+    /// dumps of the segment (e.g. golden tests). Unlabeled ops are omitted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<()>::new()
+    ///     .op0_labeled("ten", || 10)
+    ///     .op1_labeled("double", |x| x * 2);
+    /// assert_eq!(segment.op_labels(), ["ten", "double"]);
+    /// ```
+    #[must_use]
+    pub fn op_labels(&self) -> Vec<&'static str> {
+        self.segment.op_labels()
+    }
+
+    /// Raises the alignment of the stack base the segment executes on to at least `align`, for
+    /// callers that need results placed at a stronger alignment than their types require (e.g.
+    /// SIMD loads from a caller-provided buffer).
+    ///
+    /// The alignment is otherwise the largest alignment of any type pushed by the segment; it is
+    /// never lowered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `align` is not a power of two.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<()>::new()
+    ///     .with_min_alignment(32)
+    ///     .op0(|| [1.0f32, 2.0, 3.0, 4.0]);
+    /// assert_eq!(segment.call(()).unwrap(), [1.0, 2.0, 3.0, 4.0]);
+    /// ```
+    #[must_use]
+    pub fn with_min_alignment(mut self, align: usize) -> Self {
+        assert!(
+            align.is_power_of_two(),
+            "alignment {align} is not a power of two"
+        );
+        self.segment.update_base_alignment(align);
+        self
+    }
+
+    /// Asserts at compile time that the stack holds the types of `Expected`, bottom to top, and
+    /// returns `self` unchanged so the assertion can sit mid-chain.
+    ///
+    /// A mismatch is a compile error; there is no runtime cost.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "debug")]
+    /// # {
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32, &str)>::new()
+    ///     .assert_stack::<(i32, &str)>()
+    ///     .op1(|s| s.len())
+    ///     .assert_stack::<(i32, usize)>();
+    /// # }
+    /// ```
+    #[cfg(any(test, feature = "debug"))]
+    #[must_use]
+    pub fn assert_stack<Expected: IntoList>(self) -> Self
+    where
+        ReverseList<Expected::Output<CNil<()>>>: SameList<Stack>,
+    {
+        self
+    }
+
     /// Executes all operations in the segment and returns the final result.
     pub(crate) fn call0<U: 'static>(&self) -> Result<U> {
         unsafe { self.segment.call0() }
     }
 
-    pub(crate) fn call1<U: 'static, A>(&self, args: A) -> Result<U> {
-        unsafe { self.segment.call1(args) }
+    pub(crate) fn call1<U: 'static, A>(&self, arg: A) -> Result<U> {
+        unsafe { self.segment.call1(arg) }
     }
 
     pub(crate) fn call2<U: 'static, A, B>(&self, args: (A, B)) -> Result<U> {
         unsafe { self.segment.call2(args) }
     }
+
+    /// Executes the segment with `args` and returns the two or three values it leaves on the
+    /// stack as a tuple, bottom value first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any op returns an error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32,)>::new()
+    ///     .op1(|x| x * 2)
+    ///     .op0(|| "done");
+    /// assert_eq!(segment.call_tuple((21,)).unwrap(), (42, "done"));
+    /// ```
+    pub fn call_tuple(&self, args: Args) -> Result<Stack::Output>
+    where
+        Args: PushArgs,
+        Stack: TupleResult,
+    {
+        let mut stack = RawStack::with_base_alignment(self.segment.base_alignment());
+        args.push_args(&mut stack);
+        unsafe {
+            self.segment.call0_stack(&mut stack)?;
+            Ok(Stack::pop_tuple(&mut stack))
+        }
+    }
+}
+
+impl<A, B, Stack, Env, Fallibility> Segment<(A, B), Stack, Env, Fallibility>
+where
+    A: Clone + 'static,
+    B: 'static,
+    Stack: List,
+    Env: 'static,
+    Fallibility: 'static,
+{
+    /// Binds the first argument to `a`, returning a segment that takes only the second.
+    ///
+    /// Each call pushes a clone of `a` beneath the supplied argument before running the ops of
+    /// `self`, so the stack they see is the same as for a call with both arguments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let subtract = Segment::<(i32, i32)>::new().op2(|a, b| a - b);
+    /// let ten_minus = subtract.curry(10);
+    /// assert_eq!(ten_minus.call((3,)).unwrap(), 7);
+    /// ```
+    pub fn curry(self, a: A) -> Segment<(B,), Stack, Env, Fallibility> {
+        let mut segment = self.segment;
+        segment.prepend_raw0_(move |stack| {
+            // Safety: a `(B,)` segment is called with exactly one `B` on the stack.
+            let b: B = unsafe { stack.pop(false) };
+            stack.push(a.clone());
+            stack.push(b);
+            Ok(())
+        });
+        segment.update_base_alignment(align_of::<A>().max(align_of::<B>()));
+        Segment {
+            segment,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<Args: IntoList + 'static, Stack: List, Env: 'static> Segment<Args, Stack, Env, Pure> {
+    /// Executes the segment with `args` and returns the final result directly, since no op of a
+    /// [`Pure`] segment can fail.
+    ///
+    /// The call runs on a stack of its own, so its ops don't count against an enclosing step
+    /// budget or deadline (see [`RawSegment::call0_limited`] and
+    /// [`RawSegment::call0_with_deadline`]), which could otherwise fail it. A pure segment runs
+    /// a fixed number of ops.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<(i32,)>::new().op0(|| 2).op2(|x, y| x * y);
+    /// assert_eq!(segment.call_infallible((21,)), 42);
+    /// ```
+    ///
+    /// A segment with a fallible op has no `call_infallible`:
+    ///
+    /// ```compile_fail
+    /// use cel_runtime::segment::*;
+    ///
+    /// let segment = Segment::<()>::new().op0r(|| Ok(42));
+    /// segment.call_infallible(());
+    /// ```
+    pub fn call_infallible<R>(&self, args: Args) -> R
+    where
+        Self: Callable<Args, Output = Result<R>>,
+    {
+        self.call(args)
+            .unwrap_or_else(|e| unreachable!("pure segment failed: {e}"))
+    }
 }
 
 // trait Fn<Args> is currently unstable - so we use a call trait as a temporary workaround.
@@ -204,7 +729,8 @@ pub trait Callable<Args> {
     fn call(&self, args: Args) -> Self::Output;
 }
 
-impl<T: DropStack + 'static> Callable<()> for Segment<(), T>
+impl<T: DropStack + 'static, Env: 'static, Fallibility: 'static> Callable<()>
+    for Segment<(), T, Env, Fallibility>
 where
     T::Tail: EmptyList + CStackListHeadLimit,
 {
@@ -214,17 +740,19 @@ where
     }
 }
 
-impl<T: DropStack + 'static, A: 'static> Callable<(A,)> for Segment<(A,), T>
+impl<T: DropStack + 'static, A: 'static, Env: 'static, Fallibility: 'static> Callable<(A,)>
+    for Segment<(A,), T, Env, Fallibility>
 where
     T::Tail: EmptyList + CStackListHeadLimit,
 {
     type Output = Result<T::Head>;
     fn call(&self, args: (A,)) -> Self::Output {
-        self.call1(args)
+        self.call1(args.0)
     }
 }
 
-impl<T: DropStack + 'static, A: 'static, B: 'static> Callable<(A, B)> for Segment<(A, B), T>
+impl<T: DropStack + 'static, A: 'static, B: 'static, Env: 'static, Fallibility: 'static>
+    Callable<(A, B)> for Segment<(A, B), T, Env, Fallibility>
 where
     T::Tail: EmptyList + CStackListHeadLimit,
 {
@@ -234,6 +762,60 @@ where
     }
 }
 
+/// An argument tuple of a supported arity, which [`Segment::into_fn`] unpacks into the
+/// parameters of a plain closure.
+pub trait UnpackArgs<R>: Sized {
+    /// The closure type taking the tuple's elements as separate parameters and returning `R`.
+    type Fn: ?Sized;
+    /// Boxes a closure that packs its parameters into a tuple and calls `callable` with it.
+    fn unpack(callable: impl Callable<Self, Output = R> + 'static) -> Box<Self::Fn>;
+}
+
+impl<R> UnpackArgs<R> for () {
+    type Fn = dyn Fn() -> R;
+    fn unpack(callable: impl Callable<(), Output = R> + 'static) -> Box<Self::Fn> {
+        Box::new(move || callable.call(()))
+    }
+}
+
+impl<A, R> UnpackArgs<R> for (A,) {
+    type Fn = dyn Fn(A) -> R;
+    fn unpack(callable: impl Callable<(A,), Output = R> + 'static) -> Box<Self::Fn> {
+        Box::new(move |a| callable.call((a,)))
+    }
+}
+
+impl<A, B, R> UnpackArgs<R> for (A, B) {
+    type Fn = dyn Fn(A, B) -> R;
+    fn unpack(callable: impl Callable<(A, B), Output = R> + 'static) -> Box<Self::Fn> {
+        Box::new(move |a, b| callable.call((a, b)))
+    }
+}
+
+impl<Args: IntoList + 'static, Stack: List + 'static, Env: 'static, Fallibility: 'static>
+    Segment<Args, Stack, Env, Fallibility>
+{
+    /// Consumes the segment, returning a boxed closure that takes the segment's arguments as
+    /// separate parameters and calls it, so the segment can be passed anywhere a closure is
+    /// expected.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use cel_runtime::segment::*;
+    ///
+    /// let add = Segment::<(i32, i32)>::new().op2(|x, y| x + y).into_fn();
+    /// assert_eq!(add(2, 3).unwrap(), 5);
+    /// ```
+    pub fn into_fn<R>(self) -> Box<Args::Fn>
+    where
+        Self: Callable<Args, Output = R>,
+        Args: UnpackArgs<R>,
+    {
+        Args::unpack(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,6 +824,119 @@ mod tests {
     use std::sync::atomic::AtomicUsize;
     use std::sync::atomic::Ordering;
 
+    #[test]
+    fn into_fn_passes_segment_as_closure() -> Result<()> {
+        fn apply_twice(f: impl Fn(i32) -> Result<i32>, x: i32) -> Result<i32> {
+            f(f(x)?)
+        }
+
+        let double = Segment::<(i32,)>::new().op1(|x| x * 2).into_fn();
+        assert_eq!(apply_twice(double, 3)?, 12);
+
+        let answer = Segment::<()>::new().op0(|| 42).into_fn();
+        assert_eq!(answer()?, 42);
+
+        let checked = Segment::<(u8, u8)>::new()
+            .op2(|x, y| x.checked_add(y))
+            .op1r(|sum: Option<u8>| sum.ok_or_else(|| anyhow::anyhow!("overflow")))
+            .into_fn();
+        assert_eq!(checked(1, 2)?, 3);
+        assert!(checked(200, 100).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn segments_are_unpin() {
+        fn assert_unpin<T: Unpin>() {}
+        assert_unpin::<Segment<(i32,)>>();
+        assert_unpin::<Segment<(std::marker::PhantomPinned,), CNil<()>, std::marker::PhantomPinned>>(
+        );
+        assert_unpin::<crate::DynSegment>();
+    }
+
+    #[test]
+    fn argument_type_ids_lists_arguments_in_order() {
+        let segment = Segment::<(u32, &str)>::new().op1(|s: &str| s.len());
+        assert_eq!(
+            segment.argument_type_ids(),
+            [TypeId::of::<u32>(), TypeId::of::<&str>()]
+        );
+        assert!(Segment::<()>::new().argument_type_ids().is_empty());
+    }
+
+    #[test]
+    fn discard_to_unit_drops_remaining_values() -> Result<()> {
+        #[derive(Clone)]
+        struct DropCounter(Arc<AtomicUsize>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let tracker = DropCounter(drops.clone());
+        let segment = Segment::<()>::new()
+            .op0(move || tracker.clone())
+            .op0(|| 42u8)
+            .discard_to_unit();
+        segment.call(())?;
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        segment.call(())?;
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn curry_binds_first_argument() -> Result<()> {
+        let add = Segment::<(i32, i32)>::new().op2(|a, b| a + b);
+        let increment = add.curry(1);
+        assert_eq!(increment.call((41,))?, 42);
+        assert_eq!(increment.call((-1,))?, 0);
+
+        let scaled = Segment::<(u8, f64)>::with_env(10.0)
+            .op2(|a: u8, b: f64| f64::from(a) + b)
+            .curry(2)
+            .op0_env(|scale: &f64| *scale)
+            .op2(|x, scale| x * scale);
+        assert_eq!(scaled.call_infallible((0.5,)), 25.0);
+        Ok(())
+    }
+
+    #[test]
+    fn curry_keeps_op_records_and_stored_values() -> Result<()> {
+        #[derive(Clone, Copy)]
+        #[repr(align(32))]
+        struct Wide(i32);
+
+        let wide = Wide(100);
+        let factor = 3u8;
+        let curried = Segment::<(i32, i32)>::new()
+            .op2_labeled("add", |a, b| a + b)
+            .op1_labeled("offset", move |x: i32| x + wide.0)
+            .op1(move |x: i32| x * i32::from(factor))
+            .curry(1);
+        assert_eq!(curried.op_labels(), ["add", "offset"]);
+        assert_eq!(curried.segment.op_records().len(), 4);
+        assert_eq!(curried.call((2,))?, 309);
+        Ok(())
+    }
+
+    #[test]
+    fn op0_env_reads_shared_environment() {
+        struct Config {
+            base: u32,
+            step: u32,
+        }
+        let segment = Segment::<()>::with_env(Config { base: 40, step: 2 })
+            .op0_env(|config| config.base)
+            .op0_env(|config| config.step)
+            .op2(|base, step| base + step);
+        assert_eq!(segment.call(()).unwrap(), 42);
+        assert_eq!(segment.call(()).unwrap(), 42);
+    }
+
     #[test]
     fn segment_from_dyn_segment() -> Result<()> {
         let mut dyn_segment = DynSegment::new::<(i32,)>();
@@ -253,6 +948,110 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn fallible_dyn_segment_converts_only_to_fallible_segment() -> Result<()> {
+        let fallible = || -> Result<DynSegment> {
+            let mut dyn_segment = DynSegment::new::<(i32,)>();
+            dyn_segment.op1r(|x: i32| Ok(x + 1))?;
+            Ok(dyn_segment)
+        };
+        assert!(Segment::<(i32,), CStackList<i32, CNil<()>>>::try_from(fallible()?).is_err());
+        let segment =
+            Segment::<(i32,), CStackList<i32, CNil<()>>, (), Fallible>::try_from(fallible()?)?;
+        assert_eq!(segment.call((41,))?, 42);
+        Ok(())
+    }
+
+    #[test]
+    fn pure_segment_call_infallible_returns_bare_value() {
+        let segment = Segment::<(i32, i32)>::new()
+            .op2(|x, y| x + y)
+            .op1(|x| x * 2);
+        let result: i32 = segment.call_infallible((20, 1));
+        assert_eq!(result, 42);
+    }
+
+    #[test]
+    fn call_infallible_ignores_enclosing_step_budget() -> Result<()> {
+        let pure = Segment::<(i32,)>::new().op1(|x| x + 1).op1(|x| x * 2);
+        let mut outer = RawSegment::new();
+        outer.push_op0(move || pure.call_infallible::<i32>((20,)));
+        // Only the outer op counts; the nested ops run outside the budget.
+        assert_eq!(unsafe { outer.call0_limited::<i32>(1) }?, 42);
+        let error = unsafe { outer.call0_limited::<i32>(0) }.unwrap_err();
+        assert_eq!(error.to_string(), "step budget exceeded");
+        Ok(())
+    }
+
+    #[test]
+    fn labeled_ops_record_labels_in_order() {
+        let segment = Segment::<(i32,)>::new()
+            .op0_labeled("two", || 2)
+            .op2_labeled("mul", |x, y| x * y)
+            .op1(|x| x + 1)
+            .op1_labeled("to_string", |x| x.to_string());
+        assert_eq!(segment.op_labels(), ["two", "mul", "to_string"]);
+        assert_eq!(segment.call_infallible((20,)), "41");
+    }
+
+    #[test]
+    fn with_min_alignment_aligns_results() {
+        let segment = Segment::<()>::new()
+            .op0(|| 1u8)
+            .with_min_alignment(64)
+            .op1(|x| [f32::from(x); 4]);
+        assert_eq!(segment.segment.base_alignment(), 64);
+
+        let mut stack = RawStack::with_base_alignment(segment.segment.base_alignment());
+        unsafe {
+            segment.segment.call0_stack(&mut stack).unwrap();
+            assert!(stack.read_at(0, |ptr| (ptr as usize).is_multiple_of(64)));
+            assert_eq!(stack.pop::<[f32; 4]>(false), [1.0; 4]);
+        }
+
+        // Never lowers the alignment computed from the pushed types.
+        let segment = Segment::<()>::new().op0(|| 1u64).with_min_alignment(1);
+        assert_eq!(segment.segment.base_alignment(), align_of::<u64>());
+    }
+
+    #[test]
+    #[should_panic(expected = "alignment 3 is not a power of two")]
+    fn with_min_alignment_rejects_non_power_of_two() {
+        let _ = Segment::<()>::new().with_min_alignment(3);
+    }
+
+    #[test]
+    fn call_tuple_returns_every_stack_value() {
+        let pair = Segment::<(i32,)>::new()
+            .op1(|x| x * 2)
+            .op0(|| String::from("answer"));
+        assert_eq!(
+            pair.call_tuple((21,)).unwrap(),
+            (42, String::from("answer"))
+        );
+
+        let triple = Segment::<()>::new().op0(|| 1u8).op0(|| 2.5f64).op0(|| 'c');
+        assert_eq!(triple.call_tuple(()).unwrap(), (1u8, 2.5, 'c'));
+    }
+
+    #[test]
+    fn then_composes_segments() {
+        let parse = Segment::<(&str,)>::new().op1r(|s| {
+            s.parse::<i32>()
+                .map_err(|e| anyhow::anyhow!("invalid integer `{s}`: {e}"))
+        });
+        let format = Segment::<(i32,)>::new()
+            .op1(|n| n * 2)
+            .op1(|n| format!("<{n}>"));
+        let segment = parse.then(format);
+
+        assert_eq!(segment.call(("21",)).unwrap(), "<42>");
+        assert_eq!(
+            segment.call(("x",)).unwrap_err().to_string(),
+            "invalid integer `x`: invalid digit found in string"
+        );
+    }
+
     #[test]
     fn unit_result() {
         let segment = Segment::new();
@@ -290,6 +1089,69 @@ mod tests {
         assert_eq!(drop_count.load(Ordering::SeqCst), 1); // The DropCounter from op0 was dropped
     }
 
+    #[test]
+    fn op0r_error_drops_mixed_alignment_values_in_lifo_order() {
+        std::thread_local! {
+            static DROPPED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+        }
+
+        struct DropString(String);
+
+        impl Drop for DropString {
+            fn drop(&mut self) {
+                DROPPED.with_borrow_mut(|log| log.push(self.0.clone()));
+            }
+        }
+
+        struct DropU8(u8);
+
+        impl Drop for DropU8 {
+            fn drop(&mut self) {
+                DROPPED.with_borrow_mut(|log| log.push(self.0.to_string()));
+            }
+        }
+
+        // `u8` under `String` forces the `String` to be padded; `u8` over `String` doesn't.
+        let segment = Segment::new()
+            .op0(|| DropU8(1))
+            .op0(|| DropString(String::from("two")))
+            .op0(|| DropU8(3))
+            .op0r(|| -> Result<u32> { Err(anyhow::anyhow!("error")) })
+            .op2(|_: DropU8, x: u32| x)
+            .op2(|_: DropString, x: u32| x)
+            .op2(|_: DropU8, x: u32| x);
+
+        let result = segment.call(());
+        assert!(matches!(result, Err(e) if e.to_string() == "error"));
+        assert_eq!(DROPPED.take(), ["3", "two", "1"]);
+    }
+
+    #[test]
+    fn op1r_error_drops_values_below_argument() {
+        let value = std::rc::Rc::new(());
+        let tracked = value.clone();
+        let segment = Segment::new()
+            .op0(move || tracked.clone())
+            .op0(|| 7u32)
+            .op1r(|_: u32| -> Result<u32> { Err(anyhow::anyhow!("error")) })
+            .op2(|_: std::rc::Rc<()>, x: u32| x);
+
+        assert!(segment.call(()).is_err());
+        assert_eq!(std::rc::Rc::strong_count(&value), 2); // `value` and the op0 capture
+    }
+
+    #[test]
+    fn op1r_error_drops_its_argument_once() {
+        let value = std::rc::Rc::new(());
+        let tracked = value.clone();
+        let segment = Segment::new()
+            .op0(move || tracked.clone())
+            .op1r(|_: std::rc::Rc<()>| -> Result<u32> { Err(anyhow::anyhow!("error")) });
+
+        assert!(segment.call(()).is_err());
+        assert_eq!(std::rc::Rc::strong_count(&value), 2); // `value` and the op0 capture
+    }
+
     #[test]
     fn binary_operation_args_of_different_types() {
         let result = Segment::new()
@@ -323,6 +1185,21 @@ mod tests {
         assert_eq!(result.unwrap(), "Length * 2 = 10");
     }
 
+    #[test]
+    fn assert_stack_after_op2() {
+        let result = Segment::<(u8, i32)>::new()
+            .assert_stack::<(u8, i32)>()
+            .op0(|| 2)
+            .assert_stack::<(u8, i32, i32)>()
+            .op2(|x: i32, y: i32| x * y)
+            .assert_stack::<(u8, i32)>()
+            .op2(|a: u8, n: i32| format!("{a}:{n}"))
+            .assert_stack::<(String,)>()
+            .call((7, 21));
+
+        assert_eq!(result.unwrap(), "7:42");
+    }
+
     #[test]
     fn call_with_args() {
         let result = Segment::new() //
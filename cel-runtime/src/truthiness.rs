@@ -0,0 +1,118 @@
+//! Truthiness of the values CEL's `?:` (elvis) operator tests: `a ?: b` keeps a truthy `a`, and
+//! otherwise evaluates to `b`.
+//!
+//! A value is falsy when it equals its type's default (`false`, zero, the empty string), and the
+//! result has the left operand's type. An `Option<T>` is truthy when it holds a value, which is
+//! unwrapped, so the result is a `T`.
+
+use crate::dyn_segment::DynSegment;
+use anyhow::{Result, anyhow};
+use std::any::TypeId;
+
+/// One type whose values `?:` can test.
+struct Truthy {
+    value: fn() -> TypeId,
+    elvis: fn(&mut DynSegment, DynSegment) -> Result<()>,
+}
+
+macro_rules! truthies {
+    ($($t:ty),+ $(,)?) => {
+        &[$(
+            Truthy {
+                value: TypeId::of::<$t>,
+                elvis: |segment, fallback| {
+                    segment.coalesce(fallback, |value: $t| {
+                        (value != <$t>::default()).then_some(value)
+                    })
+                },
+            },
+            Truthy {
+                value: TypeId::of::<Option<$t>>,
+                elvis: |segment, fallback| segment.coalesce(fallback, |value: Option<$t>| value),
+            },
+        )+]
+    };
+}
+
+/// Every type a literal or built-in operator produces, each with its optional form.
+const TRUTHIES: &[Truthy] = truthies!(
+    bool, char, String, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64,
+);
+
+/// Appends `a ?: fallback` to `segment`, where `a` is the value on top of `segment` and
+/// `fallback` is a fragment created with [`DynSegment::new_fragment`] that produces `b`.
+///
+/// # Errors
+///
+/// Returns an error if `segment` produces no value or a type with no truthiness in the table, or
+/// if `fallback` doesn't produce the result type: the type of `a`, or `T` for an `Option<T>`.
+///
+/// # Examples
+///
+/// ```
+/// use cel_runtime::{DynSegment, elvis};
+///
+/// let mut segment = DynSegment::new::<()>();
+/// segment.op0(|| 0i32);
+/// let mut fallback = segment.new_fragment();
+/// fallback.op0(|| 5i32);
+/// elvis(&mut segment, fallback).unwrap();
+/// assert_eq!(segment.call0::<i32>().unwrap(), 5);
+/// ```
+pub fn elvis(segment: &mut DynSegment, fallback: DynSegment) -> Result<()> {
+    let [info] = segment.peek_stack_infos(1) else {
+        return Err(anyhow!("`?:` requires a left operand"));
+    };
+    let id = info.type_id;
+    let truthy = TRUTHIES
+        .iter()
+        .find(|t| (t.value)() == id)
+        .ok_or_else(|| anyhow!("`?:` cannot test the truthiness of {}", info.type_name))?;
+    (truthy.elvis)(segment, fallback)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run<T: Clone + 'static, R: Clone + 'static>(value: T, fallback: R) -> Result<R> {
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(move || value.clone());
+        let mut fragment = segment.new_fragment();
+        fragment.op0(move || fallback.clone());
+        elvis(&mut segment, fragment)?;
+        segment.call0::<R>()
+    }
+
+    #[test]
+    fn falsy_values_select_the_fallback() -> Result<()> {
+        assert!(run(false, true)?);
+        assert_eq!(run(0i64, 5i64)?, 5);
+        assert_eq!(run(0.0f64, 1.5f64)?, 1.5);
+        assert_eq!(run(String::new(), String::from("b"))?, "b");
+        assert_eq!(run(None::<u8>, 7u8)?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn truthy_values_are_kept() -> Result<()> {
+        assert!(run(true, false)?);
+        assert_eq!(run(-3i32, 5i32)?, -3);
+        assert_eq!(run(String::from("a"), String::from("b"))?, "a");
+        assert_eq!(run(Some(2u8), 7u8)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_type_is_an_error() {
+        struct Opaque;
+        let mut segment = DynSegment::new::<()>();
+        segment.op0(|| Opaque);
+        let fallback = segment.new_fragment();
+        let err = elvis(&mut segment, fallback).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("`?:` cannot test the truthiness of ")
+        );
+    }
+}
@@ -3,6 +3,7 @@
 
 use typenum::{B1, Bit, Sub1, U0, UInt, Unsigned};
 
+use crate::c_stack_list::{CNil, CStackList, CStackListHeadLimit};
 use crate::list_traits::{
     EmptyList, IntoList, List, ListIndex, ListTypeIterator, ListTypeIteratorAdvance,
     ListTypeProperty,
@@ -24,6 +25,52 @@ impl<T: IntoList> IntoTupleList for T {
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Conversion to and from CStackList
+
+/// A `CStackList` converts to the tuple list with the same elements, in order.
+impl IntoTupleList for CNil<()> {
+    type Output = ();
+    fn into_tuple_list(self) -> Self::Output {}
+}
+
+/// A `CStackList` converts to the tuple list with the same elements, in order.
+impl<H: 'static, T: List + CStackListHeadLimit + IntoTupleList> IntoTupleList for CStackList<H, T> {
+    type Output = (H, T::Output);
+    fn into_tuple_list(self) -> Self::Output {
+        (self.1, self.0.into_tuple_list())
+    }
+}
+
+/// Converts a tuple list into the [`CStackList`] with the same elements, in order; the inverse of
+/// [`IntoTupleList`] for a `CStackList`.
+///
+/// The method isn't named `into_c_stack_list` because a cons cell `(H, T)` is also a 2-tuple,
+/// which [`IntoCStackList`](crate::c_stack_list::IntoCStackList) converts as a two-element list.
+pub trait TupleListIntoCStackList {
+    /// The resulting list type.
+    type Output: List;
+    /// Convert into a `CStackList` preserving element order.
+    fn tuple_list_into_c_stack_list(self) -> Self::Output;
+}
+
+impl TupleListIntoCStackList for () {
+    type Output = CNil<()>;
+    fn tuple_list_into_c_stack_list(self) -> Self::Output {
+        CNil::<()>::root_empty()
+    }
+}
+
+impl<H: 'static, T: TupleListIntoCStackList> TupleListIntoCStackList for (H, T)
+where
+    T::Output: CStackListHeadLimit,
+{
+    type Output = CStackList<H, T::Output>;
+    fn tuple_list_into_c_stack_list(self) -> Self::Output {
+        CStackList(self.1.tuple_list_into_c_stack_list(), self.0)
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // ListTypeIteratorAdvance
 
@@ -136,6 +183,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::c_stack_list::IntoCStackList;
     use crate::list_traits::TypeIdIterator;
     use std::any::TypeId;
     use typenum::{U0, U1, U2, U3};
@@ -165,6 +213,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn c_stack_list_round_trips_through_tuple_list() {
+        let tuples = (1, 2.5, "x").into_c_stack_list().into_tuple_list();
+        assert_eq!(tuples, (1, (2.5, ("x", ()))));
+        assert_eq!(
+            tuples.tuple_list_into_c_stack_list(),
+            (1, 2.5, "x").into_c_stack_list()
+        );
+
+        let tuples = (7u8, ("y", (false, ())));
+        assert_eq!(
+            tuples.tuple_list_into_c_stack_list().into_tuple_list(),
+            tuples
+        );
+    }
+
     #[test]
     fn push() {
         assert_eq!(().push_first(1), (1, ()));